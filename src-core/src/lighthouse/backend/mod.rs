@@ -0,0 +1,76 @@
+pub mod native;
+pub mod simulated;
+
+use std::time::Duration;
+
+use bluest::Uuid;
+use tokio::sync::mpsc::UnboundedSender;
+
+use super::models::LighthouseError;
+
+/// The operations a characteristic supports
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CharacteristicProperties {
+    pub read: bool,
+    pub write: bool,
+    pub write_without_response: bool,
+    pub notify: bool,
+}
+
+/// The bluetooth operations the lighthouse module depends on.
+/// Devices are referred to by the string form of their id.
+#[tonic::async_trait]
+pub trait LighthouseBackend: Send + Sync {
+    /// Waits until the adapter can be used
+    async fn wait_available(&self) -> Result<(), String>;
+
+    /// Scans for devices for the given duration, sending the id of every advertising device as it is seen.
+    /// The same device may be sent more than once.
+    async fn scan(
+        &self,
+        duration: Duration,
+        discovered: UnboundedSender<String>,
+    ) -> Result<(), String>;
+
+    /// Gets the advertised name of a device seen during a scan
+    async fn device_name(&self, device_id: &str) -> Result<String, String>;
+
+    /// Connects to a device if it isn't connected yet, and returns the uuids of its services
+    async fn connect(&self, device_id: &str) -> Result<Vec<Uuid>, LighthouseError>;
+
+    async fn disconnect(&self, device_id: &str);
+
+    async fn properties(
+        &self,
+        device_id: &str,
+        service_uuid: Uuid,
+        characteristic_uuid: Uuid,
+    ) -> Result<CharacteristicProperties, LighthouseError>;
+
+    async fn read(
+        &self,
+        device_id: &str,
+        service_uuid: Uuid,
+        characteristic_uuid: Uuid,
+    ) -> Result<Vec<u8>, LighthouseError>;
+
+    /// Subscribes to notifications of a characteristic, sending every value it notifies until the subscription ends
+    /// (for example when the device disconnects) or the receiver is dropped
+    async fn notify(
+        &self,
+        device_id: &str,
+        service_uuid: Uuid,
+        characteristic_uuid: Uuid,
+        values: UnboundedSender<Vec<u8>>,
+    ) -> Result<(), LighthouseError>;
+
+    /// Writes a value to a characteristic, waiting for the device to acknowledge it if `with_response` is set
+    async fn write(
+        &self,
+        device_id: &str,
+        service_uuid: Uuid,
+        characteristic_uuid: Uuid,
+        value: &[u8],
+        with_response: bool,
+    ) -> Result<(), LighthouseError>;
+}
@@ -0,0 +1,222 @@
+use std::{collections::HashMap, time::Duration};
+
+use bluest::{Adapter, Characteristic, Device, Service, Uuid};
+use futures_util::StreamExt;
+use tokio::{
+    sync::{mpsc::UnboundedSender, Mutex},
+    time::sleep,
+};
+
+use super::{CharacteristicProperties, LighthouseBackend};
+use crate::lighthouse::models::LighthouseError;
+
+/// Talks to real devices through the system's bluetooth adapter
+pub struct NativeBackend {
+    adapter: Adapter,
+    /// Every device seen during a scan, by id
+    devices: Mutex<HashMap<String, Device>>,
+}
+
+impl NativeBackend {
+    /// Uses the default bluetooth adapter, if there is one
+    pub async fn new() -> Option<Self> {
+        Adapter::default().await.map(|adapter| Self {
+            adapter,
+            devices: Mutex::new(HashMap::new()),
+        })
+    }
+
+    async fn get_device(&self, device_id: &str) -> Result<Device, LighthouseError> {
+        self.devices
+            .lock()
+            .await
+            .get(device_id)
+            .cloned()
+            .ok_or(LighthouseError::DeviceNotFound)
+    }
+
+    async fn get_service(
+        &self,
+        device_id: &str,
+        service_uuid: Uuid,
+    ) -> Result<Service, LighthouseError> {
+        let device = self.get_device(device_id).await?;
+        let services = device
+            .services()
+            .await
+            .map_err(|e| LighthouseError::FailedToGetServices(e.to_string()))?;
+        services
+            .into_iter()
+            .find(|service| service.uuid().eq(&service_uuid))
+            .ok_or(LighthouseError::ServiceNotFound)
+    }
+
+    async fn get_characteristic(
+        &self,
+        device_id: &str,
+        service_uuid: Uuid,
+        characteristic_uuid: Uuid,
+    ) -> Result<Characteristic, LighthouseError> {
+        let service = self.get_service(device_id, service_uuid).await?;
+        let characteristics = service
+            .characteristics()
+            .await
+            .map_err(|e| LighthouseError::FailedToGetCharacteristics(e.to_string()))?;
+        characteristics
+            .into_iter()
+            .find(|characteristic| characteristic.uuid().eq(&characteristic_uuid))
+            .ok_or(LighthouseError::CharacteristicNotFound)
+    }
+}
+
+#[tonic::async_trait]
+impl LighthouseBackend for NativeBackend {
+    async fn wait_available(&self) -> Result<(), String> {
+        self.adapter
+            .wait_available()
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    async fn scan(
+        &self,
+        duration: Duration,
+        discovered: UnboundedSender<String>,
+    ) -> Result<(), String> {
+        let mut scan = self.adapter.scan(&[]).await.map_err(|e| e.to_string())?;
+        let mut timer = Box::pin(sleep(duration));
+        loop {
+            tokio::select! {
+                _ = timer.as_mut() => {
+                    break;
+                }
+                result = scan.next() => {
+                    let Some(discovered_device) = result else {
+                        break;
+                    };
+                    let device_id = discovered_device.device.id().to_string();
+                    self.devices
+                        .lock()
+                        .await
+                        .insert(device_id.clone(), discovered_device.device);
+                    let _ = discovered.send(device_id);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn device_name(&self, device_id: &str) -> Result<String, String> {
+        let device = self
+            .get_device(device_id)
+            .await
+            .map_err(|_| String::from("Device not found"))?;
+        device.name_async().await.map_err(|e| e.to_string())
+    }
+
+    /// Devices are connected to implicitly when their services are first requested
+    async fn connect(&self, device_id: &str) -> Result<Vec<Uuid>, LighthouseError> {
+        let device = self.get_device(device_id).await?;
+        let services = device
+            .services()
+            .await
+            .map_err(|e| LighthouseError::FailedToGetServices(e.to_string()))?;
+        Ok(services.iter().map(|service| service.uuid()).collect())
+    }
+
+    async fn disconnect(&self, device_id: &str) {
+        if let Ok(device) = self.get_device(device_id).await {
+            let _ = self.adapter.disconnect_device(&device).await;
+        }
+    }
+
+    async fn properties(
+        &self,
+        device_id: &str,
+        service_uuid: Uuid,
+        characteristic_uuid: Uuid,
+    ) -> Result<CharacteristicProperties, LighthouseError> {
+        let characteristic = self
+            .get_characteristic(device_id, service_uuid, characteristic_uuid)
+            .await?;
+        let properties = characteristic
+            .properties()
+            .await
+            .map_err(|e| LighthouseError::FailedToGetCharacteristicProperties(e.to_string()))?;
+        Ok(CharacteristicProperties {
+            read: properties.read,
+            write: properties.write,
+            write_without_response: properties.write_without_response,
+            notify: properties.notify,
+        })
+    }
+
+    async fn read(
+        &self,
+        device_id: &str,
+        service_uuid: Uuid,
+        characteristic_uuid: Uuid,
+    ) -> Result<Vec<u8>, LighthouseError> {
+        let characteristic = self
+            .get_characteristic(device_id, service_uuid, characteristic_uuid)
+            .await?;
+        characteristic
+            .read()
+            .await
+            .map_err(|e| LighthouseError::FailedToReadCharacteristic(e.to_string()))
+    }
+
+    async fn notify(
+        &self,
+        device_id: &str,
+        service_uuid: Uuid,
+        characteristic_uuid: Uuid,
+        values: UnboundedSender<Vec<u8>>,
+    ) -> Result<(), LighthouseError> {
+        let characteristic = self
+            .get_characteristic(device_id, service_uuid, characteristic_uuid)
+            .await?;
+        let mut notifications = characteristic
+            .notify()
+            .await
+            .map_err(|e| LighthouseError::FailedToReadCharacteristic(e.to_string()))?;
+        loop {
+            tokio::select! {
+                _ = values.closed() => {
+                    break;
+                }
+                result = notifications.next() => {
+                    match result {
+                        Some(Ok(value)) => {
+                            let _ = values.send(value);
+                        }
+                        Some(Err(e)) => {
+                            return Err(LighthouseError::FailedToReadCharacteristic(e.to_string()));
+                        }
+                        None => break,
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn write(
+        &self,
+        device_id: &str,
+        service_uuid: Uuid,
+        characteristic_uuid: Uuid,
+        value: &[u8],
+        with_response: bool,
+    ) -> Result<(), LighthouseError> {
+        let characteristic = self
+            .get_characteristic(device_id, service_uuid, characteristic_uuid)
+            .await?;
+        let result = if with_response {
+            characteristic.write(value).await
+        } else {
+            characteristic.write_without_response(value).await
+        };
+        result.map_err(|e| LighthouseError::FailedToWriteCharacteristic(e.to_string()))
+    }
+}
@@ -0,0 +1,582 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use bluest::Uuid;
+use tokio::{
+    sync::{mpsc::UnboundedSender, Mutex},
+    time::sleep,
+};
+use tokio_util::sync::CancellationToken;
+
+use super::{CharacteristicProperties, LighthouseBackend};
+use crate::lighthouse::{
+    models::{LighthouseDeviceType, LighthouseError},
+    DEVICE_INFORMATION_SERVICE, FIRMWARE_REVISION_CHARACTERISTIC, HARDWARE_REVISION_CHARACTERISTIC,
+    LIGHTHOUSE_V1_PWR_CHARACTERISTIC, LIGHTHOUSE_V1_PWR_SERVICE,
+    LIGHTHOUSE_V2_CHANNEL_CHARACTERISTIC, LIGHTHOUSE_V2_IDENTIFY_CHARACTERISTIC,
+    LIGHTHOUSE_V2_PWR_CHARACTERISTIC, LIGHTHOUSE_V2_PWR_SERVICE,
+};
+
+/// How long a simulated V2 base station takes to boot
+const V2_BOOT_DURATION: Duration = Duration::from_secs(3);
+
+/// A base station emulated by the simulated backend
+#[derive(Debug, Clone)]
+pub struct SimulatedStation {
+    pub id: String,
+    pub name: String,
+    pub device_type: LighthouseDeviceType,
+    /// The identifier printed on the back of a V1 base station. Sleep commands with another identifier are ignored.
+    pub v1_identifier: u32,
+    pub channel: u8,
+    pub firmware_revision: String,
+    pub hardware_revision: String,
+    /// Stations out of range don't show up in scans and cannot be connected to
+    pub in_range: bool,
+}
+
+impl SimulatedStation {
+    pub fn v1(id: &str, v1_identifier: u32) -> Self {
+        Self {
+            id: id.to_string(),
+            name: format!("HTC BS {:06X}", v1_identifier & 0xffffff),
+            device_type: LighthouseDeviceType::LighthouseV1,
+            v1_identifier,
+            channel: 0,
+            firmware_revision: String::from("436"),
+            hardware_revision: String::from("1"),
+            in_range: true,
+        }
+    }
+
+    pub fn v2(id: &str, channel: u8) -> Self {
+        Self {
+            id: id.to_string(),
+            name: format!("LHB-{:08X}", 0xA0B0C000 + channel as u32),
+            device_type: LighthouseDeviceType::LighthouseV2,
+            v1_identifier: 0,
+            channel,
+            firmware_revision: String::from("1.14.1"),
+            hardware_revision: String::from("lighthouse_v2"),
+            in_range: true,
+        }
+    }
+}
+
+/// Faults the simulated backend injects, to exercise error handling and retries
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SimulatedFaults {
+    /// Fail every nth read (0 to never fail)
+    pub fail_every_nth_read: u32,
+    /// Drop the connection to a device after every nth operation on it (0 to never drop).
+    /// The next operation on that device fails, after which it is connected again.
+    pub disconnect_every_nth_operation: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum SimulatedPower {
+    /// The last command a V1 base station accepted: the subcommand and the timeout
+    V1 {
+        command: u8,
+        timeout: u16,
+    },
+    /// The power state byte of a V2 base station
+    V2(u8),
+    V2Booting(Instant),
+}
+
+struct StationState {
+    station: SimulatedStation,
+    power: SimulatedPower,
+    connected: bool,
+    /// Cancelled when the connection drops, which ends its notification subscriptions
+    connection: CancellationToken,
+    operations: u32,
+}
+
+impl StationState {
+    fn disconnect(&mut self) {
+        self.connected = false;
+        self.connection.cancel();
+        self.connection = CancellationToken::new();
+    }
+}
+
+struct Subscription {
+    device_id: String,
+    characteristic_uuid: Uuid,
+    values: UnboundedSender<Vec<u8>>,
+}
+
+struct SimulatedState {
+    stations: HashMap<String, StationState>,
+    subscriptions: Vec<Subscription>,
+    faults: SimulatedFaults,
+    reads: u32,
+}
+
+impl SimulatedState {
+    fn notify(&mut self, device_id: &str, characteristic_uuid: Uuid, value: Vec<u8>) {
+        self.subscriptions
+            .retain(|subscription| !subscription.values.is_closed());
+        for subscription in self.subscriptions.iter().filter(|subscription| {
+            subscription.device_id == device_id
+                && subscription.characteristic_uuid == characteristic_uuid
+        }) {
+            let _ = subscription.values.send(value.clone());
+        }
+    }
+}
+
+/// Emulates V1 and V2 base stations in memory, so the lighthouse module can be used without bluetooth hardware
+pub struct SimulatedBackend {
+    state: Arc<Mutex<SimulatedState>>,
+}
+
+impl SimulatedBackend {
+    pub fn new(stations: Vec<SimulatedStation>) -> Self {
+        let stations = stations
+            .into_iter()
+            .map(|station| (station.id.clone(), new_station_state(station)))
+            .collect();
+        Self {
+            state: Arc::new(Mutex::new(SimulatedState {
+                stations,
+                subscriptions: Vec::new(),
+                faults: SimulatedFaults::default(),
+                reads: 0,
+            })),
+        }
+    }
+
+    /// Two V2 base stations and a V1 base station, all powered on
+    pub fn with_default_stations() -> Self {
+        Self::new(vec![
+            SimulatedStation::v2("SIMULATED-V2-1", 1),
+            SimulatedStation::v2("SIMULATED-V2-2", 2),
+            SimulatedStation::v1("SIMULATED-V1-1", 0x12345678),
+        ])
+    }
+
+    /// Adds a station, which shows up in the next scan
+    #[cfg(test)]
+    pub async fn add_station(&self, station: SimulatedStation) {
+        self.state
+            .lock()
+            .await
+            .stations
+            .insert(station.id.clone(), new_station_state(station));
+    }
+
+    /// Moves a station in or out of range. Going out of range drops its connection.
+    #[cfg(test)]
+    pub async fn set_in_range(&self, device_id: &str, in_range: bool) {
+        if let Some(station) = self.state.lock().await.stations.get_mut(device_id) {
+            station.station.in_range = in_range;
+            if !in_range {
+                station.disconnect();
+            }
+        }
+    }
+
+    /// Sets the faults to inject from now on
+    #[cfg(test)]
+    pub async fn set_faults(&self, faults: SimulatedFaults) {
+        self.state.lock().await.faults = faults;
+    }
+
+    /// Runs an operation on a connected station, dropping the connection as configured by the faults
+    async fn with_station<T>(
+        &self,
+        device_id: &str,
+        service_uuid: Uuid,
+        characteristic_uuid: Uuid,
+        operation: impl FnOnce(
+            &mut StationState,
+            CharacteristicProperties,
+            &mut SimulatedState,
+        ) -> Result<T, LighthouseError>,
+    ) -> Result<T, LighthouseError> {
+        let mut state = self.state.lock().await;
+        let faults = state.faults;
+        let mut station = state
+            .stations
+            .remove(device_id)
+            .ok_or(LighthouseError::DeviceNotFound)?;
+        let result = (|| {
+            if !station.station.in_range {
+                return Err(LighthouseError::FailedToGetServices(String::from(
+                    "Device unreachable",
+                )));
+            }
+            if !station.connected {
+                // Reconnects for the next attempt, like the OS would
+                station.connected = true;
+                return Err(LighthouseError::FailedToGetServices(String::from(
+                    "Device disconnected",
+                )));
+            }
+            let properties =
+                characteristic_properties(&station, service_uuid, characteristic_uuid)?;
+            station.operations += 1;
+            let result = operation(&mut station, properties, &mut state);
+            if faults.disconnect_every_nth_operation > 0
+                && station.operations % faults.disconnect_every_nth_operation == 0
+            {
+                station.disconnect();
+            }
+            result
+        })();
+        state.stations.insert(device_id.to_string(), station);
+        result
+    }
+}
+
+fn new_station_state(station: SimulatedStation) -> StationState {
+    let power = match station.device_type {
+        LighthouseDeviceType::LighthouseV1 => SimulatedPower::V1 {
+            command: 0x00,
+            timeout: 0,
+        },
+        LighthouseDeviceType::LighthouseV2 => SimulatedPower::V2(0x0b),
+    };
+    StationState {
+        station,
+        power,
+        connected: false,
+        connection: CancellationToken::new(),
+        operations: 0,
+    }
+}
+
+fn services(device_type: &LighthouseDeviceType) -> Vec<Uuid> {
+    match device_type {
+        LighthouseDeviceType::LighthouseV1 => {
+            vec![LIGHTHOUSE_V1_PWR_SERVICE, DEVICE_INFORMATION_SERVICE]
+        }
+        LighthouseDeviceType::LighthouseV2 => {
+            vec![LIGHTHOUSE_V2_PWR_SERVICE, DEVICE_INFORMATION_SERVICE]
+        }
+    }
+}
+
+fn characteristic_properties(
+    station: &StationState,
+    service_uuid: Uuid,
+    characteristic_uuid: Uuid,
+) -> Result<CharacteristicProperties, LighthouseError> {
+    if !services(&station.station.device_type).contains(&service_uuid) {
+        return Err(LighthouseError::ServiceNotFound);
+    }
+    let read_only = CharacteristicProperties {
+        read: true,
+        ..Default::default()
+    };
+    match (service_uuid, characteristic_uuid) {
+        (LIGHTHOUSE_V1_PWR_SERVICE, LIGHTHOUSE_V1_PWR_CHARACTERISTIC) => {
+            Ok(CharacteristicProperties {
+                read: true,
+                write: true,
+                ..Default::default()
+            })
+        }
+        (LIGHTHOUSE_V2_PWR_SERVICE, LIGHTHOUSE_V2_PWR_CHARACTERISTIC) => {
+            Ok(CharacteristicProperties {
+                read: true,
+                write: true,
+                write_without_response: true,
+                notify: true,
+            })
+        }
+        (LIGHTHOUSE_V2_PWR_SERVICE, LIGHTHOUSE_V2_CHANNEL_CHARACTERISTIC) => {
+            Ok(CharacteristicProperties {
+                read: true,
+                write: true,
+                ..Default::default()
+            })
+        }
+        (LIGHTHOUSE_V2_PWR_SERVICE, LIGHTHOUSE_V2_IDENTIFY_CHARACTERISTIC) => {
+            Ok(CharacteristicProperties {
+                write: true,
+                ..Default::default()
+            })
+        }
+        (DEVICE_INFORMATION_SERVICE, FIRMWARE_REVISION_CHARACTERISTIC)
+        | (DEVICE_INFORMATION_SERVICE, HARDWARE_REVISION_CHARACTERISTIC) => Ok(read_only),
+        _ => Err(LighthouseError::CharacteristicNotFound),
+    }
+}
+
+fn read_value(station: &mut StationState, characteristic_uuid: Uuid) -> Vec<u8> {
+    match characteristic_uuid {
+        LIGHTHOUSE_V1_PWR_CHARACTERISTIC => match station.power {
+            SimulatedPower::V1 { command, timeout } => {
+                let timeout = timeout.to_be_bytes();
+                vec![0x12, command, timeout[0], timeout[1]]
+            }
+            _ => vec![],
+        },
+        LIGHTHOUSE_V2_PWR_CHARACTERISTIC => {
+            if let SimulatedPower::V2Booting(since) = station.power {
+                if since.elapsed() >= V2_BOOT_DURATION {
+                    station.power = SimulatedPower::V2(0x0b);
+                }
+            }
+            match station.power {
+                SimulatedPower::V2(state) => vec![state],
+                SimulatedPower::V2Booting(_) => vec![0x01],
+                _ => vec![],
+            }
+        }
+        LIGHTHOUSE_V2_CHANNEL_CHARACTERISTIC => vec![station.station.channel],
+        FIRMWARE_REVISION_CHARACTERISTIC => station.station.firmware_revision.as_bytes().to_vec(),
+        HARDWARE_REVISION_CHARACTERISTIC => station.station.hardware_revision.as_bytes().to_vec(),
+        _ => vec![],
+    }
+}
+
+fn write_value(
+    station: &mut StationState,
+    characteristic_uuid: Uuid,
+    value: &[u8],
+) -> Result<(), LighthouseError> {
+    let invalid = || LighthouseError::FailedToWriteCharacteristic(String::from("Invalid value"));
+    match characteristic_uuid {
+        LIGHTHOUSE_V1_PWR_CHARACTERISTIC => {
+            if value.len() < 8 || value[0] != 0x12 {
+                return Err(invalid());
+            }
+            let command = value[1];
+            let timeout = u16::from_be_bytes([value[2], value[3]]);
+            let identifier = u32::from_le_bytes([value[4], value[5], value[6], value[7]]);
+            match command {
+                // Wake up, and stay on
+                0x00 => station.power = SimulatedPower::V1 { command, timeout },
+                // Sleep after the default timeout, accepted by any station
+                0x01 => station.power = SimulatedPower::V1 { command, timeout },
+                // Sleep, only accepted with the station's own identifier
+                0x02 if identifier == station.station.v1_identifier => {
+                    station.power = SimulatedPower::V1 { command, timeout }
+                }
+                0x02 => {}
+                _ => return Err(invalid()),
+            }
+        }
+        LIGHTHOUSE_V2_PWR_CHARACTERISTIC => match value.first() {
+            Some(0x00) => station.power = SimulatedPower::V2(0x00),
+            Some(0x02) => station.power = SimulatedPower::V2(0x02),
+            Some(0x01) => {
+                if station.power != SimulatedPower::V2(0x0b) {
+                    station.power = SimulatedPower::V2Booting(Instant::now());
+                }
+            }
+            _ => return Err(invalid()),
+        },
+        LIGHTHOUSE_V2_CHANNEL_CHARACTERISTIC => match value.first() {
+            Some(channel) => station.station.channel = *channel,
+            None => return Err(invalid()),
+        },
+        LIGHTHOUSE_V2_IDENTIFY_CHARACTERISTIC => {}
+        _ => return Err(invalid()),
+    }
+    Ok(())
+}
+
+#[tonic::async_trait]
+impl LighthouseBackend for SimulatedBackend {
+    async fn wait_available(&self) -> Result<(), String> {
+        Ok(())
+    }
+
+    async fn scan(
+        &self,
+        duration: Duration,
+        discovered: UnboundedSender<String>,
+    ) -> Result<(), String> {
+        let device_ids: Vec<String> = self
+            .state
+            .lock()
+            .await
+            .stations
+            .values()
+            .filter(|station| station.station.in_range)
+            .map(|station| station.station.id.clone())
+            .collect();
+        for device_id in device_ids {
+            let _ = discovered.send(device_id);
+        }
+        sleep(duration).await;
+        Ok(())
+    }
+
+    async fn device_name(&self, device_id: &str) -> Result<String, String> {
+        self.state
+            .lock()
+            .await
+            .stations
+            .get(device_id)
+            .filter(|station| station.station.in_range)
+            .map(|station| station.station.name.clone())
+            .ok_or(String::from("Device not found"))
+    }
+
+    async fn connect(&self, device_id: &str) -> Result<Vec<Uuid>, LighthouseError> {
+        let mut state = self.state.lock().await;
+        let station = state
+            .stations
+            .get_mut(device_id)
+            .ok_or(LighthouseError::DeviceNotFound)?;
+        if !station.station.in_range {
+            return Err(LighthouseError::FailedToGetServices(String::from(
+                "Device unreachable",
+            )));
+        }
+        station.connected = true;
+        Ok(services(&station.station.device_type))
+    }
+
+    async fn disconnect(&self, device_id: &str) {
+        if let Some(station) = self.state.lock().await.stations.get_mut(device_id) {
+            station.disconnect();
+        }
+    }
+
+    async fn properties(
+        &self,
+        device_id: &str,
+        service_uuid: Uuid,
+        characteristic_uuid: Uuid,
+    ) -> Result<CharacteristicProperties, LighthouseError> {
+        self.with_station(
+            device_id,
+            service_uuid,
+            characteristic_uuid,
+            |_, properties, _| Ok(properties),
+        )
+        .await
+    }
+
+    async fn read(
+        &self,
+        device_id: &str,
+        service_uuid: Uuid,
+        characteristic_uuid: Uuid,
+    ) -> Result<Vec<u8>, LighthouseError> {
+        self.with_station(
+            device_id,
+            service_uuid,
+            characteristic_uuid,
+            |station, properties, state| {
+                if !properties.read {
+                    return Err(LighthouseError::FailedToReadCharacteristic(String::from(
+                        "Read not permitted",
+                    )));
+                }
+                state.reads += 1;
+                let every = state.faults.fail_every_nth_read;
+                if every > 0 && state.reads % every == 0 {
+                    return Err(LighthouseError::FailedToReadCharacteristic(String::from(
+                        "Simulated read failure",
+                    )));
+                }
+                Ok(read_value(station, characteristic_uuid))
+            },
+        )
+        .await
+    }
+
+    async fn notify(
+        &self,
+        device_id: &str,
+        service_uuid: Uuid,
+        characteristic_uuid: Uuid,
+        values: UnboundedSender<Vec<u8>>,
+    ) -> Result<(), LighthouseError> {
+        let connection = self
+            .with_station(
+                device_id,
+                service_uuid,
+                characteristic_uuid,
+                |station, properties, state| {
+                    if !properties.notify {
+                        return Err(LighthouseError::FailedToReadCharacteristic(String::from(
+                            "Notify not permitted",
+                        )));
+                    }
+                    state.subscriptions.push(Subscription {
+                        device_id: station.station.id.clone(),
+                        characteristic_uuid,
+                        values: values.clone(),
+                    });
+                    Ok(station.connection.clone())
+                },
+            )
+            .await?;
+        tokio::select! {
+            _ = connection.cancelled() => {}
+            _ = values.closed() => {}
+        }
+        self.state
+            .lock()
+            .await
+            .subscriptions
+            .retain(|subscription| !subscription.values.same_channel(&values));
+        Ok(())
+    }
+
+    async fn write(
+        &self,
+        device_id: &str,
+        service_uuid: Uuid,
+        characteristic_uuid: Uuid,
+        value: &[u8],
+        with_response: bool,
+    ) -> Result<(), LighthouseError> {
+        let booting = self
+            .with_station(
+                device_id,
+                service_uuid,
+                characteristic_uuid,
+                |station, properties, state| {
+                    let permitted = if with_response {
+                        properties.write
+                    } else {
+                        properties.write_without_response
+                    };
+                    if !permitted {
+                        return Err(LighthouseError::FailedToWriteCharacteristic(String::from(
+                            "Write not permitted",
+                        )));
+                    }
+                    write_value(station, characteristic_uuid, value)?;
+                    if characteristic_uuid == LIGHTHOUSE_V2_PWR_CHARACTERISTIC {
+                        let value = read_value(station, characteristic_uuid);
+                        state.notify(device_id, characteristic_uuid, value);
+                    }
+                    Ok(matches!(station.power, SimulatedPower::V2Booting(_)))
+                },
+            )
+            .await?;
+        // Finish booting after a while, notifying subscribers of the new state
+        if booting {
+            let state = self.state.clone();
+            let device_id = device_id.to_string();
+            tokio::spawn(async move {
+                sleep(V2_BOOT_DURATION).await;
+                let mut state = state.lock().await;
+                let Some(station) = state.stations.get_mut(&device_id) else {
+                    return;
+                };
+                if !matches!(station.power, SimulatedPower::V2Booting(_)) {
+                    return;
+                }
+                let value = read_value(station, LIGHTHOUSE_V2_PWR_CHARACTERISTIC);
+                state.notify(&device_id, LIGHTHOUSE_V2_PWR_CHARACTERISTIC, value);
+            });
+        }
+        Ok(())
+    }
+}
@@ -1,6 +1,10 @@
-use std::time::Duration;
+use std::{collections::HashMap, time::Duration};
 
-use super::models::{LighthouseDeviceModel, LighthouseError};
+use super::models::{
+    LighthouseDeviceInfo, LighthouseDeviceModel, LighthouseDevicePowerResult, LighthouseError,
+    LighthouseGroup, LighthouseMonitorConfig, LighthouseOpenVRPowerRule, LighthousePowerState,
+    LighthouseV1DeviceConfig,
+};
 
 #[tauri::command]
 #[oyasumivr_macros::command_profiling]
@@ -33,6 +37,22 @@ pub async fn lighthouse_get_device_power_state(
     super::get_device_power_state(device_id).await
 }
 
+/// Makes a V2 base station blink its status LED
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn lighthouse_identify_device(device_id: String) -> Result<(), LighthouseError> {
+    super::identify_device(device_id).await
+}
+
+/// Reads the channel, firmware revision and hardware revision of a device again
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn lighthouse_refresh_device_info(
+    device_id: String,
+) -> Result<LighthouseDeviceInfo, LighthouseError> {
+    super::refresh_device_info(device_id).await
+}
+
 #[tauri::command]
 #[oyasumivr_macros::command_profiling]
 pub async fn lighthouse_get_status() -> super::models::LighthouseStatus {
@@ -50,3 +70,76 @@ pub async fn lighthouse_get_scanning_status() -> bool {
 pub async fn lighthouse_reset() {
     super::reset().await
 }
+
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn lighthouse_set_groups(groups: Vec<LighthouseGroup>) {
+    super::groups::set_groups(groups).await
+}
+
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn lighthouse_get_groups() -> Vec<LighthouseGroup> {
+    super::groups::get_groups().await
+}
+
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn lighthouse_set_monitor_config(config: LighthouseMonitorConfig) {
+    super::monitor::set_config(config).await
+}
+
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn lighthouse_get_monitor_config() -> LighthouseMonitorConfig {
+    super::monitor::get_config().await
+}
+
+/// Gets the stored configurations of V1 base stations, by device id
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn lighthouse_get_v1_configs() -> HashMap<String, LighthouseV1DeviceConfig> {
+    super::v1::get_configs().await
+}
+
+/// Stores the configuration of a V1 base station, or removes it if none is given
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn lighthouse_set_v1_config(
+    device_id: String,
+    config: Option<LighthouseV1DeviceConfig>,
+) -> Result<(), LighthouseError> {
+    super::v1::set_config(device_id, config).await
+}
+
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn lighthouse_set_power_state_batch(
+    device_ids: Vec<String>,
+    power_state: LighthousePowerState,
+    v1_timeout: Option<u16>,
+) -> Vec<LighthouseDevicePowerResult> {
+    super::groups::set_power_state_batch(None, device_ids, power_state, v1_timeout).await
+}
+
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn lighthouse_set_group_power_state(
+    group_id: String,
+    power_state: LighthousePowerState,
+    v1_timeout: Option<u16>,
+) -> Result<Vec<LighthouseDevicePowerResult>, LighthouseError> {
+    super::groups::set_group_power_state(group_id, power_state, v1_timeout).await
+}
+
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn lighthouse_set_openvr_power_rules(rules: Vec<LighthouseOpenVRPowerRule>) {
+    super::groups::set_openvr_power_rules(rules).await
+}
+
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn lighthouse_get_openvr_power_rules() -> Vec<LighthouseOpenVRPowerRule> {
+    super::groups::get_openvr_power_rules().await
+}
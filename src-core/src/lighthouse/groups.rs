@@ -0,0 +1,249 @@
+use std::{sync::LazyLock, time::Duration};
+
+use futures_util::StreamExt;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::utils::{get_data_file_path, send_event};
+
+use super::models::{
+    LighthouseBatchPowerCompletedEvent, LighthouseDevicePowerResult, LighthouseError,
+    LighthouseGroup, LighthouseOpenVRPowerRule, LighthousePowerState,
+};
+
+/// How many devices are sent power commands at the same time.
+/// Most bluetooth adapters struggle with more simultaneous connections than this.
+const MAX_CONCURRENT_POWER_COMMANDS: usize = 3;
+const MAX_POWER_COMMAND_ATTEMPTS: u32 = 3;
+const POWER_COMMAND_RETRY_DELAY: Duration = Duration::from_millis(500);
+static EVENT_BATCH_POWER_COMPLETED: &str = "LIGHTHOUSE_BATCH_POWER_COMPLETED";
+const CONFIG_FILE_NAME: &str = "lighthouse_groups.json";
+
+static GROUPS: LazyLock<Mutex<Vec<LighthouseGroup>>> = LazyLock::new(Default::default);
+static OPENVR_POWER_RULES: LazyLock<Mutex<Vec<LighthouseOpenVRPowerRule>>> =
+    LazyLock::new(Default::default);
+
+/// The groups and power rules, as stored on disk
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredGroups {
+    groups: Vec<LighthouseGroup>,
+    openvr_power_rules: Vec<LighthouseOpenVRPowerRule>,
+}
+
+/// A change in the status of OpenVR that power rules can respond to
+pub enum OpenVRTransition {
+    Started,
+    Stopped,
+}
+
+/// Loads the stored groups and power rules, so the rules apply without the UI having to set them first
+pub async fn load() {
+    let Some(path) = get_data_file_path(CONFIG_FILE_NAME).await else {
+        return;
+    };
+    let Ok(contents) = tokio::fs::read_to_string(&path).await else {
+        return;
+    };
+    match serde_json::from_str::<StoredGroups>(&contents) {
+        Ok(stored) => {
+            *GROUPS.lock().await = stored.groups;
+            *OPENVR_POWER_RULES.lock().await = stored.openvr_power_rules;
+        }
+        Err(e) => warn!("[Core] Could not read lighthouse groups: {e}"),
+    }
+}
+
+async fn save() {
+    let stored = StoredGroups {
+        groups: GROUPS.lock().await.clone(),
+        openvr_power_rules: OPENVR_POWER_RULES.lock().await.clone(),
+    };
+    let Some(path) = get_data_file_path(CONFIG_FILE_NAME).await else {
+        return;
+    };
+    if let Err(e) = tokio::fs::write(&path, serde_json::to_string(&stored).unwrap()).await {
+        error!("[Core] Could not write lighthouse groups: {e}");
+    }
+}
+
+pub async fn set_groups(groups: Vec<LighthouseGroup>) {
+    *GROUPS.lock().await = groups;
+    save().await;
+}
+
+pub async fn get_groups() -> Vec<LighthouseGroup> {
+    GROUPS.lock().await.clone()
+}
+
+pub async fn set_openvr_power_rules(rules: Vec<LighthouseOpenVRPowerRule>) {
+    *OPENVR_POWER_RULES.lock().await = rules;
+    save().await;
+}
+
+pub async fn get_openvr_power_rules() -> Vec<LighthouseOpenVRPowerRule> {
+    OPENVR_POWER_RULES.lock().await.clone()
+}
+
+/// Sets the power state of all devices in a group
+pub async fn set_group_power_state(
+    group_id: String,
+    state: LighthousePowerState,
+    v1_timeout: Option<u16>,
+) -> Result<Vec<LighthouseDevicePowerResult>, LighthouseError> {
+    let device_ids = GROUPS
+        .lock()
+        .await
+        .iter()
+        .find(|group| group.id == group_id)
+        .map(|group| group.device_ids.clone())
+        .ok_or(LighthouseError::GroupNotFound)?;
+    Ok(set_power_state_batch(Some(group_id), device_ids, state, v1_timeout).await)
+}
+
+/// Sets the power state of multiple devices, a few at a time, retrying failed attempts.
+/// Returns the result for every device, in the order they were given.
+pub async fn set_power_state_batch(
+    group_id: Option<String>,
+    device_ids: Vec<String>,
+    state: LighthousePowerState,
+    v1_timeout: Option<u16>,
+) -> Vec<LighthouseDevicePowerResult> {
+    let mut results: Vec<(usize, LighthouseDevicePowerResult)> =
+        futures_util::stream::iter(device_ids.into_iter().enumerate())
+            .map(|(i, device_id)| {
+                let state = state.clone();
+                async move {
+                    (
+                        i,
+                        set_power_state_with_retries(device_id, state, v1_timeout).await,
+                    )
+                }
+            })
+            .buffer_unordered(MAX_CONCURRENT_POWER_COMMANDS)
+            .collect()
+            .await;
+    results.sort_by_key(|(i, _)| *i);
+    let results: Vec<LighthouseDevicePowerResult> =
+        results.into_iter().map(|(_, result)| result).collect();
+    let failed = results.iter().filter(|result| !result.success).count();
+    if failed > 0 {
+        warn!(
+            "[Core] Failed to set power state of {failed} out of {} lighthouse devices",
+            results.len()
+        );
+    }
+    send_event(
+        EVENT_BATCH_POWER_COMPLETED,
+        LighthouseBatchPowerCompletedEvent {
+            group_id,
+            power_state: state,
+            results: &results,
+        },
+    )
+    .await;
+    results
+}
+
+async fn set_power_state_with_retries(
+    device_id: String,
+    state: LighthousePowerState,
+    v1_timeout: Option<u16>,
+) -> LighthouseDevicePowerResult {
+    let mut attempts = 0;
+    let error = loop {
+        attempts += 1;
+        let error = match set_power_state(&device_id, state.clone(), v1_timeout).await {
+            Ok(()) => break None,
+            Err(error) => error,
+        };
+        // Retrying won't help if the device is unknown, or its identifier is missing or wrong
+        let retryable = !matches!(
+            error,
+            LighthouseError::DeviceNotFound
+                | LighthouseError::MissingV1Identifier
+                | LighthouseError::InvalidV1Identifier
+                | LighthouseError::V1IdentifierRejected
+        );
+        if !retryable || attempts >= MAX_POWER_COMMAND_ATTEMPTS {
+            break Some(error);
+        }
+        tokio::time::sleep(POWER_COMMAND_RETRY_DELAY).await;
+    };
+    LighthouseDevicePowerResult {
+        device_id,
+        success: error.is_none(),
+        attempts,
+        error,
+    }
+}
+
+async fn set_power_state(
+    device_id: &str,
+    state: LighthousePowerState,
+    v1_timeout: Option<u16>,
+) -> Result<(), LighthouseError> {
+    // V1 base stations are controlled with their stored identifier
+    super::set_device_power_state(device_id.to_string(), state, v1_timeout, None).await
+}
+
+/// Applies the power rules for the given OpenVR transition, in the background.
+/// Devices that are already in (or on their way to) the requested state are left alone.
+pub async fn on_openvr_transition(transition: OpenVRTransition) {
+    let rules = OPENVR_POWER_RULES.lock().await.clone();
+    for rule in rules {
+        let state = match transition {
+            OpenVRTransition::Started => rule.on_start,
+            OpenVRTransition::Stopped => rule.on_stop,
+        };
+        let Some(state) = state else {
+            continue;
+        };
+        let Some(device_ids) = GROUPS
+            .lock()
+            .await
+            .iter()
+            .find(|group| group.id == rule.group_id)
+            .map(|group| group.device_ids.clone())
+        else {
+            warn!(
+                "[Core] Could not apply OpenVR power rule for unknown lighthouse group {}",
+                rule.group_id
+            );
+            continue;
+        };
+        let mut applicable_device_ids = Vec::new();
+        {
+            let power_states = super::LIGHTHOUSE_DEVICE_POWER_STATES.lock().await;
+            for device_id in device_ids {
+                let applicable = matches!(
+                    (&state, power_states.get(&device_id)),
+                    (
+                        LighthousePowerState::On,
+                        Some(LighthousePowerState::Sleep | LighthousePowerState::Standby),
+                    ) | (
+                        LighthousePowerState::Sleep | LighthousePowerState::Standby,
+                        Some(LighthousePowerState::On | LighthousePowerState::Booting),
+                    )
+                );
+                if applicable {
+                    applicable_device_ids.push(device_id);
+                }
+            }
+        }
+        if applicable_device_ids.is_empty() {
+            continue;
+        }
+        info!(
+            "[Core] Setting power state of lighthouse group {} to {state:?} for OpenVR status change",
+            rule.group_id
+        );
+        tokio::spawn(set_power_state_batch(
+            Some(rule.group_id),
+            applicable_device_ids,
+            state,
+            rule.v1_timeout,
+        ));
+    }
+}
@@ -1,5 +1,11 @@
+pub mod backend;
 pub mod commands;
+pub mod groups;
 pub mod models;
+pub mod monitor;
+#[cfg(test)]
+mod tests;
+pub mod v1;
 
 use std::{
     collections::{HashMap, HashSet},
@@ -7,18 +13,22 @@ use std::{
     time::Duration,
 };
 
-use bluest::{Adapter, Characteristic, Device, DeviceId, Service, Uuid};
-use futures_util::StreamExt;
+use backend::{native::NativeBackend, simulated::SimulatedBackend, LighthouseBackend};
+use bluest::Uuid;
 use log::{debug, error, info, trace, warn};
 use models::LighthouseDevice;
-use tokio::{sync::Mutex, time::sleep};
+use tokio::{
+    sync::{mpsc, Mutex},
+    time::sleep,
+};
 
-use crate::utils::send_event;
+use crate::{globals::is_flag_set, utils::send_event};
 
 use self::models::{
-    LighthouseDeviceDiscoveredEvent, LighthouseDeviceModel, LighthouseDevicePowerStateChangedEvent,
-    LighthouseDeviceType, LighthouseError, LighthousePowerState,
-    LighthouseScanningStatusChangedEvent, LighthouseStatus, LighthouseStatusChangedEvent,
+    LighthouseDeviceDiscoveredEvent, LighthouseDeviceInfo, LighthouseDeviceInfoChangedEvent,
+    LighthouseDeviceModel, LighthouseDevicePowerStateChangedEvent, LighthouseDeviceType,
+    LighthouseError, LighthousePowerState, LighthouseScanningStatusChangedEvent, LighthouseStatus,
+    LighthouseStatusChangedEvent,
 };
 
 const LIGHTHOUSE_V1_PWR_SERVICE: Uuid = Uuid::from_u128(0x0000CB00_0000_1000_8000_00805F9B34FB);
@@ -27,13 +37,25 @@ const LIGHTHOUSE_V1_PWR_CHARACTERISTIC: Uuid =
 const LIGHTHOUSE_V2_PWR_SERVICE: Uuid = Uuid::from_u128(0x00001523_1212_EFDE_1523_785FEABCD124);
 const LIGHTHOUSE_V2_PWR_CHARACTERISTIC: Uuid =
     Uuid::from_u128(0x00001525_1212_EFDE_1523_785FEABCD124);
+/// How often, and how long apart, the state of a V1 base station is read to confirm it accepted a sleep command
+const V1_CONFIRMATION_ATTEMPTS: u32 = 3;
+const V1_CONFIRMATION_INTERVAL: Duration = Duration::from_secs(1);
 static EVENT_STATUS_CHANGED: &str = "LIGHTHOUSE_STATUS_CHANGED";
 static EVENT_SCANNING_STATUS_CHANGED: &str = "LIGHTHOUSE_SCANNING_STATUS_CHANGED";
 static EVENT_DEVICE_DISCOVERED: &str = "LIGHTHOUSE_DEVICE_DISCOVERED";
 static EVENT_DEVICE_POWER_STATE_CHANGED: &str = "LIGHTHOUSE_DEVICE_POWER_STATE_CHANGED";
+static EVENT_DEVICE_INFO_CHANGED: &str = "LIGHTHOUSE_DEVICE_INFO_CHANGED";
 
-// const LIGHTHOUSE_V2_IDENTIFY_CHARACTERISTIC: Uuid =
-//     Uuid::from_u128(0x00008421_1212_EFDE_1523_785FEABCD124);
+const LIGHTHOUSE_V2_IDENTIFY_CHARACTERISTIC: Uuid =
+    Uuid::from_u128(0x00008421_1212_EFDE_1523_785FEABCD124);
+const LIGHTHOUSE_V2_CHANNEL_CHARACTERISTIC: Uuid =
+    Uuid::from_u128(0x00001524_1212_EFDE_1523_785FEABCD124);
+// Standard bluetooth device information service
+const DEVICE_INFORMATION_SERVICE: Uuid = Uuid::from_u128(0x0000180A_0000_1000_8000_00805F9B34FB);
+const FIRMWARE_REVISION_CHARACTERISTIC: Uuid =
+    Uuid::from_u128(0x00002A26_0000_1000_8000_00805F9B34FB);
+const HARDWARE_REVISION_CHARACTERISTIC: Uuid =
+    Uuid::from_u128(0x00002A27_0000_1000_8000_00805F9B34FB);
 
 static LIGHTHOUSE_DEVICES: LazyLock<Arc<Mutex<Vec<LighthouseDevice>>>> =
     LazyLock::new(|| Arc::new(Mutex::new(Vec::new())));
@@ -41,51 +63,48 @@ static LIGHTHOUSE_DEVICE_POWER_STATES: LazyLock<Mutex<HashMap<String, Lighthouse
     LazyLock::new(|| Mutex::new(HashMap::new()));
 static LIGHTHOUSE_DEVICE_V1_TIMEOUTS: LazyLock<Mutex<HashMap<String, u16>>> =
     LazyLock::new(|| Mutex::new(HashMap::new()));
+static LIGHTHOUSE_DEVICE_INFO: LazyLock<Mutex<HashMap<String, LighthouseDeviceInfo>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
 static SCANNING: LazyLock<Mutex<bool>> = LazyLock::new(|| Mutex::new(false));
-static ADAPTER: LazyLock<Mutex<Option<Adapter>>> = LazyLock::new(Mutex::default);
+static BACKEND: LazyLock<Mutex<Option<Arc<dyn LighthouseBackend>>>> = LazyLock::new(Mutex::default);
 static STATUS: LazyLock<Mutex<LighthouseStatus>> =
     LazyLock::new(|| Mutex::new(LighthouseStatus::Uninitialized));
-static PROCESSING_DEVICES: LazyLock<Mutex<HashSet<DeviceId>>> =
+static PROCESSING_DEVICES: LazyLock<Mutex<HashSet<String>>> =
     LazyLock::new(|| Mutex::new(HashSet::new()));
 
 pub async fn init() {
-    // Initialize adapter
-    {
-        let adapter = Adapter::default().await;
-        if adapter.is_none() {
+    // Load the stored groups, power rules and monitor configuration
+    groups::load().await;
+    monitor::load().await;
+    // Initialize the backend
+    if is_flag_set("SIMULATE_LIGHTHOUSES").await {
+        info!("[Core] Using simulated lighthouse devices");
+        init_with_backend(Arc::new(SimulatedBackend::with_default_stations())).await;
+        return;
+    }
+    match NativeBackend::new().await {
+        Some(backend) => init_with_backend(Arc::new(backend)).await,
+        None => {
             set_lighthouse_status(LighthouseStatus::NoAdapter).await;
             warn!("[Core] No bluetooth adapter was found. Disabling lighthouse module.");
-            return;
         }
-        *ADAPTER.lock().await = adapter;
     }
+}
+
+/// Initializes the lighthouse module with the given bluetooth backend
+pub async fn init_with_backend(backend: Arc<dyn LighthouseBackend>) {
+    *BACKEND.lock().await = Some(backend);
     set_lighthouse_status(LighthouseStatus::Ready).await;
-    // Poll the status of connected lighthouses every few seconds in a separate task
-    tokio::spawn(async move {
-        loop {
-            sleep(Duration::from_secs(2)).await;
-            let devices_guard = LIGHTHOUSE_DEVICES.lock().await;
-            let devices = devices_guard.clone();
-            drop(devices_guard);
-            for d in devices.iter() {
-                let _ = get_device_power_state(d.bt_device.id().to_string()).await;
-            }
-        }
-    });
 }
 
 pub async fn start_scan(duration: Duration) {
-    // Get the adapter
-    let adapter_guard = ADAPTER.lock().await;
-    let adapter = match adapter_guard.as_ref() {
-        Some(adapter) => adapter,
-        None => {
-            // No bluetooth adapter was found, we stop here
-            return;
-        }
+    // Get the backend
+    let Some(backend) = get_backend().await else {
+        // No bluetooth adapter was found, we stop here
+        return;
     };
     // Wait until the adapter is available
-    if let Err(e) = adapter.wait_available().await {
+    if let Err(e) = backend.wait_available().await {
         warn!("[Core] Failed to wait for bluetooth adapter to become available: {e}");
         set_scanning_status(false).await;
         return;
@@ -98,30 +117,16 @@ pub async fn start_scan(duration: Duration) {
         }
     }
     set_scanning_status(true).await;
-    // Start the scan
-    let mut scan = match adapter.scan(&[]).await {
-        Ok(scan) => scan,
-        Err(err) => {
-            warn!("[Core] Failed to scan for lighthouse devices: {err}");
-            set_scanning_status(false).await;
-            return;
-        }
-    };
-    // Listen for scan scan results
-    let mut timer = Box::pin(sleep(duration));
-    loop {
-        tokio::select! {
-            _ = timer.as_mut() => {
-                break;
-            }
-            result = scan.next() => {
-                if let Some(discovered_device) = result {
-                    tokio::spawn(handle_discovered_device(discovered_device.device));
-                } else {
-                    break;
-                }
-            }
+    // Handle scan results as they come in
+    let (discovered_tx, mut discovered_rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        while let Some(device_id) = discovered_rx.recv().await {
+            tokio::spawn(handle_discovered_device(device_id));
         }
+    });
+    // Scan for the given duration
+    if let Err(err) = backend.scan(duration, discovered_tx).await {
+        warn!("[Core] Failed to scan for lighthouse devices: {err}");
     }
     set_scanning_status(false).await;
 }
@@ -143,28 +148,69 @@ pub async fn get_device_power_state(
     let device = get_device(device_id.clone())
         .await
         .ok_or(LighthouseError::DeviceNotFound)?;
-    let characteristic = match get_power_characteristic(device_id.clone()).await {
-        Ok(characteristic) => characteristic,
-        Err(err) => {
-            return Err(err);
-        }
-    };
-    let characteristic_props = match characteristic.properties().await {
-        Ok(props) => props,
-        Err(err) => {
-            return Err(LighthouseError::FailedToGetCharacteristicProperties(err));
-        }
-    };
+    let backend = get_backend().await.ok_or(LighthouseError::DeviceNotFound)?;
+    let (service_uuid, characteristic_uuid) = get_power_characteristic(&device.device_type);
+    let characteristic_props = backend
+        .properties(&device_id, service_uuid, characteristic_uuid)
+        .await?;
     if !characteristic_props.read {
         return Err(LighthouseError::CharacteristicDoesNotSupportRead);
     }
-    let value = match characteristic.read().await {
-        Ok(value) => value,
-        Err(err) => {
-            return Err(LighthouseError::FailedToReadCharacteristic(err));
-        }
+    let value = backend
+        .read(&device_id, service_uuid, characteristic_uuid)
+        .await?;
+    let (state, v1_timeout) = decode_power_state(&device.device_type, &value)?;
+    update_power_state(device_id, state.clone(), v1_timeout).await;
+    Ok((state, v1_timeout))
+}
+
+/// Checks if the power characteristic of a device can notify changes to its value
+async fn supports_power_notifications(device_id: &str) -> Result<bool, LighthouseError> {
+    let device = get_device(device_id.to_string())
+        .await
+        .ok_or(LighthouseError::DeviceNotFound)?;
+    let backend = get_backend().await.ok_or(LighthouseError::DeviceNotFound)?;
+    let (service_uuid, characteristic_uuid) = get_power_characteristic(&device.device_type);
+    let properties = backend
+        .properties(device_id, service_uuid, characteristic_uuid)
+        .await?;
+    Ok(properties.notify)
+}
+
+/// Sends every value the power characteristic of a device notifies, until the subscription ends
+async fn subscribe_power_notifications(
+    device_id: String,
+    values: mpsc::UnboundedSender<Vec<u8>>,
+) -> Result<(), LighthouseError> {
+    let device = get_device(device_id.clone())
+        .await
+        .ok_or(LighthouseError::DeviceNotFound)?;
+    let backend = get_backend().await.ok_or(LighthouseError::DeviceNotFound)?;
+    let (service_uuid, characteristic_uuid) = get_power_characteristic(&device.device_type);
+    backend
+        .notify(&device_id, service_uuid, characteristic_uuid, values)
+        .await
+}
+
+async fn handle_power_notification(device_id: String, value: Vec<u8>) {
+    let Some(device) = get_device(device_id.clone()).await else {
+        return;
     };
-    let (state, v1_timeout) = match device.device_type {
+    match decode_power_state(&device.device_type, &value) {
+        Ok((state, v1_timeout)) => update_power_state(device_id, state, v1_timeout).await,
+        Err(e) => debug!(
+            "[Core] Received invalid power state notification from lighthouse device ({}): {e:?}",
+            device.device_name
+        ),
+    }
+}
+
+/// Decodes the value of the power characteristic into the power state and, for V1 base stations, the timeout
+fn decode_power_state(
+    device_type: &LighthouseDeviceType,
+    value: &[u8],
+) -> Result<(LighthousePowerState, Option<u16>), LighthouseError> {
+    let (state, v1_timeout) = match device_type {
         LighthouseDeviceType::LighthouseV1 => {
             if value.len() < 4 {
                 return Err(LighthouseError::InvalidCharacteristicValue);
@@ -194,7 +240,15 @@ pub async fn get_device_power_state(
             )
         }
     };
+    Ok((state, v1_timeout))
+}
 
+/// Stores the power state of a device, and lets the UI know if it changed
+async fn update_power_state(
+    device_id: String,
+    state: LighthousePowerState,
+    v1_timeout: Option<u16>,
+) {
     // Get currently known power state and timeout, and update atomically
     let (state_changed, timeout_changed) = {
         let mut power_states_guard = LIGHTHOUSE_DEVICE_POWER_STATES.lock().await;
@@ -238,7 +292,6 @@ pub async fn get_device_power_state(
         )
         .await;
     }
-    Ok((state, v1_timeout))
 }
 
 pub async fn set_device_power_state(
@@ -250,90 +303,122 @@ pub async fn set_device_power_state(
     let device = get_device(device_id.clone())
         .await
         .ok_or(LighthouseError::DeviceNotFound)?;
-    let characteristic = match get_power_characteristic(device_id.clone()).await {
-        Ok(characteristic) => characteristic,
-        Err(err) => return Err(err),
-    };
+    let backend = get_backend().await.ok_or(LighthouseError::DeviceNotFound)?;
+    let (service_uuid, characteristic_uuid) = get_power_characteristic(&device.device_type);
+    // The timeout sent along with a V1 sleep command, which the base station reports back once it accepted it
+    let mut sleep_timeout = None;
     match device.device_type {
         LighthouseDeviceType::LighthouseV1 => {
-            match state {
-                LighthousePowerState::On => {
-                    // Determine payload
-                    // (We ignore the identifier and timeout, as we want to stay on indefinitely)
-                    let payload = [
-                        0x12, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00,
-                        0x00, 0x00,
-                    ];
-                    // Write command
-                    let result = characteristic.write(&payload).await;
-                    if let Err(e) = result {
-                        error!(
-                            "[Core] Failed to power on lighthouse device ({}) : {}",
-                            device.device_name, e
-                        );
-                        return Err(LighthouseError::FailedToWriteCharacteristic(e));
-                    }
-                }
+            let payload = match state {
+                LighthousePowerState::On => v1::wake_packet(),
                 LighthousePowerState::Sleep | LighthousePowerState::Standby => {
-                    // Construct payload
-                    let mut payload = [
-                        0x12, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-                        0x00, 0x00,
-                    ];
-                    // Set identifier
-                    let identifier = v1_identifier.unwrap_or(0xffffffff);
-                    payload[4..8].copy_from_slice(&identifier.to_le_bytes());
-                    // Determine timeout
-                    let mut timeout = v1_timeout.unwrap_or(1);
-                    // If we don't have an identifier, switch command to 0x1201 (60 sec default)
-                    if identifier == 0xffffffff {
-                        payload[1] = 0x01;
-                        timeout = 60;
-                    }
-                    // Set timeout
-                    payload[2..4].copy_from_slice(&timeout.to_be_bytes());
-                    // Write command
-                    let result = characteristic.write(&payload).await;
-                    if let Err(e) = result {
-                        error!(
-                            "[Core] Failed to power off lighthouse device ({}) : {}",
-                            device.device_name, e
-                        );
-                        return Err(LighthouseError::FailedToWriteCharacteristic(e));
-                    } else {
-                        // Wait a bit for the device to actually power off
-                        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-                    }
+                    // Use the stored identifier and timeout, unless they were given
+                    let config = v1::get_config(&device_id).await;
+                    let identifier = match (v1_identifier, config.as_ref()) {
+                        (Some(identifier), _) => identifier,
+                        (None, Some(config)) => v1::parse_identifier(&config.identifier)?,
+                        (None, None) => return Err(LighthouseError::MissingV1Identifier),
+                    };
+                    let timeout = v1_timeout
+                        .or(config.and_then(|config| config.timeout))
+                        .unwrap_or(v1::DEFAULT_SLEEP_TIMEOUT);
+                    sleep_timeout = Some(timeout);
+                    v1::sleep_packet(identifier, timeout)
                 }
                 LighthousePowerState::Booting | LighthousePowerState::Unknown => {
                     warn!("[Core] Attempted to set lighthouse device power to an invalid state");
+                    return Ok(());
                 }
             };
+            if let Err(e) = backend
+                .write(
+                    &device_id,
+                    service_uuid,
+                    characteristic_uuid,
+                    &payload,
+                    true,
+                )
+                .await
+            {
+                error!(
+                    "[Core] Failed to set power state of lighthouse device ({}) : {:?}",
+                    device.device_name, e
+                );
+                return Err(e);
+            }
         }
         LighthouseDeviceType::LighthouseV2 => {
-            match state {
-                LighthousePowerState::Sleep => {
-                    let _ = characteristic.write_without_response(&[0x00]).await;
-                }
-                LighthousePowerState::Standby => {
-                    let _ = characteristic.write_without_response(&[0x02]).await;
-                }
-                LighthousePowerState::On => {
-                    let _ = characteristic.write_without_response(&[0x01]).await;
-                }
+            let payload = match state {
+                LighthousePowerState::Sleep => 0x00,
+                LighthousePowerState::Standby => 0x02,
+                LighthousePowerState::On => 0x01,
                 LighthousePowerState::Booting | LighthousePowerState::Unknown => {
                     warn!("[Core] Attempted to set lighthouse device power to an invalid state");
+                    return Ok(());
                 }
             };
+            if let Err(e) = backend
+                .write(
+                    &device_id,
+                    service_uuid,
+                    characteristic_uuid,
+                    &[payload],
+                    false,
+                )
+                .await
+            {
+                error!(
+                    "[Core] Failed to set power state of lighthouse device ({}) : {:?}",
+                    device.device_name, e
+                );
+                return Err(e);
+            }
         }
     };
+    monitor::on_command_sent(&device_id).await;
+    // V1 base stations silently ignore sleep commands with the wrong identifier
+    if let Some(timeout) = sleep_timeout {
+        return confirm_v1_sleep_timeout(&device, timeout).await;
+    }
     // Fetch the new state for confirmation
     let _ = get_device_power_state(device_id).await;
     Ok(())
 }
 
-async fn handle_discovered_device(device: Device) {
-    let device_id = device.id();
+/// Waits for a V1 base station to report the timeout of a sleep command, which it only does once it accepted it
+async fn confirm_v1_sleep_timeout(
+    device: &LighthouseDevice,
+    timeout: u16,
+) -> Result<(), LighthouseError> {
+    let mut reported = false;
+    for _ in 0..V1_CONFIRMATION_ATTEMPTS {
+        sleep(V1_CONFIRMATION_INTERVAL).await;
+        if let Ok((_, v1_timeout)) = get_device_power_state(device.id.clone()).await {
+            if v1_timeout == Some(timeout) {
+                return Ok(());
+            }
+            reported = true;
+        }
+    }
+    // If the state could not be read at all, we can't tell if the identifier was wrong
+    if !reported {
+        warn!(
+            "[Core] Could not confirm that lighthouse device ({}) accepted the sleep command",
+            device.device_name
+        );
+        return Ok(());
+    }
+    warn!(
+        "[Core] Lighthouse device ({}) rejected the sleep command, its identifier is likely wrong",
+        device.device_name
+    );
+    Err(LighthouseError::V1IdentifierRejected)
+}
+
+async fn handle_discovered_device(device_id: String) {
+    let Some(backend) = get_backend().await else {
+        return;
+    };
 
     // Check if this device is already being processed and add it atomically
     {
@@ -345,7 +430,7 @@ async fn handle_discovered_device(device: Device) {
     }
 
     // Helper closure to clean up processing device on early return
-    let cleanup = |device_id: DeviceId| {
+    let cleanup = |device_id: String| {
         tokio::spawn(async move {
             let mut processing_devices_guard = PROCESSING_DEVICES.lock().await;
             processing_devices_guard.remove(&device_id);
@@ -361,7 +446,7 @@ async fn handle_discovered_device(device: Device) {
         }
     }
     // Get the device name
-    let device_name = match device.name_async().await {
+    let device_name = match backend.device_name(&device_id).await {
         Ok(name) => name,
         Err(err) => {
             trace!("[Core] Failed to get name of discovered device: {err}");
@@ -381,36 +466,37 @@ async fn handle_discovered_device(device: Device) {
         "[Core] Getting services of discovered device: {}",
         device_name.clone()
     );
-    let services = match tokio::time::timeout(Duration::from_secs(15), device.services()).await {
-        Ok(Ok(services)) => services,
-        Ok(Err(err)) => {
-            warn!(
-                "[Core] Failed to get services of discovered device ({}): {}",
-                device_name.clone(),
-                err
-            );
-            cleanup(device_id.clone());
-            return;
-        }
-        Err(_) => {
-            debug!(
-                "[Core] Timeout getting services of discovered device: {}",
-                device_name.clone()
-            );
-            cleanup(device_id.clone());
-            return;
-        }
-    };
+    let services =
+        match tokio::time::timeout(Duration::from_secs(15), backend.connect(&device_id)).await {
+            Ok(Ok(services)) => services,
+            Ok(Err(err)) => {
+                warn!(
+                    "[Core] Failed to get services of discovered device ({}): {:?}",
+                    device_name.clone(),
+                    err
+                );
+                cleanup(device_id.clone());
+                return;
+            }
+            Err(_) => {
+                debug!(
+                    "[Core] Timeout getting services of discovered device: {}",
+                    device_name.clone()
+                );
+                cleanup(device_id.clone());
+                return;
+            }
+        };
     // Determine the device type based on the services present
     let device_type = {
         if services
             .iter()
-            .any(|service| service.uuid().eq(&LIGHTHOUSE_V1_PWR_SERVICE))
+            .any(|service| service.eq(&LIGHTHOUSE_V1_PWR_SERVICE))
         {
             LighthouseDeviceType::LighthouseV1
         } else if services
             .iter()
-            .any(|service| service.uuid().eq(&LIGHTHOUSE_V2_PWR_SERVICE))
+            .any(|service| service.eq(&LIGHTHOUSE_V2_PWR_SERVICE))
         {
             LighthouseDeviceType::LighthouseV2
         } else {
@@ -426,7 +512,6 @@ async fn handle_discovered_device(device: Device) {
         id: device_id.clone(),
         device_name: device_name.clone(),
         device_type: device_type.clone(),
-        bt_device: device.clone(),
     };
     {
         let mut lighthouse_devices_guard = LIGHTHOUSE_DEVICES.lock().await;
@@ -450,6 +535,10 @@ async fn handle_discovered_device(device: Device) {
         device_type.clone(),
         device_name
     );
+    // Read the channel and revisions in the background
+    tokio::spawn(refresh_device_info(device_id.clone()));
+    // Keep track of the power state
+    monitor::start(device_id.clone()).await;
 
     // Clean up processing device
     cleanup(device_id.clone());
@@ -475,79 +564,152 @@ async fn set_scanning_status(scanning: bool) {
     .await;
 }
 
+async fn get_backend() -> Option<Arc<dyn LighthouseBackend>> {
+    BACKEND.lock().await.clone()
+}
+
 async fn get_device(device_id: String) -> Option<Arc<LighthouseDevice>> {
     let devices = LIGHTHOUSE_DEVICES.lock().await;
     for device in devices.iter() {
-        if device.id.to_string().eq(&device_id) {
+        if device.id.eq(&device_id) {
             return Some(Arc::new(device.clone()));
         }
     }
     None
 }
 
-async fn get_lighthouse_service(device: LighthouseDevice) -> Result<Service, LighthouseError> {
-    let services = match device.bt_device.services().await {
-        Ok(services) => services,
-        Err(err) => return Err(LighthouseError::FailedToGetServices(err)),
-    };
-    let service_uuid = match device.device_type {
-        LighthouseDeviceType::LighthouseV1 => LIGHTHOUSE_V1_PWR_SERVICE,
-        LighthouseDeviceType::LighthouseV2 => LIGHTHOUSE_V2_PWR_SERVICE,
-    };
-    let service = services
-        .iter()
-        .find(|service| service.uuid().eq(&service_uuid));
-    match service {
-        Some(service) => Ok(service.clone()),
-        None => Err(LighthouseError::ServiceNotFound),
+/// Gets the service and characteristic used for reading and setting the power state of a device
+fn get_power_characteristic(device_type: &LighthouseDeviceType) -> (Uuid, Uuid) {
+    match device_type {
+        LighthouseDeviceType::LighthouseV1 => {
+            (LIGHTHOUSE_V1_PWR_SERVICE, LIGHTHOUSE_V1_PWR_CHARACTERISTIC)
+        }
+        LighthouseDeviceType::LighthouseV2 => {
+            (LIGHTHOUSE_V2_PWR_SERVICE, LIGHTHOUSE_V2_PWR_CHARACTERISTIC)
+        }
     }
 }
 
-async fn get_power_characteristic(device_id: String) -> Result<Characteristic, LighthouseError> {
-    let device = get_device(device_id)
+/// Makes a V2 base station blink its status LED, so it can be found physically
+pub async fn identify_device(device_id: String) -> Result<(), LighthouseError> {
+    let device = get_device(device_id.clone())
         .await
         .ok_or(LighthouseError::DeviceNotFound)?;
-    let service = match get_lighthouse_service(Arc::unwrap_or_clone(device.clone())).await {
-        Ok(service) => service,
-        Err(err) => return Err(err),
+    if device.device_type != LighthouseDeviceType::LighthouseV2 {
+        return Err(LighthouseError::NotSupported);
+    }
+    let backend = get_backend().await.ok_or(LighthouseError::DeviceNotFound)?;
+    if let Err(e) = backend
+        .write(
+            &device_id,
+            LIGHTHOUSE_V2_PWR_SERVICE,
+            LIGHTHOUSE_V2_IDENTIFY_CHARACTERISTIC,
+            &[0x00],
+            true,
+        )
+        .await
+    {
+        error!(
+            "[Core] Failed to identify lighthouse device ({}) : {:?}",
+            device.device_name, e
+        );
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Reads the channel of a V2 base station, and the firmware and hardware revisions of any base station,
+/// and updates the device if anything changed. Values that cannot be read are left empty.
+pub async fn refresh_device_info(
+    device_id: String,
+) -> Result<LighthouseDeviceInfo, LighthouseError> {
+    let device = get_device(device_id.clone())
+        .await
+        .ok_or(LighthouseError::DeviceNotFound)?;
+    let channel = match device.device_type {
+        LighthouseDeviceType::LighthouseV1 => None,
+        LighthouseDeviceType::LighthouseV2 => read_characteristic(
+            device_id.clone(),
+            LIGHTHOUSE_V2_PWR_SERVICE,
+            LIGHTHOUSE_V2_CHANNEL_CHARACTERISTIC,
+        )
+        .await
+        .ok()
+        .and_then(|value| value.first().copied()),
     };
-    let characteristics = match service.characteristics().await {
-        Ok(characteristics) => characteristics,
-        Err(err) => return Err(LighthouseError::FailedToGetCharacteristics(err)),
+    let read_string = |characteristic_uuid| {
+        let device_id = device_id.clone();
+        async move {
+            read_characteristic(device_id, DEVICE_INFORMATION_SERVICE, characteristic_uuid)
+                .await
+                .ok()
+                .map(|value| {
+                    String::from_utf8_lossy(&value)
+                        .trim_end_matches('\0')
+                        .to_string()
+                })
+        }
     };
-    let characteristic_uuid = match device.device_type {
-        LighthouseDeviceType::LighthouseV1 => LIGHTHOUSE_V1_PWR_CHARACTERISTIC,
-        LighthouseDeviceType::LighthouseV2 => LIGHTHOUSE_V2_PWR_CHARACTERISTIC,
+    let info = LighthouseDeviceInfo {
+        channel,
+        firmware_revision: read_string(FIRMWARE_REVISION_CHARACTERISTIC).await,
+        hardware_revision: read_string(HARDWARE_REVISION_CHARACTERISTIC).await,
     };
-    let characteristic = characteristics
-        .iter()
-        .find(|characteristic| characteristic.uuid().eq(&characteristic_uuid));
-    match characteristic {
-        Some(characteristic) => Ok(characteristic.clone()),
-        None => Err(LighthouseError::CharacteristicNotFound),
+    let previous_info = LIGHTHOUSE_DEVICE_INFO
+        .lock()
+        .await
+        .insert(device_id, info.clone());
+    if previous_info.as_ref() != Some(&info) {
+        send_event(
+            EVENT_DEVICE_INFO_CHANGED,
+            LighthouseDeviceInfoChangedEvent {
+                device: map_discovered_device_to_lighthouse_device(Arc::unwrap_or_clone(device))
+                    .await,
+            },
+        )
+        .await;
     }
+    Ok(info)
 }
 
-async fn map_discovered_device_to_lighthouse_device(d: LighthouseDevice) -> LighthouseDeviceModel {
-    let power_state = match LIGHTHOUSE_DEVICE_POWER_STATES
-        .lock()
+async fn read_characteristic(
+    device_id: String,
+    service_uuid: Uuid,
+    characteristic_uuid: Uuid,
+) -> Result<Vec<u8>, LighthouseError> {
+    let backend = get_backend().await.ok_or(LighthouseError::DeviceNotFound)?;
+    backend
+        .read(&device_id, service_uuid, characteristic_uuid)
         .await
-        .get(&d.bt_device.id().to_string())
-    {
+}
+
+async fn map_discovered_device_to_lighthouse_device(d: LighthouseDevice) -> LighthouseDeviceModel {
+    let power_state = match LIGHTHOUSE_DEVICE_POWER_STATES.lock().await.get(&d.id) {
         Some(state) => state.clone(),
         None => LighthousePowerState::Unknown,
     };
     let v1_timeout = LIGHTHOUSE_DEVICE_V1_TIMEOUTS
         .lock()
         .await
-        .get(&d.bt_device.id().to_string())
+        .get(&d.id)
         .copied();
+    let info = LIGHTHOUSE_DEVICE_INFO
+        .lock()
+        .await
+        .get(&d.id)
+        .cloned()
+        .unwrap_or_default();
+    let reachable = monitor::is_reachable(&d.id).await;
     let ld = LighthouseDeviceModel {
-        id: d.id.to_string(),
+        id: d.id,
         device_name: d.device_name,
         power_state,
         device_type: d.device_type,
         v1_timeout,
+        channel: info.channel,
+        firmware_revision: info.firmware_revision,
+        hardware_revision: info.hardware_revision,
+        reachable,
     };
     if ld.device_type == LighthouseDeviceType::LighthouseV1 {
         info!("LD: {ld:?}");
@@ -565,13 +727,14 @@ async fn reset() {
         drop(scanning_guard);
         sleep(Duration::from_millis(100)).await;
     }
+    // Stop keeping track of the known devices
+    monitor::stop_all().await;
     // Disconnect all devices
     {
-        let adapter_guard = ADAPTER.lock().await;
-        if let Some(adapter) = adapter_guard.as_ref() {
+        if let Some(backend) = get_backend().await {
             let devices_guard = LIGHTHOUSE_DEVICES.lock().await;
             for device in devices_guard.iter() {
-                let _ = adapter.disconnect_device(&device.bt_device).await;
+                backend.disconnect(&device.id).await;
             }
         }
     }
@@ -590,6 +753,11 @@ async fn reset() {
         let mut lighthouse_device_v1_timeouts_guard = LIGHTHOUSE_DEVICE_V1_TIMEOUTS.lock().await;
         lighthouse_device_v1_timeouts_guard.clear();
     }
+    // Clear all known device info
+    {
+        let mut lighthouse_device_info_guard = LIGHTHOUSE_DEVICE_INFO.lock().await;
+        lighthouse_device_info_guard.clear();
+    }
     // Clear all processing devices
     {
         let mut processing_devices_guard = PROCESSING_DEVICES.lock().await;
@@ -1,4 +1,3 @@
-use bluest::{Device, DeviceId};
 use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -30,15 +29,20 @@ pub enum LighthouseDeviceType {
 #[derive(Debug)]
 pub enum LighthouseError {
     DeviceNotFound,
-    FailedToGetServices(bluest::Error),
+    FailedToGetServices(String),
     ServiceNotFound,
-    FailedToGetCharacteristics(bluest::Error),
+    FailedToGetCharacteristics(String),
     CharacteristicNotFound,
-    FailedToReadCharacteristic(bluest::Error),
-    FailedToWriteCharacteristic(bluest::Error),
+    FailedToReadCharacteristic(String),
+    FailedToWriteCharacteristic(String),
     InvalidCharacteristicValue,
     CharacteristicDoesNotSupportRead,
-    FailedToGetCharacteristicProperties(bluest::Error),
+    FailedToGetCharacteristicProperties(String),
+    GroupNotFound,
+    MissingV1Identifier,
+    InvalidV1Identifier,
+    V1IdentifierRejected,
+    NotSupported,
 }
 
 impl Serialize for LighthouseError {
@@ -54,7 +58,7 @@ impl Serialize for LighthouseError {
             }
             LighthouseError::FailedToGetServices(e) => {
                 error.serialize_field("error", "FailedToGetServices")?;
-                error.serialize_field("message", &Some(e.to_string()))?;
+                error.serialize_field("message", &Some(e))?;
             }
             LighthouseError::ServiceNotFound => {
                 error.serialize_field("error", "ServiceNotFound")?;
@@ -62,7 +66,7 @@ impl Serialize for LighthouseError {
             }
             LighthouseError::FailedToGetCharacteristics(e) => {
                 error.serialize_field("error", "FailedToGetCharacteristics")?;
-                error.serialize_field("message", &Some(e.to_string()))?;
+                error.serialize_field("message", &Some(e))?;
             }
             LighthouseError::CharacteristicNotFound => {
                 error.serialize_field("error", "CharacteristicNotFound")?;
@@ -70,11 +74,11 @@ impl Serialize for LighthouseError {
             }
             LighthouseError::FailedToReadCharacteristic(e) => {
                 error.serialize_field("error", "FailedToReadCharacteristic")?;
-                error.serialize_field("message", &Some(e.to_string()))?;
+                error.serialize_field("message", &Some(e))?;
             }
             LighthouseError::FailedToWriteCharacteristic(e) => {
                 error.serialize_field("error", "FailedToWriteCharacteristic")?;
-                error.serialize_field("message", &Some(e.to_string()))?;
+                error.serialize_field("message", &Some(e))?;
             }
             LighthouseError::InvalidCharacteristicValue => {
                 error.serialize_field("error", "InvalidCharacteristicValue")?;
@@ -86,7 +90,27 @@ impl Serialize for LighthouseError {
             }
             LighthouseError::FailedToGetCharacteristicProperties(e) => {
                 error.serialize_field("error", "FailedToReadCharacteristicProperties")?;
-                error.serialize_field("message", &Some(e.to_string()))?;
+                error.serialize_field("message", &Some(e))?;
+            }
+            LighthouseError::GroupNotFound => {
+                error.serialize_field("error", "GroupNotFound")?;
+                error.serialize_field("message", &None::<String>)?;
+            }
+            LighthouseError::MissingV1Identifier => {
+                error.serialize_field("error", "MissingV1Identifier")?;
+                error.serialize_field("message", &None::<String>)?;
+            }
+            LighthouseError::InvalidV1Identifier => {
+                error.serialize_field("error", "InvalidV1Identifier")?;
+                error.serialize_field("message", &None::<String>)?;
+            }
+            LighthouseError::V1IdentifierRejected => {
+                error.serialize_field("error", "V1IdentifierRejected")?;
+                error.serialize_field("message", &None::<String>)?;
+            }
+            LighthouseError::NotSupported => {
+                error.serialize_field("error", "NotSupported")?;
+                error.serialize_field("message", &None::<String>)?;
             }
         };
         error.end()
@@ -101,12 +125,88 @@ pub struct LighthouseDeviceModel {
     pub power_state: LighthousePowerState,
     pub device_type: LighthouseDeviceType,
     pub v1_timeout: Option<u16>,
+    /// The channel (mode) of a V2 base station. Base stations sharing a channel interfere with each other.
+    pub channel: Option<u8>,
+    pub firmware_revision: Option<String>,
+    pub hardware_revision: Option<String>,
+    /// Whether the device responded within the unreachable timeout
+    pub reachable: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LighthouseDeviceInfo {
+    pub channel: Option<u8>,
+    pub firmware_revision: Option<String>,
+    pub hardware_revision: Option<String>,
+}
+
+/// How often the power state of devices is checked
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LighthouseMonitorConfig {
+    /// The polling interval right after a command was sent, or the state changed
+    pub min_poll_interval_ms: u64,
+    /// The polling interval the monitor backs off to while the state stays the same
+    pub max_poll_interval_ms: u64,
+    /// How long a device can go without responding before it is marked unreachable
+    pub unreachable_timeout_ms: u64,
+}
+
+impl Default for LighthouseMonitorConfig {
+    fn default() -> Self {
+        Self {
+            min_poll_interval_ms: 1000,
+            max_poll_interval_ms: 30000,
+            unreachable_timeout_ms: 60000,
+        }
+    }
+}
+
+/// What is needed to control a V1 base station, which only goes to sleep when given its own identifier
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LighthouseV1DeviceConfig {
+    /// The identifier printed on the back of the base station, as 8 hexadecimal digits
+    pub identifier: String,
+    /// How long (in seconds) the base station waits before going to sleep when told to
+    pub timeout: Option<u16>,
+}
+
+/// A set of devices that can be powered on or off together
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LighthouseGroup {
+    pub id: String,
+    pub name: String,
+    pub device_ids: Vec<String>,
+}
+
+/// Changes the power state of a group when SteamVR starts or stops
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LighthouseOpenVRPowerRule {
+    pub group_id: String,
+    /// The power state to set once OpenVR has been initialized
+    pub on_start: Option<LighthousePowerState>,
+    /// The power state to set once OpenVR has been shut down after being initialized
+    pub on_stop: Option<LighthousePowerState>,
+    /// The V1 timeout to use when putting V1 base stations to sleep
+    pub v1_timeout: Option<u16>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LighthouseDevicePowerResult {
+    pub device_id: String,
+    pub success: bool,
+    pub attempts: u32,
+    pub error: Option<LighthouseError>,
 }
 
 #[derive(Debug, Clone)]
 pub struct LighthouseDevice {
-    pub id: DeviceId,
-    pub bt_device: Device,
+    pub id: String,
     pub device_name: String,
     pub device_type: LighthouseDeviceType,
 }
@@ -132,6 +232,12 @@ pub struct LighthouseDeviceDiscoveredEvent {
     pub device: LighthouseDeviceModel,
 }
 
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LighthouseDeviceInfoChangedEvent {
+    pub device: LighthouseDeviceModel,
+}
+
 #[derive(Debug, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct LighthouseDevicePowerStateChangedEvent {
@@ -139,3 +245,18 @@ pub struct LighthouseDevicePowerStateChangedEvent {
     pub power_state: LighthousePowerState,
     pub v1_timeout: Option<u16>,
 }
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LighthouseDeviceReachabilityChangedEvent {
+    pub device_id: String,
+    pub reachable: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LighthouseBatchPowerCompletedEvent<'a> {
+    pub group_id: Option<String>,
+    pub power_state: LighthousePowerState,
+    pub results: &'a [LighthouseDevicePowerResult],
+}
@@ -0,0 +1,200 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, LazyLock},
+    time::{Duration, Instant},
+};
+
+use log::{error, info, warn};
+use tokio::{
+    sync::{mpsc, Mutex, Notify},
+    task::JoinHandle,
+    time::sleep,
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::utils::{get_data_file_path, send_event};
+
+use super::models::{
+    LighthouseDeviceReachabilityChangedEvent, LighthouseMonitorConfig, LighthousePowerState,
+};
+
+static EVENT_DEVICE_REACHABILITY_CHANGED: &str = "LIGHTHOUSE_DEVICE_REACHABILITY_CHANGED";
+const CONFIG_FILE_NAME: &str = "lighthouse_monitor.json";
+
+static CONFIG: LazyLock<Mutex<LighthouseMonitorConfig>> = LazyLock::new(Default::default);
+static MONITORED_DEVICES: LazyLock<Mutex<HashMap<String, Arc<MonitoredDevice>>>> =
+    LazyLock::new(Default::default);
+static UNREACHABLE_DEVICES: LazyLock<Mutex<HashSet<String>>> = LazyLock::new(Default::default);
+
+struct MonitoredDevice {
+    /// Wakes the monitor up when a command was sent to the device
+    command_sent: Notify,
+    /// The last time the device responded to a read, or sent a notification
+    last_seen: Mutex<Instant>,
+    cancellation_token: CancellationToken,
+}
+
+/// Loads the stored configuration, if it was ever changed from the defaults
+pub async fn load() {
+    let Some(path) = get_data_file_path(CONFIG_FILE_NAME).await else {
+        return;
+    };
+    let Ok(contents) = tokio::fs::read_to_string(&path).await else {
+        return;
+    };
+    match serde_json::from_str(&contents) {
+        Ok(config) => *CONFIG.lock().await = config,
+        Err(e) => warn!("[Core] Could not read lighthouse monitor configuration: {e}"),
+    }
+}
+
+/// Stores the configuration. It applies to monitored devices from their next poll.
+pub async fn set_config(config: LighthouseMonitorConfig) {
+    *CONFIG.lock().await = config.clone();
+    let Some(path) = get_data_file_path(CONFIG_FILE_NAME).await else {
+        return;
+    };
+    if let Err(e) = tokio::fs::write(&path, serde_json::to_string(&config).unwrap()).await {
+        error!("[Core] Could not write lighthouse monitor configuration: {e}");
+    }
+}
+
+pub async fn get_config() -> LighthouseMonitorConfig {
+    CONFIG.lock().await.clone()
+}
+
+pub async fn is_reachable(device_id: &str) -> bool {
+    !UNREACHABLE_DEVICES.lock().await.contains(device_id)
+}
+
+/// Starts keeping track of the power state of a device, in the background
+pub async fn start(device_id: String) {
+    let mut monitored_devices = MONITORED_DEVICES.lock().await;
+    if monitored_devices.contains_key(&device_id) {
+        return;
+    }
+    let device = Arc::new(MonitoredDevice {
+        command_sent: Notify::new(),
+        last_seen: Mutex::new(Instant::now()),
+        cancellation_token: CancellationToken::new(),
+    });
+    monitored_devices.insert(device_id.clone(), device.clone());
+    tokio::spawn(monitor_device(device_id, device));
+}
+
+pub async fn stop_all() {
+    for (_, device) in MONITORED_DEVICES.lock().await.drain() {
+        device.cancellation_token.cancel();
+    }
+    UNREACHABLE_DEVICES.lock().await.clear();
+}
+
+/// Makes the monitor check the device right away, and frequently until its state settles
+pub async fn on_command_sent(device_id: &str) {
+    if let Some(device) = MONITORED_DEVICES.lock().await.get(device_id) {
+        device.command_sent.notify_one();
+    }
+}
+
+async fn monitor_device(device_id: String, device: Arc<MonitoredDevice>) {
+    let mut interval = Duration::from_millis(CONFIG.lock().await.min_poll_interval_ms);
+    let mut last_state: Option<LighthousePowerState> = None;
+    let mut notifications: Option<JoinHandle<()>> = None;
+    let mut notify_supported: Option<bool> = None;
+    loop {
+        let config = CONFIG.lock().await.clone();
+        let min_interval = Duration::from_millis(config.min_poll_interval_ms);
+        let max_interval = Duration::from_millis(config.max_poll_interval_ms);
+        // Subscribe to notifications where supported, and subscribe again if the subscription ended
+        if notify_supported.is_none() {
+            notify_supported = super::supports_power_notifications(&device_id).await.ok();
+        }
+        if notify_supported == Some(true)
+            && notifications.as_ref().is_none_or(|task| task.is_finished())
+        {
+            notifications = Some(tokio::spawn(subscribe(device_id.clone(), device.clone())));
+        }
+        // Poll the power state
+        match super::get_device_power_state(device_id.clone()).await {
+            Ok((state, _)) => {
+                *device.last_seen.lock().await = Instant::now();
+                // Check again soon while the state is changing, and back off while it is stable
+                interval = if last_state.as_ref() == Some(&state) {
+                    (interval * 2).clamp(min_interval, max_interval)
+                } else {
+                    min_interval
+                };
+                last_state = Some(state);
+            }
+            Err(_) => {
+                interval = (interval * 2).clamp(min_interval, max_interval);
+            }
+        }
+        let unreachable_timeout = Duration::from_millis(config.unreachable_timeout_ms);
+        let reachable = device.last_seen.lock().await.elapsed() < unreachable_timeout;
+        set_reachable(&device_id, reachable).await;
+        // Notifications already tell us about changes, so we only have to poll to notice the device going away
+        let subscribed = notifications
+            .as_ref()
+            .is_some_and(|task| !task.is_finished());
+        let wait = if subscribed {
+            max_interval.max(interval)
+        } else {
+            interval
+        };
+        tokio::select! {
+            _ = device.cancellation_token.cancelled() => break,
+            _ = device.command_sent.notified() => {
+                interval = min_interval;
+                // Give the device a moment to act on the command
+                sleep(min_interval).await;
+            }
+            _ = sleep(wait) => {}
+        }
+    }
+    if let Some(task) = notifications {
+        task.abort();
+    }
+}
+
+async fn subscribe(device_id: String, device: Arc<MonitoredDevice>) {
+    let (values_tx, mut values_rx) = mpsc::unbounded_channel();
+    let subscription = super::subscribe_power_notifications(device_id.clone(), values_tx);
+    let receiver = async {
+        while let Some(value) = values_rx.recv().await {
+            *device.last_seen.lock().await = Instant::now();
+            super::handle_power_notification(device_id.clone(), value).await;
+        }
+    };
+    let (result, _) = tokio::join!(subscription, receiver);
+    if let Err(e) = result {
+        warn!("[Core] Could not subscribe to power state notifications of lighthouse device {device_id}: {e:?}");
+    }
+}
+
+async fn set_reachable(device_id: &str, reachable: bool) {
+    let changed = {
+        let mut unreachable_devices = UNREACHABLE_DEVICES.lock().await;
+        if reachable {
+            unreachable_devices.remove(device_id)
+        } else {
+            unreachable_devices.insert(device_id.to_string())
+        }
+    };
+    if !changed {
+        return;
+    }
+    if reachable {
+        info!("[Core] Lighthouse device {device_id} is reachable again");
+    } else {
+        warn!("[Core] Lighthouse device {device_id} has become unreachable");
+    }
+    send_event(
+        EVENT_DEVICE_REACHABILITY_CHANGED,
+        LighthouseDeviceReachabilityChangedEvent {
+            device_id: device_id.to_string(),
+            reachable,
+        },
+    )
+    .await;
+}
@@ -0,0 +1,320 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::{sync::Mutex, time::sleep};
+
+use super::{
+    backend::{
+        simulated::{SimulatedBackend, SimulatedFaults, SimulatedStation},
+        LighthouseBackend,
+    },
+    decode_power_state, get_device_power_state, get_devices, groups, init_with_backend,
+    models::{
+        LighthouseDeviceType, LighthouseError, LighthouseMonitorConfig, LighthousePowerState,
+        LighthouseV1DeviceConfig,
+    },
+    monitor, reset, set_device_power_state, start_scan, v1, LIGHTHOUSE_V2_PWR_CHARACTERISTIC,
+    LIGHTHOUSE_V2_PWR_SERVICE,
+};
+
+const V2_1: &str = "SIMULATED-V2-1";
+const V2_2: &str = "SIMULATED-V2-2";
+const V1_1: &str = "SIMULATED-V1-1";
+const V1_1_IDENTIFIER: &str = "12345678";
+
+// The lighthouse module keeps its state in statics, so tests cannot run at the same time
+static LOCK: Mutex<()> = Mutex::const_new(());
+
+/// Starts the module with the given backend, without scanning yet.
+/// The monitor only polls each device once, so it doesn't interfere with the reads and faults of a test.
+async fn setup(backend: SimulatedBackend) -> Arc<SimulatedBackend> {
+    reset().await;
+    monitor::set_config(LighthouseMonitorConfig {
+        min_poll_interval_ms: 3_600_000,
+        max_poll_interval_ms: 3_600_000,
+        unreachable_timeout_ms: 3_600_000,
+    })
+    .await;
+    let backend = Arc::new(backend);
+    init_with_backend(backend.clone()).await;
+    backend
+}
+
+/// Scans, and waits for the discovered devices to have been read
+async fn discover() {
+    start_scan(Duration::from_millis(100)).await;
+    sleep(Duration::from_millis(200)).await;
+}
+
+async fn device_ids() -> Vec<String> {
+    let mut ids: Vec<String> = get_devices().await.into_iter().map(|d| d.id).collect();
+    ids.sort();
+    ids
+}
+
+/// Reads the power state until a read succeeds
+async fn read_power_state(device_id: &str) -> (LighthousePowerState, Option<u16>) {
+    for _ in 0..10 {
+        if let Ok(state) = get_device_power_state(device_id.to_string()).await {
+            return state;
+        }
+    }
+    panic!("Could not read the power state of {device_id}");
+}
+
+#[tokio::test]
+async fn discovers_stations_in_range() {
+    let _lock = LOCK.lock().await;
+    let backend = setup(SimulatedBackend::with_default_stations()).await;
+    backend.set_in_range(V2_2, false).await;
+    discover().await;
+    assert_eq!(device_ids().await, vec![V1_1, V2_1]);
+
+    let devices = get_devices().await;
+    let v1 = devices.iter().find(|d| d.id == V1_1).unwrap();
+    assert_eq!(v1.device_type, LighthouseDeviceType::LighthouseV1);
+    assert_eq!(v1.power_state, LighthousePowerState::On);
+    assert!(v1.device_name.starts_with("HTC BS"));
+    let v2 = devices.iter().find(|d| d.id == V2_1).unwrap();
+    assert_eq!(v2.device_type, LighthouseDeviceType::LighthouseV2);
+    assert_eq!(v2.power_state, LighthousePowerState::On);
+    assert_eq!(v2.channel, Some(1));
+    assert_eq!(v2.firmware_revision.as_deref(), Some("1.14.1"));
+
+    // Stations coming into range, or added later, show up in the next scan
+    backend.set_in_range(V2_2, true).await;
+    backend
+        .add_station(SimulatedStation::v2("SIMULATED-V2-3", 3))
+        .await;
+    discover().await;
+    assert_eq!(device_ids().await, vec![V1_1, V2_1, V2_2, "SIMULATED-V2-3"]);
+}
+
+#[tokio::test]
+async fn ignores_devices_that_are_not_base_stations() {
+    let _lock = LOCK.lock().await;
+    let mut station = SimulatedStation::v2("SIMULATED-OTHER", 1);
+    station.name = String::from("Some Headphones");
+    setup(SimulatedBackend::new(vec![station])).await;
+    discover().await;
+    assert!(get_devices().await.is_empty());
+}
+
+#[test]
+fn decodes_v1_power_states() {
+    let v1 = LighthouseDeviceType::LighthouseV1;
+    assert_eq!(
+        decode_power_state(&v1, &[0x12, 0x00, 0x00, 0x00]).unwrap(),
+        (LighthousePowerState::On, Some(0))
+    );
+    assert_eq!(
+        decode_power_state(&v1, &[0x12, 0x02, 0x00, 0x05]).unwrap(),
+        (LighthousePowerState::Sleep, Some(5))
+    );
+    // Timeouts above 10 seconds are only used to test identifiers
+    assert_eq!(
+        decode_power_state(&v1, &[0x12, 0x02, 0xa4, 0x55]).unwrap(),
+        (LighthousePowerState::Unknown, Some(42069))
+    );
+    assert!(matches!(
+        decode_power_state(&v1, &[0x12, 0x00]),
+        Err(LighthouseError::InvalidCharacteristicValue)
+    ));
+}
+
+#[test]
+fn decodes_v2_power_states() {
+    let v2 = LighthouseDeviceType::LighthouseV2;
+    let decode = |value: u8| decode_power_state(&v2, &[value]).unwrap().0;
+    assert_eq!(decode(0x00), LighthousePowerState::Sleep);
+    assert_eq!(decode(0x02), LighthousePowerState::Standby);
+    assert_eq!(decode(0x0b), LighthousePowerState::On);
+    for booting in [0x01, 0x08, 0x09] {
+        assert_eq!(decode(booting), LighthousePowerState::Booting);
+    }
+    assert_eq!(decode(0x42), LighthousePowerState::Unknown);
+    assert!(matches!(
+        decode_power_state(&v2, &[]),
+        Err(LighthouseError::InvalidCharacteristicValue)
+    ));
+}
+
+#[tokio::test]
+async fn sets_v2_power_states() {
+    let _lock = LOCK.lock().await;
+    let backend = setup(SimulatedBackend::with_default_stations()).await;
+    discover().await;
+    for state in [LighthousePowerState::Standby, LighthousePowerState::Sleep] {
+        set_device_power_state(V2_1.to_string(), state.clone(), None, None)
+            .await
+            .unwrap();
+        assert_eq!(read_power_state(V2_1).await, (state, None));
+    }
+    set_device_power_state(V2_1.to_string(), LighthousePowerState::On, None, None)
+        .await
+        .unwrap();
+    assert_eq!(
+        read_power_state(V2_1).await.0,
+        LighthousePowerState::Booting
+    );
+    // The raw value the station reports while booting
+    let value = backend
+        .read(
+            V2_1,
+            LIGHTHOUSE_V2_PWR_SERVICE,
+            LIGHTHOUSE_V2_PWR_CHARACTERISTIC,
+        )
+        .await
+        .unwrap();
+    assert_eq!(value, vec![0x01]);
+}
+
+#[tokio::test]
+async fn retries_power_commands_under_flaky_reads() {
+    let _lock = LOCK.lock().await;
+    let backend = setup(SimulatedBackend::with_default_stations()).await;
+    discover().await;
+    v1::set_config(
+        V1_1.to_string(),
+        Some(LighthouseV1DeviceConfig {
+            identifier: V1_1_IDENTIFIER.to_string(),
+            timeout: Some(5),
+        }),
+    )
+    .await
+    .unwrap();
+    backend
+        .set_faults(SimulatedFaults {
+            fail_every_nth_read: 2,
+            ..Default::default()
+        })
+        .await;
+    // A dropped connection makes the first attempt fail
+    backend.disconnect(V2_1).await;
+
+    let results = groups::set_power_state_batch(
+        None,
+        vec![V2_1.to_string(), V2_2.to_string(), V1_1.to_string()],
+        LighthousePowerState::Sleep,
+        None,
+    )
+    .await;
+    let result_ids: Vec<&str> = results.iter().map(|r| r.device_id.as_str()).collect();
+    assert_eq!(result_ids, vec![V2_1, V2_2, V1_1]);
+    assert!(results.iter().all(|r| r.success && r.error.is_none()));
+    assert_eq!(results[0].attempts, 2);
+    assert_eq!(results[1].attempts, 1);
+
+    assert_eq!(
+        read_power_state(V2_1).await,
+        (LighthousePowerState::Sleep, None)
+    );
+    assert_eq!(
+        read_power_state(V2_2).await,
+        (LighthousePowerState::Sleep, None)
+    );
+    assert_eq!(
+        read_power_state(V1_1).await,
+        (LighthousePowerState::Sleep, Some(5))
+    );
+}
+
+#[tokio::test]
+async fn recovers_from_dropped_connections() {
+    let _lock = LOCK.lock().await;
+    let backend = setup(SimulatedBackend::with_default_stations()).await;
+    discover().await;
+    backend
+        .set_faults(SimulatedFaults {
+            disconnect_every_nth_operation: 3,
+            ..Default::default()
+        })
+        .await;
+
+    // Every third operation drops the connection, failing the operation after it
+    let mut failures = Vec::new();
+    for _ in 0..12 {
+        let result = backend
+            .read(
+                V2_1,
+                LIGHTHOUSE_V2_PWR_SERVICE,
+                LIGHTHOUSE_V2_PWR_CHARACTERISTIC,
+            )
+            .await;
+        failures.push(matches!(
+            result,
+            Err(LighthouseError::FailedToGetServices(_))
+        ));
+    }
+    assert_eq!(failures.iter().filter(|failed| **failed).count(), 3);
+    assert!(failures.windows(2).all(|pair| !(pair[0] && pair[1])));
+
+    // Power commands are retried until the connection is back
+    for state in [LighthousePowerState::Standby, LighthousePowerState::Sleep] {
+        let results = groups::set_power_state_batch(
+            None,
+            vec![V2_1.to_string(), V2_2.to_string()],
+            state.clone(),
+            None,
+        )
+        .await;
+        assert!(results.iter().all(|r| r.success && r.attempts <= 2));
+        assert_eq!(read_power_state(V2_1).await.0, state);
+        assert_eq!(read_power_state(V2_2).await.0, state);
+    }
+}
+
+#[tokio::test]
+async fn reports_rejected_v1_identifiers() {
+    let _lock = LOCK.lock().await;
+    setup(SimulatedBackend::with_default_stations()).await;
+    discover().await;
+
+    // Without an identifier, the sleep command isn't sent at all
+    v1::set_config(V1_1.to_string(), None).await.unwrap();
+    assert!(matches!(
+        set_device_power_state(V1_1.to_string(), LighthousePowerState::Sleep, None, None).await,
+        Err(LighthouseError::MissingV1Identifier)
+    ));
+
+    // The station ignores sleep commands with the wrong identifier
+    v1::set_config(
+        V1_1.to_string(),
+        Some(LighthouseV1DeviceConfig {
+            identifier: String::from("DEADBEEF"),
+            timeout: None,
+        }),
+    )
+    .await
+    .unwrap();
+    let results = groups::set_power_state_batch(
+        None,
+        vec![V1_1.to_string()],
+        LighthousePowerState::Sleep,
+        None,
+    )
+    .await;
+    assert!(!results[0].success);
+    assert_eq!(results[0].attempts, 1);
+    assert!(matches!(
+        results[0].error,
+        Some(LighthouseError::V1IdentifierRejected)
+    ));
+    assert_eq!(
+        read_power_state(V1_1).await,
+        (LighthousePowerState::On, Some(0))
+    );
+
+    // An identifier given with the command takes precedence over the stored one
+    set_device_power_state(
+        V1_1.to_string(),
+        LighthousePowerState::Sleep,
+        Some(3),
+        Some(v1::parse_identifier(V1_1_IDENTIFIER).unwrap()),
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        read_power_state(V1_1).await,
+        (LighthousePowerState::Sleep, Some(3))
+    );
+}
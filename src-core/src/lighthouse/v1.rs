@@ -0,0 +1,110 @@
+use std::{collections::HashMap, sync::LazyLock};
+
+use log::{error, info, warn};
+use tokio::sync::{Mutex, MutexGuard};
+
+use crate::utils::get_data_file_path;
+
+use super::models::{LighthouseError, LighthouseV1DeviceConfig};
+
+const CONFIG_FILE_NAME: &str = "lighthouse_v1_devices.json";
+/// V1 base stations accept commands with this identifier regardless of their own identifier, except for sleeping
+const BROADCAST_IDENTIFIER: u32 = 0xffffffff;
+/// How long (in seconds) a V1 base station waits before going to sleep, if no timeout was configured
+pub const DEFAULT_SLEEP_TIMEOUT: u16 = 1;
+
+static CONFIGS: LazyLock<Mutex<Option<HashMap<String, LighthouseV1DeviceConfig>>>> =
+    LazyLock::new(Default::default);
+
+/// Loads the stored configurations the first time they are needed
+async fn get_stored_configs(
+) -> MutexGuard<'static, Option<HashMap<String, LighthouseV1DeviceConfig>>> {
+    let mut configs_guard = CONFIGS.lock().await;
+    if configs_guard.is_none() {
+        let mut configs = HashMap::new();
+        if let Some(path) = get_data_file_path(CONFIG_FILE_NAME).await {
+            if let Ok(contents) = tokio::fs::read_to_string(&path).await {
+                match serde_json::from_str(&contents) {
+                    Ok(stored_configs) => configs = stored_configs,
+                    Err(e) => warn!("[Core] Could not read V1 lighthouse configurations: {e}"),
+                }
+            }
+        }
+        *configs_guard = Some(configs);
+    }
+    configs_guard
+}
+
+pub async fn get_configs() -> HashMap<String, LighthouseV1DeviceConfig> {
+    get_stored_configs().await.clone().unwrap_or_default()
+}
+
+pub async fn get_config(device_id: &str) -> Option<LighthouseV1DeviceConfig> {
+    get_stored_configs()
+        .await
+        .as_ref()
+        .and_then(|configs| configs.get(device_id).cloned())
+}
+
+/// Stores (or removes) the configuration of a V1 base station
+pub async fn set_config(
+    device_id: String,
+    config: Option<LighthouseV1DeviceConfig>,
+) -> Result<(), LighthouseError> {
+    let config = match config {
+        Some(config) => Some(LighthouseV1DeviceConfig {
+            identifier: format!("{:08X}", parse_identifier(&config.identifier)?),
+            timeout: config.timeout,
+        }),
+        None => None,
+    };
+    let mut configs_guard = get_stored_configs().await;
+    let configs = configs_guard.get_or_insert_with(HashMap::new);
+    match config {
+        Some(config) => {
+            info!("[Core] Storing V1 lighthouse configuration for device {device_id}");
+            configs.insert(device_id, config);
+        }
+        None => {
+            configs.remove(&device_id);
+        }
+    }
+    let Some(path) = get_data_file_path(CONFIG_FILE_NAME).await else {
+        return Ok(());
+    };
+    if let Err(e) = tokio::fs::write(&path, serde_json::to_string(configs).unwrap()).await {
+        error!("[Core] Could not write V1 lighthouse configurations: {e}");
+    }
+    Ok(())
+}
+
+/// Parses an identifier as printed on the back of a V1 base station (8 hexadecimal digits)
+pub fn parse_identifier(identifier: &str) -> Result<u32, LighthouseError> {
+    let identifier = identifier.trim();
+    if identifier.len() != 8 {
+        return Err(LighthouseError::InvalidV1Identifier);
+    }
+    u32::from_str_radix(identifier, 16).map_err(|_| LighthouseError::InvalidV1Identifier)
+}
+
+/// Builds a V1 power command: 0x12, the subcommand, the timeout (big endian) and the identifier (little endian),
+/// padded with zeroes
+fn command_packet(subcommand: u8, timeout: u16, identifier: u32) -> [u8; 20] {
+    let mut packet = [0u8; 20];
+    packet[0] = 0x12;
+    packet[1] = subcommand;
+    packet[2..4].copy_from_slice(&timeout.to_be_bytes());
+    packet[4..8].copy_from_slice(&identifier.to_le_bytes());
+    packet
+}
+
+/// Wakes up a V1 base station, and keeps it on indefinitely
+pub fn wake_packet() -> [u8; 20] {
+    command_packet(0x00, 0, BROADCAST_IDENTIFIER)
+}
+
+/// Puts a V1 base station to sleep after the given timeout (in seconds).
+/// Base stations ignore this command if the identifier isn't their own.
+pub fn sleep_packet(identifier: u32, timeout: u16) -> [u8; 20] {
+    command_packet(0x02, timeout, identifier)
+}
@@ -334,6 +334,18 @@ fn on_cron_minute_start(_: &str) {
 fn configure_command_handlers() -> impl Fn(tauri::ipc::Invoke) -> bool {
     tauri::generate_handler![
         openvr::commands::openvr_get_devices,
+        openvr::commands::sleep_detector_get_classification,
+        openvr::commands::sleep_detector_get_baseline,
+        openvr::commands::sleep_detector_set_baseline,
+        openvr::commands::sleep_detector_get_classifier_config,
+        openvr::commands::sleep_detector_set_classifier_config,
+        openvr::commands::sleep_detector_start_calibration,
+        openvr::commands::sleep_detector_finish_calibration,
+        openvr::commands::gesture_detector_get_config,
+        openvr::commands::gesture_detector_set_config,
+        openvr::commands::sleep_detector_start_pose_recording,
+        openvr::commands::sleep_detector_stop_pose_recording,
+        openvr::commands::sleep_detector_replay_pose_recording,
         openvr::commands::openvr_status,
         openvr::commands::openvr_get_analog_gain,
         openvr::commands::openvr_set_analog_gain,
@@ -378,14 +390,42 @@ fn configure_command_handlers() -> impl Fn(tauri::ipc::Invoke) -> bool {
         os::commands::is_vrchat_active,
         os::commands::is_elevation_security_disabled,
         osc::commands::osc_send_command,
+        osc::commands::osc_send_values,
+        osc::commands::osc_send_bundle,
         osc::commands::osc_valid_addr,
         osc::commands::start_osc_server,
         osc::commands::stop_osc_server,
+        osc::commands::osc_get_discovered_services,
+        osc::commands::osc_select_discovered_service,
+        osc::commands::osc_get_selected_discovered_service,
+        osc::commands::osc_set_target_override,
+        osc::commands::osc_get_target_override,
+        osc::commands::oscquery_get_host_info,
+        osc::commands::oscquery_get_tree,
+        osc::commands::get_osc_receiver_stats,
+        osc::commands::set_osc_relay_targets,
+        osc::commands::get_osc_relay_status,
+        osc::commands::osc_start_recording,
+        osc::commands::osc_stop_recording,
+        osc::commands::osc_start_replay,
+        osc::commands::osc_stop_replay,
+        osc::commands::vrc_avatar_get_state,
+        osc::commands::vrc_avatar_subscribe_parameters,
+        osc::commands::vrc_avatar_unsubscribe_parameters,
+        osc::commands::vrc_chatbox_send,
+        osc::commands::vrc_chatbox_clear_queue,
+        osc::commands::vrc_chatbox_set_typing,
+        osc::commands::vrc_input_press_button,
+        osc::commands::vrc_input_set_button,
+        osc::commands::vrc_input_set_axis,
+        osc::commands::vrc_input_release_all,
         osc::commands::get_vrchat_osc_address,
         osc::commands::get_vrchat_oscquery_address,
         osc::commands::add_osc_method,
         osc::commands::set_osc_method_value,
         osc::commands::set_osc_receive_address_whitelist,
+        osc::commands::set_osc_receive_address_filter,
+        osc::commands::remove_osc_receive_address_filter,
         elevated_sidecar::commands::elevated_sidecar_started,
         elevated_sidecar::commands::start_elevated_sidecar,
         elevated_sidecar::commands::elevated_sidecar_get_grpc_web_port,
@@ -395,6 +435,13 @@ fn configure_command_handlers() -> impl Fn(tauri::ipc::Invoke) -> bool {
         overlay_sidecar::commands::overlay_sidecar_get_grpc_port,
         system_tray::commands::set_close_to_system_tray,
         vrc_log_parser::commands::init_vrc_log_watcher,
+        vrc_log_parser::commands::set_vrc_log_directory_override,
+        vrc_log_parser::commands::get_vrc_log_directory,
+        vrc_log_parser::commands::vrc_history_get_instance_visits,
+        vrc_log_parser::commands::vrc_history_get_players_at,
+        vrc_log_parser::commands::vrc_history_get_world_duration,
+        vrc_log_parser::commands::vrc_history_clear,
+        vrc_log_parser::commands::vrc_log_import,
         discord::commands::discord_update_activity,
         discord::commands::discord_clear_activity,
         http::commands::get_http_server_port,
@@ -403,9 +450,21 @@ fn configure_command_handlers() -> impl Fn(tauri::ipc::Invoke) -> bool {
         lighthouse::commands::lighthouse_get_devices,
         lighthouse::commands::lighthouse_set_device_power_state,
         lighthouse::commands::lighthouse_get_device_power_state,
+        lighthouse::commands::lighthouse_identify_device,
+        lighthouse::commands::lighthouse_refresh_device_info,
         lighthouse::commands::lighthouse_get_status,
         lighthouse::commands::lighthouse_get_scanning_status,
         lighthouse::commands::lighthouse_reset,
+        lighthouse::commands::lighthouse_set_groups,
+        lighthouse::commands::lighthouse_get_groups,
+        lighthouse::commands::lighthouse_set_monitor_config,
+        lighthouse::commands::lighthouse_get_monitor_config,
+        lighthouse::commands::lighthouse_get_v1_configs,
+        lighthouse::commands::lighthouse_set_v1_config,
+        lighthouse::commands::lighthouse_set_power_state_batch,
+        lighthouse::commands::lighthouse_set_group_power_state,
+        lighthouse::commands::lighthouse_set_openvr_power_rules,
+        lighthouse::commands::lighthouse_get_openvr_power_rules,
         steam::commands::steam_active,
         steam::commands::steam_achievement_get,
         steam::commands::steam_achievement_set,
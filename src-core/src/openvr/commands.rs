@@ -1,7 +1,11 @@
 use crate::globals::STEAM_APP_KEY;
 
 use super::{
-    models::{BindingOriginData, OVRDevice, OVRFrameLimits},
+    models::{
+        BindingOriginData, GestureDetectorConfig, OVRDevice, OVRFrameLimits, SleepBaseline,
+        SleepCalibrationLabel, SleepClassification, SleepClassifierConfig,
+        SleepDetectorReplayResult,
+    },
     OVR_CONTEXT,
 };
 use enumset::EnumSet;
@@ -31,6 +35,103 @@ pub async fn openvr_set_init_delay_fix(enabled: bool) {
     *super::OVR_INIT_DELAY_FIX.lock().await = enabled;
 }
 
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn sleep_detector_get_classification() -> SleepClassification {
+    super::devices::SLEEP_DETECTOR.lock().await.classification()
+}
+
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn sleep_detector_get_baseline() -> SleepBaseline {
+    super::devices::SLEEP_DETECTOR.lock().await.baseline()
+}
+
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn sleep_detector_set_baseline(baseline: SleepBaseline) {
+    let mut detector = super::devices::SLEEP_DETECTOR.lock().await;
+    detector.set_baseline(baseline);
+    super::sleep_detector::save_settings(&detector.settings()).await;
+}
+
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn sleep_detector_get_classifier_config() -> SleepClassifierConfig {
+    super::devices::SLEEP_DETECTOR
+        .lock()
+        .await
+        .classifier_config()
+}
+
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn sleep_detector_set_classifier_config(config: SleepClassifierConfig) {
+    let mut detector = super::devices::SLEEP_DETECTOR.lock().await;
+    detector.set_classifier_config(config);
+    super::sleep_detector::save_settings(&detector.settings()).await;
+}
+
+/// Starts learning the user's movement while they are in the given state (resting or awake)
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn sleep_detector_start_calibration(label: SleepCalibrationLabel) {
+    super::devices::SLEEP_DETECTOR
+        .lock()
+        .await
+        .start_calibration(label);
+}
+
+/// Finishes the calibration, and returns the updated baseline. The baseline is stored for future sessions.
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn sleep_detector_finish_calibration() -> Result<SleepBaseline, String> {
+    let mut detector = super::devices::SLEEP_DETECTOR.lock().await;
+    let baseline = detector.finish_calibration()?;
+    super::sleep_detector::save_settings(&detector.settings()).await;
+    Ok(baseline)
+}
+
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn gesture_detector_get_config() -> GestureDetectorConfig {
+    super::devices::GESTURE_DETECTOR.lock().await.config.clone()
+}
+
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn gesture_detector_set_config(config: GestureDetectorConfig) {
+    super::gesture_detector::save_config(&config).await;
+    super::devices::GESTURE_DETECTOR.lock().await.config = config;
+}
+
+/// Starts recording HMD and controller poses to the given file, for replaying them through the detectors later
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn sleep_detector_start_pose_recording(path: String) -> Result<(), String> {
+    super::pose_recorder::start_recording(path.into()).await
+}
+
+/// Stops the pose recording, and returns the number of recorded poses
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn sleep_detector_stop_pose_recording() -> Result<u64, String> {
+    super::pose_recorder::stop_recording().await
+}
+
+/// Runs new detector instances over a pose recording, optionally with a different baseline or configuration.
+/// Doesn't affect the live detectors.
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn sleep_detector_replay_pose_recording(
+    path: String,
+    baseline: Option<SleepBaseline>,
+    config: Option<SleepClassifierConfig>,
+    gesture_config: Option<GestureDetectorConfig>,
+) -> Result<SleepDetectorReplayResult, String> {
+    super::pose_recorder::replay_recording(path.into(), baseline, config, gesture_config).await
+}
+
 #[tauri::command]
 #[oyasumivr_macros::command_profiling]
 pub async fn openvr_get_devices() -> Vec<OVRDevice> {
@@ -1,11 +1,11 @@
 use std::collections::HashMap;
 
 use super::models::{
-    DeviceUpdateEvent, OVRDevice, OVRDevicePose, OVRHandleType, OpenVRInputEvent,
-    TrackedDeviceClass,
+    DeviceUpdateEvent, OVRDevice, OVRDevicePose, OVRHandleType, OpenVRInputEvent, RecordedPose,
+    TrackedControllerRole, TrackedDeviceClass,
 };
-use super::{GestureDetector, SleepDetector, OVR_CONTEXT};
-use crate::utils::send_event;
+use super::{pose_recorder, GestureDetector, SleepDetector, OVR_CONTEXT};
+use crate::utils::{get_time, send_event};
 use byteorder::{ByteOrder, LE};
 use chrono::{DateTime, Duration, Utc};
 use log::error;
@@ -17,9 +17,9 @@ use strum::IntoEnumIterator;
 use tokio::sync::Mutex;
 
 static OVR_DEVICES: LazyLock<Mutex<Vec<OVRDevice>>> = LazyLock::new(|| Mutex::new(Vec::new()));
-static SLEEP_DETECTOR: LazyLock<Mutex<SleepDetector>> =
+pub static SLEEP_DETECTOR: LazyLock<Mutex<SleepDetector>> =
     LazyLock::new(|| Mutex::new(SleepDetector::new()));
-static GESTURE_DETECTOR: LazyLock<Mutex<GestureDetector>> =
+pub static GESTURE_DETECTOR: LazyLock<Mutex<GestureDetector>> =
     LazyLock::new(|| Mutex::new(GestureDetector::new()));
 static NEXT_DEVICE_REFRESH: LazyLock<Mutex<DateTime<Utc>>> =
     LazyLock::new(|| Mutex::new(DateTime::from_timestamp_millis(0).unwrap()));
@@ -293,6 +293,22 @@ async fn refresh_device_poses() {
             0.0,
         )
     };
+    // Look up the HMD and controllers, for controller gestures and the pose recording
+    let tracked_devices: Vec<(u32, TrackedDeviceClass, TrackedControllerRole)> = OVR_DEVICES
+        .lock()
+        .await
+        .iter()
+        .filter(|device| {
+            matches!(
+                device.class,
+                TrackedDeviceClass::HMD | TrackedDeviceClass::Controller
+            )
+        })
+        .map(|device| (device.index, device.class.clone(), device.role.clone()))
+        .collect();
+    let recording = pose_recorder::is_recording().await;
+    let mut recorded_poses = Vec::new();
+    let now = get_time() as u64;
     for (n, pose) in poses.iter().enumerate() {
         if pose.bDeviceIsConnected && pose.bPoseIsValid {
             let matrix = pose.mDeviceToAbsoluteTracking.m;
@@ -322,6 +338,29 @@ async fn refresh_device_poses() {
             let pos = ovr::sys::HmdVector3_t {
                 v: [matrix[0][3], matrix[1][3], matrix[2][3]],
             };
+            if let Some((index, class, role)) = tracked_devices
+                .iter()
+                .find(|(index, _, _)| *index == n as u32)
+            {
+                // Update gesture detector with hand controller poses
+                if *class == TrackedDeviceClass::Controller {
+                    GESTURE_DETECTOR
+                        .lock()
+                        .await
+                        .process_controller_pose(role, pos.v, now as u128);
+                }
+                // Record HMD and controller poses
+                if recording {
+                    recorded_poses.push(RecordedPose {
+                        time: now,
+                        index: *index,
+                        class: class.clone(),
+                        role: role.clone(),
+                        position: pos.v,
+                        quaternion: [q.x, q.y, q.z, q.w],
+                    });
+                }
+            }
             // Update sleep and gesture detectors (0 == HMD)
             if n == 0 {
                 SLEEP_DETECTOR
@@ -355,6 +394,9 @@ async fn refresh_device_poses() {
             }
         }
     }
+    if !recorded_poses.is_empty() {
+        pose_recorder::record(&recorded_poses).await;
+    }
 }
 
 async fn detect_inputs() {
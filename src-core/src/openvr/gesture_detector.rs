@@ -1,130 +1,279 @@
-use nalgebra::{Quaternion, UnitQuaternion};
+use std::collections::{HashMap, VecDeque};
 
-use crate::utils::{get_time, send_event};
+use log::{error, warn};
+use nalgebra::{Quaternion, UnitQuaternion, Vector3};
 
-use super::models::GestureDetected;
+use crate::utils::{get_data_file_path, get_time, send_event};
 
-const MAX_EVENT_AGE_MS: u128 = 5000; // 5 seconds
+use super::models::{
+    ControllerGestureConfig, Gesture, GestureDetected, GestureDetectorConfig, HeadGestureConfig,
+    TrackedControllerRole,
+};
 
+const CONFIG_FILE_NAME: &str = "gesture_detector.json";
+/// Controller poses older than this are not used for detecting gestures
+const MAX_CONTROLLER_POSE_AGE_MS: u128 = 500;
+
+/// The rotation of the head since the previous pose, around the head's own axes (in degrees)
 #[derive(Clone, Copy)]
-struct YawEvent {
+struct RotationEvent {
+    pitch: f64,
     yaw: f64,
+    roll: f64,
+    timestamp: u128,
+}
+
+#[derive(Clone, Copy)]
+struct ControllerPose {
+    position: [f32; 3],
     timestamp: u128,
 }
 
+#[derive(Clone, Copy)]
+struct HoldState {
+    since: u128,
+    detected: bool,
+}
+
 pub struct GestureDetector {
-    events: Vec<YawEvent>,
-    last_detection: u128,
+    pub config: GestureDetectorConfig,
+    events: VecDeque<RotationEvent>,
+    last_orientation: Option<UnitQuaternion<f64>>,
+    left_hand: Option<ControllerPose>,
+    right_hand: Option<ControllerPose>,
+    holds: HashMap<Gesture, HoldState>,
+    last_detections: HashMap<Gesture, u128>,
 }
 
 impl GestureDetector {
     pub fn new() -> Self {
         Self {
-            events: Vec::new(),
-            last_detection: 0,
+            config: GestureDetectorConfig::default(),
+            events: VecDeque::new(),
+            last_orientation: None,
+            left_hand: None,
+            right_hand: None,
+            holds: HashMap::new(),
+            last_detections: HashMap::new(),
+        }
+    }
+
+    pub async fn log_pose(&mut self, position: [f32; 3], quaternion: [f64; 4]) {
+        for gesture in self.process_pose(position, quaternion, get_time()) {
+            send_event("GESTURE_DETECTED", GestureDetected { gesture }).await;
         }
     }
 
-    pub async fn log_pose(&mut self, _position: [f32; 3], quaternion: [f64; 4]) {
-        // Determine yaw
-        let q = UnitQuaternion::from_quaternion(Quaternion::new(
+    /// Processes an HMD pose sampled at the given time (in milliseconds), and returns the gestures it completed.
+    /// Doesn't read the system clock, so recorded poses can be replayed on a simulated one.
+    pub fn process_pose(
+        &mut self,
+        position: [f32; 3],
+        quaternion: [f64; 4],
+        now: u128,
+    ) -> Vec<Gesture> {
+        let orientation = UnitQuaternion::from_quaternion(Quaternion::new(
             quaternion[3],
             quaternion[0],
             quaternion[1],
             quaternion[2],
         ));
-        let yaw = (2.0 * q.as_ref().imag().y.atan2(q.as_ref().scalar()))
-            * (180.0 / std::f64::consts::PI)
-            + 180.0;
-        // Log yaw event
-        let event = YawEvent {
-            yaw,
-            timestamp: get_time(),
-        };
-        self.events.push(event);
+        // Log the rotation relative to the head's previous orientation, so nodding and tilting are recognised
+        // regardless of which way the user is facing
+        if let Some(last_orientation) = self.last_orientation.replace(orientation) {
+            let rotation = (last_orientation.inverse() * orientation).scaled_axis();
+            let to_degrees = 180.0 / std::f64::consts::PI;
+            self.events.push_back(RotationEvent {
+                pitch: rotation.x * to_degrees,
+                yaw: rotation.y * to_degrees,
+                roll: rotation.z * to_degrees,
+                timestamp: now,
+            });
+        }
         // Remove old events
-        let oldest_time = event.timestamp - MAX_EVENT_AGE_MS;
-        let old_event_count = self
+        let max_event_age = [
+            &self.config.head_shake,
+            &self.config.head_nod,
+            &self.config.head_tilt,
+        ]
+        .iter()
+        .map(|config| config.window_ms as u128)
+        .max()
+        .unwrap_or_default();
+        let oldest_time = now.saturating_sub(max_event_age);
+        while self
             .events
+            .front()
+            .is_some_and(|e| e.timestamp < oldest_time)
+        {
+            self.events.pop_front();
+        }
+        // Detect gestures
+        let mut gestures = Vec::new();
+        let head_gestures: [(Gesture, &HeadGestureConfig); 3] = [
+            (Gesture::HeadShake, &self.config.head_shake),
+            (Gesture::HeadNod, &self.config.head_nod),
+            (Gesture::HeadTilt, &self.config.head_tilt),
+        ];
+        for (gesture, config) in head_gestures {
+            if !config.enabled || !self.is_cooled_down(gesture, config.cooldown_ms, now) {
+                continue;
+            }
+            let window_start = now.saturating_sub(config.window_ms as u128);
+            let movements: Vec<f64> = self
+                .events
+                .iter()
+                .filter(|e| e.timestamp >= window_start)
+                .map(|e| match gesture {
+                    Gesture::HeadNod => e.pitch,
+                    Gesture::HeadTilt => e.roll,
+                    _ => e.yaw,
+                })
+                .collect();
+            if detect_oscillation(movements, config.threshold_degrees, config.repeat_count) {
+                gestures.push(gesture);
+            }
+        }
+        let head = Vector3::new(position[0], position[1], position[2]);
+        let hands: Vec<Vector3<f32>> = [self.left_hand, self.right_hand]
+            .into_iter()
+            .flatten()
+            .filter(|hand| now.saturating_sub(hand.timestamp) <= MAX_CONTROLLER_POSE_AGE_MS)
+            .map(|hand| Vector3::new(hand.position[0], hand.position[1], hand.position[2]))
+            .collect();
+        let config = self.config.hands_above_head.clone();
+        let hands_above_head = hands.len() == 2
+            && hands
+                .iter()
+                .all(|hand| (hand.y - head.y) as f64 >= config.distance_meters);
+        if self.detect_hold(Gesture::HandsAboveHead, &config, hands_above_head, now) {
+            gestures.push(Gesture::HandsAboveHead);
+        }
+        let config = self.config.hand_covering_face.clone();
+        let hand_covering_face = hands
             .iter()
-            .take_while(|e| e.timestamp < oldest_time)
-            .count();
-        self.events.drain(..old_event_count);
-        // Convert events to relative movements
-        let mut movements = Vec::new();
-        for i in 0..self.events.len() - 1 {
-            let yaw1 = self.events[i].yaw;
-            let yaw2 = self.events[i + 1].yaw;
-            let yaw_diff = yaw2 - yaw1;
-            let yaw_diff = if yaw_diff > 180.0 {
-                yaw_diff - 360.0
-            } else if yaw_diff < -180.0 {
-                yaw_diff + 360.0
-            } else {
-                yaw_diff
-            };
-            movements.push(yaw_diff);
+            .any(|hand| ((hand - head).norm() as f64) <= config.distance_meters);
+        if self.detect_hold(Gesture::HandCoveringFace, &config, hand_covering_face, now) {
+            gestures.push(Gesture::HandCoveringFace);
         }
-        // Detect head shake
-        if get_time() - self.last_detection >= 5000 && self.detect_head_shake(movements) {
-            self.last_detection = get_time();
-            send_event(
-                "GESTURE_DETECTED",
-                GestureDetected {
-                    gesture: "head_shake".to_string(),
-                },
-            )
-            .await;
+        for gesture in &gestures {
+            self.last_detections.insert(*gesture, now);
         }
+        gestures
     }
 
-    fn detect_head_shake(&self, movements: Vec<f64>) -> bool {
-        let mut data = movements;
-        let mut offset_dir = 1.0;
-        let mut change: Option<usize>;
-        let change_dir_a = self.detect_angular_change(data.clone(), -15.0);
-        let change_dir_b = self.detect_angular_change(data.clone(), 15.0);
-        if change_dir_a.is_some() {
-            change = change_dir_a;
-        } else if change_dir_b.is_some() {
-            change = change_dir_b;
-            offset_dir = -1.0;
-        } else {
-            return false;
+    /// Keeps track of the position of a hand controller, for detecting controller gestures
+    pub fn process_controller_pose(
+        &mut self,
+        role: &TrackedControllerRole,
+        position: [f32; 3],
+        now: u128,
+    ) {
+        let pose = Some(ControllerPose {
+            position,
+            timestamp: now,
+        });
+        match role {
+            TrackedControllerRole::LeftHand => self.left_hand = pose,
+            TrackedControllerRole::RightHand => self.right_hand = pose,
+            _ => {}
         }
-        data = data[change.unwrap()..].to_vec();
-        change = self.detect_angular_change(data.clone(), 30.0 * offset_dir);
-        if change.is_none() {
+    }
+
+    fn is_cooled_down(&self, gesture: Gesture, cooldown_ms: u64, now: u128) -> bool {
+        self.last_detections
+            .get(&gesture)
+            .is_none_or(|last| now.saturating_sub(*last) >= cooldown_ms as u128)
+    }
+
+    /// Detects a position being held for long enough. Only detects once per hold.
+    fn detect_hold(
+        &mut self,
+        gesture: Gesture,
+        config: &ControllerGestureConfig,
+        holding: bool,
+        now: u128,
+    ) -> bool {
+        if !config.enabled || !holding {
+            self.holds.remove(&gesture);
             return false;
         }
-        data = data[change.unwrap()..].to_vec();
-        change = self.detect_angular_change(data, -15.0 * offset_dir);
-        if change.is_none() {
+        let cooled_down = self.is_cooled_down(gesture, config.cooldown_ms, now);
+        let hold = self.holds.entry(gesture).or_insert(HoldState {
+            since: now,
+            detected: false,
+        });
+        if hold.detected || !cooled_down || now - hold.since < config.hold_ms as u128 {
             return false;
         }
+        hold.detected = true;
         true
     }
+}
 
-    fn detect_angular_change(&self, mut data: Vec<f64>, mut offset: f64) -> Option<usize> {
-        let mut delta = 0.0;
-        // Flip data if we're looking for a negative offset
-        if offset < 0.0 {
-            data = data.iter().map(|x| -x).collect();
-            offset *= -1.0;
+/// Reads the configuration stored by a previous session, if any
+pub async fn load_config() -> Option<GestureDetectorConfig> {
+    let path = get_data_file_path(CONFIG_FILE_NAME).await?;
+    let contents = tokio::fs::read_to_string(&path).await.ok()?;
+    match serde_json::from_str(&contents) {
+        Ok(config) => Some(config),
+        Err(e) => {
+            warn!("[Core] Could not read gesture detector configuration: {e}");
+            None
         }
-        // Loop over all data points
-        for (i, item) in data.iter().enumerate() {
-            delta += item;
-            // if delta is negative, reset to 0
-            if delta < 0.0 {
-                delta = 0.0;
-            }
-            // If we have passed the given offset, angular change has been detected
-            if delta >= offset {
-                return Some(i);
+    }
+}
+
+pub async fn save_config(config: &GestureDetectorConfig) {
+    let Some(path) = get_data_file_path(CONFIG_FILE_NAME).await else {
+        return;
+    };
+    if let Err(e) = tokio::fs::write(&path, serde_json::to_string(config).unwrap()).await {
+        error!("[Core] Could not write gesture detector configuration: {e}");
+    }
+}
+
+/// Detects the head rotating to one side, back to the other side and back to the center again, for the given number
+/// of times, based on the rotation between consecutive poses.
+fn detect_oscillation(movements: Vec<f64>, threshold: f64, repeat_count: u32) -> bool {
+    // The first and last swings go from and to the center, the ones in between go all the way to the other side
+    let mut swings = vec![threshold];
+    swings.extend(std::iter::repeat_n(
+        threshold * 2.0,
+        (repeat_count.max(1) * 2 - 1) as usize,
+    ));
+    swings.push(threshold);
+    [1.0, -1.0].into_iter().any(|initial_direction| {
+        let mut data = &movements[..];
+        let mut direction = initial_direction;
+        for swing in &swings {
+            match detect_angular_change(data, swing * direction) {
+                Some(change) => data = &data[change..],
+                None => return false,
             }
+            direction *= -1.0;
+        }
+        true
+    })
+}
+
+fn detect_angular_change(data: &[f64], offset: f64) -> Option<usize> {
+    let mut delta = 0.0;
+    // Flip data if we're looking for a negative offset
+    let sign = if offset < 0.0 { -1.0 } else { 1.0 };
+    let offset = offset * sign;
+    // Loop over all data points
+    for (i, item) in data.iter().enumerate() {
+        delta += item * sign;
+        // if delta is negative, reset to 0
+        if delta < 0.0 {
+            delta = 0.0;
+        }
+        // If we have passed the given offset, angular change has been detected
+        if delta >= offset {
+            return Some(i);
         }
-        // Desired angular change could not be found
-        None
     }
+    // Desired angular change could not be found
+    None
 }
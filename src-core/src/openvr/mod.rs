@@ -7,11 +7,17 @@ mod devices;
 mod framelimiter;
 mod gesture_detector;
 mod models;
+mod pose_recorder;
+mod pose_statistics;
+#[cfg(test)]
+mod pose_statistics_benchmark;
+mod sleep_classifier;
 mod sleep_detector;
 mod supersampling;
 
 use crate::{
     globals::STEAM_APP_KEY,
+    lighthouse::groups::{self as lighthouse_groups, OpenVRTransition},
     openvr::models::{OpenVRAction, OpenVRActionSet},
     utils::send_event,
 };
@@ -41,6 +47,16 @@ pub static OVR_INPUT_CONTEXT: LazyLock<Mutex<OpenVRInputContext>> = LazyLock::ne
 static OVR_INIT_DELAY_FIX: LazyLock<Mutex<bool>> = LazyLock::new(|| Mutex::new(false));
 
 pub async fn init() {
+    // Restore the sleep detector calibration and gesture configuration from the previous session
+    if let Some(settings) = sleep_detector::load_settings().await {
+        devices::SLEEP_DETECTOR
+            .lock()
+            .await
+            .apply_settings(settings);
+    }
+    if let Some(config) = gesture_detector::load_config().await {
+        devices::GESTURE_DETECTOR.lock().await.config = config;
+    }
     *OVR_ACTIVE.lock().await = true;
     tokio::spawn(task());
 }
@@ -292,7 +308,19 @@ pub async fn task() {
 
 async fn update_status(new_status: OpenVRStatus) {
     let mut status = OVR_STATUS.lock().await;
-    *status = new_status.clone();
+    let previous_status = std::mem::replace(&mut *status, new_status.clone());
+    drop(status);
+    // Only count SteamVR as stopped if it was actually running, not when an initialization attempt fails
+    let transition = match (&previous_status, &new_status) {
+        (previous, OpenVRStatus::Initialized) if *previous != OpenVRStatus::Initialized => {
+            Some(OpenVRTransition::Started)
+        }
+        (OpenVRStatus::Initialized, OpenVRStatus::Inactive) => Some(OpenVRTransition::Stopped),
+        _ => None,
+    };
+    if let Some(transition) = transition {
+        lighthouse_groups::on_openvr_transition(transition).await;
+    }
     let status_str = serde_json::to_string(&new_status).unwrap();
     send_event(
         "OVR_STATUS_UPDATE",
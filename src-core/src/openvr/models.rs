@@ -34,7 +34,7 @@ pub struct OpenVRInputEvent {
     pub device: Option<OVRDevice>,
 }
 
-#[derive(Serialize, Clone)]
+#[derive(Serialize, Clone, PartialEq)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum OpenVRStatus {
     Inactive,
@@ -220,12 +220,222 @@ pub struct SleepDetectorStateReport {
     pub rotation_in_last_10_seconds: f64,
     pub start_time: u128,
     pub last_log: u128,
+    pub classification: SleepClassification,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum SleepState {
+    Awake,
+    Drowsy,
+    Asleep,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SleepClassification {
+    pub state: SleepState,
+    /// When the current state was entered
+    pub since: u128,
+    /// Sleep likelihood from 0.0 to 1.0, if the detector has been running long enough to tell
+    pub score: Option<f64>,
+}
+
+/// The movement rates of a user while lying still and while awake
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SleepBaseline {
+    pub resting_distance_per_second: f64,
+    pub resting_rotation_per_second: f64,
+    pub awake_distance_per_second: f64,
+    pub awake_rotation_per_second: f64,
+}
+
+impl Default for SleepBaseline {
+    fn default() -> Self {
+        Self {
+            resting_distance_per_second: 0.001,
+            resting_rotation_per_second: 0.05,
+            awake_distance_per_second: 0.05,
+            awake_rotation_per_second: 5.0,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SleepClassifierConfig {
+    pub drowsy_threshold: f64,
+    pub asleep_threshold: f64,
+    /// How far the score has to drop below a threshold to leave its state again
+    pub hysteresis_margin: f64,
+    /// How long the score has to stay above the asleep threshold to enter the asleep state
+    pub asleep_hold_ms: u64,
+    /// How long the detector has to be running before the user is classified
+    pub warmup_ms: u64,
+}
+
+impl Default for SleepClassifierConfig {
+    fn default() -> Self {
+        Self {
+            drowsy_threshold: 0.5,
+            asleep_threshold: 0.8,
+            hysteresis_margin: 0.15,
+            asleep_hold_ms: 120000,
+            warmup_ms: 60000,
+        }
+    }
+}
+
+/// The calibration and configuration of the sleep detector, as stored between sessions
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SleepDetectorSettings {
+    pub baseline: SleepBaseline,
+    pub classifier_config: SleepClassifierConfig,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum SleepCalibrationLabel {
+    /// The user is lying still
+    Resting,
+    /// The user is awake and moving around normally
+    Awake,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Gesture {
+    /// Turning the head left and right
+    HeadShake,
+    /// Moving the head up and down
+    HeadNod,
+    /// Tilting the head towards the shoulders
+    HeadTilt,
+    /// Holding both controllers above the head
+    HandsAboveHead,
+    /// Holding a controller in front of the face
+    HandCoveringFace,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GestureDetected {
-    pub gesture: String,
+    pub gesture: Gesture,
+}
+
+/// A gesture made by rotating the head back and forth around a single axis
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HeadGestureConfig {
+    pub enabled: bool,
+    /// How far the head has to rotate to either side, in degrees
+    pub threshold_degrees: f64,
+    /// How many times the head has to move back and forth
+    pub repeat_count: u32,
+    /// The time in which the full gesture has to be made
+    pub window_ms: u64,
+    /// The minimum time between two detections of this gesture
+    pub cooldown_ms: u64,
+}
+
+/// A gesture made by holding the controllers in a position relative to the head
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ControllerGestureConfig {
+    pub enabled: bool,
+    /// For hands above head: how far above the head the controllers have to be.
+    /// For hand covering face: how close to the head a controller has to be.
+    pub distance_meters: f64,
+    /// How long the position has to be held
+    pub hold_ms: u64,
+    /// The minimum time between two detections of this gesture
+    pub cooldown_ms: u64,
+}
+
+/// Gestures missing from a stored configuration get their default configuration
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GestureDetectorConfig {
+    pub head_shake: HeadGestureConfig,
+    pub head_nod: HeadGestureConfig,
+    pub head_tilt: HeadGestureConfig,
+    pub hands_above_head: ControllerGestureConfig,
+    pub hand_covering_face: ControllerGestureConfig,
+}
+
+impl Default for GestureDetectorConfig {
+    fn default() -> Self {
+        Self {
+            head_shake: HeadGestureConfig {
+                enabled: true,
+                threshold_degrees: 15.0,
+                repeat_count: 1,
+                window_ms: 5000,
+                cooldown_ms: 5000,
+            },
+            head_nod: HeadGestureConfig {
+                enabled: true,
+                threshold_degrees: 10.0,
+                repeat_count: 1,
+                window_ms: 3000,
+                cooldown_ms: 5000,
+            },
+            head_tilt: HeadGestureConfig {
+                enabled: true,
+                threshold_degrees: 15.0,
+                repeat_count: 1,
+                window_ms: 5000,
+                cooldown_ms: 5000,
+            },
+            hands_above_head: ControllerGestureConfig {
+                enabled: true,
+                distance_meters: 0.1,
+                hold_ms: 1000,
+                cooldown_ms: 5000,
+            },
+            hand_covering_face: ControllerGestureConfig {
+                enabled: true,
+                distance_meters: 0.15,
+                hold_ms: 1500,
+                cooldown_ms: 5000,
+            },
+        }
+    }
+}
+
+/// A single line in a pose recording file
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordedPose {
+    /// In milliseconds
+    pub time: u64,
+    pub index: u32,
+    pub class: TrackedDeviceClass,
+    pub role: TrackedControllerRole,
+    pub position: [f32; 3],
+    pub quaternion: [f64; 4],
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayedGesture {
+    pub gesture: Gesture,
+    pub time: u64,
+}
+
+/// The outcome of running the sleep and gesture detectors over a pose recording
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SleepDetectorReplayResult {
+    /// The number of HMD poses fed to the detectors
+    pub poses: u64,
+    pub start_time: u64,
+    pub end_time: u64,
+    pub state_changes: Vec<SleepClassification>,
+    pub gestures: Vec<ReplayedGesture>,
+    pub final_classification: SleepClassification,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -0,0 +1,256 @@
+use std::{path::PathBuf, sync::LazyLock};
+
+use log::{error, info, warn};
+use tokio::{
+    fs::File,
+    io::{AsyncWriteExt, BufWriter},
+    sync::Mutex,
+};
+
+use super::{
+    gesture_detector::GestureDetector,
+    models::{
+        GestureDetectorConfig, RecordedPose, ReplayedGesture, SleepBaseline, SleepClassifierConfig,
+        SleepDetectorReplayResult, TrackedDeviceClass,
+    },
+    sleep_detector::SleepDetector,
+};
+
+static RECORDING: LazyLock<Mutex<Option<Recording>>> = LazyLock::new(Default::default);
+
+struct Recording {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    poses: u64,
+}
+
+/// Starts writing the poses of the HMD and controllers to the given file (as JSON lines), replacing any existing file.
+pub async fn start_recording(path: PathBuf) -> Result<(), String> {
+    let mut recording = RECORDING.lock().await;
+    if recording.is_some() {
+        return Err(String::from("ALREADY_RECORDING"));
+    }
+    let file = File::create(&path).await.map_err(|e| {
+        error!("[Core] Could not create pose recording file: {e}");
+        String::from("WRITE_ERROR")
+    })?;
+    info!("[Core] Recording device poses to {}", path.display());
+    *recording = Some(Recording {
+        path,
+        writer: BufWriter::new(file),
+        poses: 0,
+    });
+    Ok(())
+}
+
+/// Stops the active recording, and returns the number of poses it contains
+pub async fn stop_recording() -> Result<u64, String> {
+    let mut recording = match RECORDING.lock().await.take() {
+        Some(recording) => recording,
+        None => return Err(String::from("NOT_RECORDING")),
+    };
+    if let Err(e) = recording.writer.flush().await {
+        error!("[Core] Could not write pose recording file: {e}");
+        return Err(String::from("WRITE_ERROR"));
+    }
+    info!(
+        "[Core] Stopped recording device poses. Recorded {} poses to {}",
+        recording.poses,
+        recording.path.display()
+    );
+    Ok(recording.poses)
+}
+
+pub async fn is_recording() -> bool {
+    RECORDING.lock().await.is_some()
+}
+
+pub async fn record(poses: &[RecordedPose]) {
+    let mut recording_guard = RECORDING.lock().await;
+    let recording = match recording_guard.as_mut() {
+        Some(recording) => recording,
+        None => return,
+    };
+    let mut lines = String::new();
+    for pose in poses {
+        lines.push_str(&serde_json::to_string(pose).unwrap());
+        lines.push('\n');
+    }
+    if let Err(e) = recording.writer.write_all(lines.as_bytes()).await {
+        error!("[Core] Could not write to pose recording file, stopping recording: {e}");
+        *recording_guard = None;
+        return;
+    }
+    recording.poses += poses.len() as u64;
+}
+
+/// Runs a fresh sleep detector and gesture detector over a recording, as fast as possible.
+/// The recorded timestamps are used as the clock, so the result doesn't depend on when or how fast it is replayed.
+pub async fn replay_recording(
+    path: PathBuf,
+    baseline: Option<SleepBaseline>,
+    config: Option<SleepClassifierConfig>,
+    gesture_config: Option<GestureDetectorConfig>,
+) -> Result<SleepDetectorReplayResult, String> {
+    let contents = tokio::fs::read_to_string(&path)
+        .await
+        .map_err(|_| String::from("READ_ERROR"))?;
+    let poses: Vec<RecordedPose> = contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| match serde_json::from_str(line) {
+            Ok(pose) => Some(pose),
+            Err(_) => {
+                warn!("[Core] Skipping unreadable entry in pose recording");
+                None
+            }
+        })
+        .collect();
+    tokio::task::spawn_blocking(move || replay(&poses, baseline, config, gesture_config))
+        .await
+        .map_err(|_| String::from("REPLAY_FAILED"))
+}
+
+/// Feeds the poses of a recording through new detector instances, in order
+pub fn replay(
+    poses: &[RecordedPose],
+    baseline: Option<SleepBaseline>,
+    config: Option<SleepClassifierConfig>,
+    gesture_config: Option<GestureDetectorConfig>,
+) -> SleepDetectorReplayResult {
+    let mut sleep_detector = SleepDetector::new();
+    let mut gesture_detector = GestureDetector::new();
+    if let Some(baseline) = baseline {
+        sleep_detector.set_baseline(baseline);
+    }
+    if let Some(config) = config {
+        sleep_detector.set_classifier_config(config);
+    }
+    if let Some(gesture_config) = gesture_config {
+        gesture_detector.config = gesture_config;
+    }
+    let mut result = SleepDetectorReplayResult {
+        poses: 0,
+        start_time: 0,
+        end_time: 0,
+        state_changes: Vec::new(),
+        gestures: Vec::new(),
+        final_classification: sleep_detector.classification(),
+    };
+    for pose in poses {
+        let now = pose.time as u128;
+        if pose.class == TrackedDeviceClass::Controller {
+            gesture_detector.process_controller_pose(&pose.role, pose.position, now);
+            continue;
+        }
+        if pose.class != TrackedDeviceClass::HMD {
+            continue;
+        }
+        if result.poses == 0 {
+            result.start_time = pose.time;
+        }
+        result.end_time = pose.time;
+        result.poses += 1;
+        let output = sleep_detector.process_pose(pose.position, pose.quaternion, now);
+        if let Some(classification) = output.state_change {
+            result.state_changes.push(classification);
+        }
+        for gesture in gesture_detector.process_pose(pose.position, pose.quaternion, now) {
+            result.gestures.push(ReplayedGesture {
+                gesture,
+                time: pose.time,
+            });
+        }
+    }
+    result.final_classification = sleep_detector.classification();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::replay;
+    use crate::openvr::models::{
+        Gesture, GestureDetectorConfig, RecordedPose, SleepClassifierConfig,
+        SleepDetectorReplayResult, SleepState,
+    };
+
+    fn recording() -> Vec<RecordedPose> {
+        include_str!("fixtures/pose_recording.jsonl")
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    /// The gestures in the replay result, with their time in seconds since the start of the recording
+    fn gestures(result: &SleepDetectorReplayResult) -> Vec<(Gesture, f64)> {
+        result
+            .gestures
+            .iter()
+            .map(|g| (g.gesture, (g.time - result.start_time) as f64 / 1000.0))
+            .collect()
+    }
+
+    #[test]
+    fn replays_state_changes_and_gestures() {
+        let result = replay(&recording(), None, None, None);
+        assert_eq!(result.poses, 1455);
+        assert_eq!(result.end_time - result.start_time, 1198000);
+
+        // The user lies still from 90 seconds in, and only counts as asleep once the movement from before has
+        // mostly left the longer windows
+        let states: Vec<(SleepState, u64)> = result
+            .state_changes
+            .iter()
+            .map(|c| (c.state, (c.since as u64 - result.start_time) / 1000))
+            .collect();
+        assert_eq!(
+            states,
+            vec![(SleepState::Drowsy, 354), (SleepState::Asleep, 824)]
+        );
+        assert_eq!(result.final_classification.state, SleepState::Asleep);
+
+        // Every gesture is made once, and recognised once it completes (or has been held long enough)
+        let gestures = gestures(&result);
+        let expected = [
+            (Gesture::HeadShake, 20.0, 22.0),
+            (Gesture::HeadNod, 35.0, 36.2),
+            (Gesture::HeadTilt, 50.0, 52.0),
+            (Gesture::HandsAboveHead, 63.0, 64.0),
+            (Gesture::HandCoveringFace, 71.5, 72.5),
+        ];
+        assert_eq!(gestures.len(), expected.len());
+        for ((gesture, time), (expected_gesture, from, to)) in gestures.into_iter().zip(expected) {
+            assert_eq!(gesture, expected_gesture);
+            assert!(
+                (from..=to).contains(&time),
+                "{gesture:?} recognised at {time}s"
+            );
+        }
+    }
+
+    #[test]
+    fn replays_with_a_different_configuration() {
+        let config = SleepClassifierConfig {
+            asleep_threshold: 0.95,
+            ..Default::default()
+        };
+        let mut gesture_config = GestureDetectorConfig::default();
+        gesture_config.head_nod.enabled = false;
+        gesture_config.hand_covering_face.enabled = false;
+        let result = replay(&recording(), None, Some(config), Some(gesture_config));
+
+        let states: Vec<SleepState> = result.state_changes.iter().map(|c| c.state).collect();
+        assert_eq!(states, vec![SleepState::Drowsy, SleepState::Asleep]);
+        assert!(result.state_changes[1].since as u64 - result.start_time > 824000);
+
+        let gestures: Vec<Gesture> = gestures(&result).into_iter().map(|(g, _)| g).collect();
+        assert_eq!(
+            gestures,
+            vec![
+                Gesture::HeadShake,
+                Gesture::HeadTilt,
+                Gesture::HandsAboveHead
+            ]
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::VecDeque;
+
+/// The windows (in milliseconds) that movement is summed over, from shortest to longest
+pub const POSE_STATISTICS_WINDOWS: [u128; 5] = [10000, 60000, 300000, 600000, 900000];
+/// Upper bound on the number of segments kept, which corresponds to the longest window at 120 Hz.
+/// Each segment takes 32 bytes, so the buffer never exceeds ~3.5 MB. At the OpenVR tick rate (~30 Hz) it stays
+/// around 0.9 MB, and at 90 Hz around 2.6 MB.
+const MAX_SEGMENTS: usize = 900 * 120;
+
+#[derive(Clone, Copy)]
+struct PoseSample {
+    position: [f32; 3],
+    quaternion: [f64; 4],
+    timestamp: u128,
+}
+
+impl PoseSample {
+    fn distance_to(&self, other: &PoseSample) -> f64 {
+        let dx: f64 = (self.position[0] - other.position[0]).into();
+        let dy: f64 = (self.position[1] - other.position[1]).into();
+        let dz: f64 = (self.position[2] - other.position[2]).into();
+        (dx * dx + dy * dy + dz * dz).sqrt()
+    }
+
+    fn angular_distance_degrees(&self, other: &PoseSample) -> f64 {
+        let q1 = self.quaternion;
+        let q2 = other.quaternion;
+        let dot_product = q1[0] * q2[0] + q1[1] * q2[1] + q1[2] * q2[2] + q1[3] * q2[3];
+        let angle = 2.0 * dot_product.abs().clamp(-1.0, 1.0).acos();
+
+        angle * 180.0 / std::f64::consts::PI
+    }
+}
+
+/// The movement between two consecutive samples
+struct Segment {
+    /// The timestamp of the first of the two samples
+    start: u128,
+    distance: f64,
+    rotation: f64,
+}
+
+#[derive(Default, Clone, Copy)]
+struct WindowSum {
+    /// The number of most recent segments that fall within the window
+    segments: usize,
+    distance: f64,
+    rotation: f64,
+}
+
+/// Keeps the total distance and rotation travelled within several sliding time windows.
+///
+/// Instead of storing poses and summing them per window, the movement between consecutive poses is stored once,
+/// and every window keeps a running sum over its most recent segments. Adding a pose adds one segment to each sum,
+/// and subtracts the segments that have slid out of each window. Every segment is added and removed only once per
+/// window, so each pose costs constant (amortized) time, regardless of the sample rate.
+pub struct WindowedPoseStatistics {
+    segments: VecDeque<Segment>,
+    windows: [WindowSum; POSE_STATISTICS_WINDOWS.len()],
+    last_sample: Option<PoseSample>,
+}
+
+impl WindowedPoseStatistics {
+    pub fn new() -> Self {
+        Self {
+            segments: VecDeque::new(),
+            windows: Default::default(),
+            last_sample: None,
+        }
+    }
+
+    pub fn add_pose(&mut self, position: [f32; 3], quaternion: [f64; 4], now: u128) {
+        let sample = PoseSample {
+            position,
+            quaternion,
+            timestamp: now,
+        };
+        if let Some(last_sample) = self.last_sample.replace(sample) {
+            let segment = Segment {
+                start: last_sample.timestamp,
+                distance: last_sample.distance_to(&sample),
+                rotation: last_sample.angular_distance_degrees(&sample),
+            };
+            for window in self.windows.iter_mut() {
+                window.segments += 1;
+                window.distance += segment.distance;
+                window.rotation += segment.rotation;
+            }
+            self.segments.push_back(segment);
+        }
+        // Slide every window forward, dropping the segments that started before it
+        for (window, window_ms) in self.windows.iter_mut().zip(POSE_STATISTICS_WINDOWS) {
+            let window_start = now.saturating_sub(window_ms);
+            while window.segments > 0 {
+                let segment = &self.segments[self.segments.len() - window.segments];
+                if segment.start >= window_start && window.segments <= MAX_SEGMENTS {
+                    break;
+                }
+                window.segments -= 1;
+                window.distance -= segment.distance;
+                window.rotation -= segment.rotation;
+            }
+            // Running sums can drift slightly below zero due to floating point error
+            if window.segments == 0 {
+                window.distance = 0.0;
+                window.rotation = 0.0;
+            }
+        }
+        // Only keep the segments that are still part of the longest window
+        let longest_window = self.windows[self.windows.len() - 1].segments;
+        self.segments.drain(..self.segments.len() - longest_window);
+    }
+
+    /// Total distance (in meters) travelled within the given window
+    pub fn distance_in_window(&self, window_ms: u128) -> f64 {
+        self.window(window_ms).distance.max(0.0)
+    }
+
+    /// Total rotation (in degrees) within the given window
+    pub fn rotation_in_window(&self, window_ms: u128) -> f64 {
+        self.window(window_ms).rotation.max(0.0)
+    }
+
+    fn window(&self, window_ms: u128) -> WindowSum {
+        POSE_STATISTICS_WINDOWS
+            .iter()
+            .position(|w| *w == window_ms)
+            .map(|i| self.windows[i])
+            .expect("window is not tracked by WindowedPoseStatistics")
+    }
+}
@@ -0,0 +1,140 @@
+//! Compares the cost of processing a single pose with the previous and current windowed movement statistics.
+//! Takes several seconds, so it only runs when asked for:
+//! `cargo test --release pose_statistics_benchmark -- --ignored --nocapture`
+
+use std::time::Instant;
+
+use super::pose_statistics::{WindowedPoseStatistics, POSE_STATISTICS_WINDOWS};
+
+/// How much history is simulated before measuring, so every window is completely filled
+const WARMUP_MS: u128 = 900000;
+/// How many samples are measured per implementation
+const MEASURED_SAMPLES: usize = 500;
+
+/// The previous approach: keep every pose of the last 15 minutes, and sum each window with a linear scan
+struct LinearPoseStatistics {
+    events: Vec<([f32; 3], [f64; 4], u128)>,
+}
+
+impl LinearPoseStatistics {
+    fn push(&mut self, position: [f32; 3], quaternion: [f64; 4], now: u128) {
+        self.events.push((position, quaternion, now));
+        let oldest_time = now.saturating_sub(900000);
+        let old_event_count = self.events.iter().take_while(|e| e.2 < oldest_time).count();
+        self.events.drain(..old_event_count);
+    }
+
+    fn add_pose(&mut self, position: [f32; 3], quaternion: [f64; 4], now: u128) -> f64 {
+        self.push(position, quaternion, now);
+        let mut total = 0.0;
+        for window_ms in POSE_STATISTICS_WINDOWS {
+            let start_time = now.saturating_sub(window_ms);
+            let start_index = self
+                .events
+                .iter()
+                .position(|e| e.2 >= start_time)
+                .unwrap_or(0);
+            let events = &self.events[start_index..];
+            for pair in events.windows(2) {
+                let (a, b) = (pair[0], pair[1]);
+                let dx: f64 = (a.0[0] - b.0[0]).into();
+                let dy: f64 = (a.0[1] - b.0[1]).into();
+                let dz: f64 = (a.0[2] - b.0[2]).into();
+                total += (dx * dx + dy * dy + dz * dz).sqrt();
+                let dot = a.1[0] * b.1[0] + a.1[1] * b.1[1] + a.1[2] * b.1[2] + a.1[3] * b.1[3];
+                total += 2.0 * dot.abs().clamp(-1.0, 1.0).acos();
+            }
+        }
+        total
+    }
+}
+
+struct BenchmarkResult {
+    sample_rate_hz: u32,
+    /// The number of poses already in the 15 minute history when measuring
+    history_samples: u64,
+    /// Cost per pose of the previous approach, which scanned the full history for every window
+    linear_nanos_per_sample: u64,
+    /// Cost per pose of the incremental window sums used by the sleep detector
+    incremental_nanos_per_sample: u64,
+}
+
+/// A slowly drifting head pose, so every sample moves a little
+fn simulated_pose(i: usize) -> ([f32; 3], [f64; 4]) {
+    let t = i as f64 * 0.01;
+    let position = [
+        (t.sin() * 0.01) as f32,
+        1.6 + (t.cos() * 0.01) as f32,
+        ((t * 0.3).sin() * 0.01) as f32,
+    ];
+    let half_angle = (t * 0.5).sin() * 0.05;
+    let quaternion = [0.0, half_angle.sin(), 0.0, half_angle.cos()];
+    (position, quaternion)
+}
+
+/// Measures the cost of adding a single pose with both the previous (linear scan) approach and the current
+/// (incremental) approach, with 15 minutes of history, at the given sample rates.
+fn run(sample_rates_hz: &[u32]) -> Vec<BenchmarkResult> {
+    sample_rates_hz
+        .iter()
+        .map(|&sample_rate_hz| {
+            let interval_ms = 1000.0 / sample_rate_hz as f64;
+            let time_of = |i: usize| (i as f64 * interval_ms) as u128;
+            let warmup_samples = (WARMUP_MS as f64 / interval_ms) as usize;
+            // Previous approach. Scanning during the warmup would take too long, so the history is filled directly.
+            let mut linear = LinearPoseStatistics { events: Vec::new() };
+            for i in 0..warmup_samples {
+                let (position, quaternion) = simulated_pose(i);
+                linear.push(position, quaternion, time_of(i));
+            }
+            let start = Instant::now();
+            let mut checksum = 0.0;
+            for i in warmup_samples..warmup_samples + MEASURED_SAMPLES {
+                let (position, quaternion) = simulated_pose(i);
+                checksum += linear.add_pose(position, quaternion, time_of(i));
+            }
+            let linear_nanos = start.elapsed().as_nanos();
+            std::hint::black_box(checksum);
+            // Current approach
+            let mut incremental = WindowedPoseStatistics::new();
+            for i in 0..warmup_samples {
+                let (position, quaternion) = simulated_pose(i);
+                incremental.add_pose(position, quaternion, time_of(i));
+            }
+            let start = Instant::now();
+            let mut checksum = 0.0;
+            for i in warmup_samples..warmup_samples + MEASURED_SAMPLES {
+                let (position, quaternion) = simulated_pose(i);
+                incremental.add_pose(position, quaternion, time_of(i));
+                for window_ms in POSE_STATISTICS_WINDOWS {
+                    checksum += incremental.distance_in_window(window_ms);
+                    checksum += incremental.rotation_in_window(window_ms);
+                }
+            }
+            let incremental_nanos = start.elapsed().as_nanos();
+            std::hint::black_box(checksum);
+            BenchmarkResult {
+                sample_rate_hz,
+                history_samples: warmup_samples as u64,
+                linear_nanos_per_sample: (linear_nanos / MEASURED_SAMPLES as u128) as u64,
+                incremental_nanos_per_sample: (incremental_nanos / MEASURED_SAMPLES as u128) as u64,
+            }
+        })
+        .collect()
+}
+
+/// At the OpenVR tick rate (30 Hz) and a typical HMD refresh rate (90 Hz)
+#[test]
+#[ignore = "benchmark, takes several seconds"]
+fn pose_statistics_benchmark() {
+    for result in run(&[30, 90]) {
+        println!(
+            "{} Hz, {} poses of history: linear {} ns/pose, incremental {} ns/pose",
+            result.sample_rate_hz,
+            result.history_samples,
+            result.linear_nanos_per_sample,
+            result.incremental_nanos_per_sample
+        );
+        assert!(result.incremental_nanos_per_sample < result.linear_nanos_per_sample);
+    }
+}
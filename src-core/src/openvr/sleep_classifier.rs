@@ -0,0 +1,390 @@
+use super::models::{
+    SleepBaseline, SleepCalibrationLabel, SleepClassification, SleepClassifierConfig, SleepState,
+};
+
+/// Movement of the HMD over the windows tracked by the sleep detector
+#[derive(Clone, Copy, Default)]
+pub struct SleepFeatures {
+    pub distance_in_last_15_minutes: f64,
+    pub distance_in_last_5_minutes: f64,
+    pub distance_in_last_1_minute: f64,
+    pub distance_in_last_10_seconds: f64,
+    pub rotation_in_last_15_minutes: f64,
+    pub rotation_in_last_5_minutes: f64,
+    pub rotation_in_last_1_minute: f64,
+    pub rotation_in_last_10_seconds: f64,
+}
+
+/// Turns movement features into a sleep likelihood, from 0.0 (certainly awake) to 1.0 (certainly asleep).
+/// `running_seconds` is how long the detector has been collecting poses, so windows that aren't filled yet can be
+/// accounted for.
+pub trait SleepScoringModel: Send + Sync {
+    fn score(
+        &self,
+        features: &SleepFeatures,
+        baseline: &SleepBaseline,
+        running_seconds: f64,
+    ) -> f64;
+}
+
+/// Scores how close the movement rate is to the user's resting baseline, compared to their awake baseline.
+///
+/// Rates are compared on a logarithmic scale, as movement while awake is orders of magnitude larger than the
+/// sensor noise measured while lying still. Longer windows weigh more heavily, so a single shift in position
+/// doesn't immediately count as being awake.
+pub struct MovementScoringModel;
+
+impl MovementScoringModel {
+    // (window in seconds, weight)
+    const WINDOWS: [(f64, f64); 3] = [(60.0, 0.25), (300.0, 0.45), (900.0, 0.3)];
+
+    /// How active the given rate is, from 0.0 (at or below resting) to 1.0 (at or above awake)
+    fn activity(rate: f64, resting_rate: f64, awake_rate: f64) -> f64 {
+        let floor = f64::EPSILON;
+        let rate = rate.max(floor).ln();
+        let resting_rate = resting_rate.max(floor).ln();
+        let awake_rate = awake_rate.max(floor).ln();
+        if awake_rate <= resting_rate {
+            return if rate > resting_rate { 1.0 } else { 0.0 };
+        }
+        ((rate - resting_rate) / (awake_rate - resting_rate)).clamp(0.0, 1.0)
+    }
+}
+
+impl SleepScoringModel for MovementScoringModel {
+    fn score(
+        &self,
+        features: &SleepFeatures,
+        baseline: &SleepBaseline,
+        running_seconds: f64,
+    ) -> f64 {
+        let distances = [
+            features.distance_in_last_1_minute,
+            features.distance_in_last_5_minutes,
+            features.distance_in_last_15_minutes,
+        ];
+        let rotations = [
+            features.rotation_in_last_1_minute,
+            features.rotation_in_last_5_minutes,
+            features.rotation_in_last_15_minutes,
+        ];
+        let mut activity = 0.0;
+        for (i, (window, weight)) in Self::WINDOWS.iter().enumerate() {
+            // Windows that aren't filled yet only contain the movement since the detector started
+            let window = window.min(running_seconds).max(1.0);
+            let distance_activity = Self::activity(
+                distances[i] / window,
+                baseline.resting_distance_per_second,
+                baseline.awake_distance_per_second,
+            );
+            let rotation_activity = Self::activity(
+                rotations[i] / window,
+                baseline.resting_rotation_per_second,
+                baseline.awake_rotation_per_second,
+            );
+            activity += weight * distance_activity.max(rotation_activity);
+        }
+        1.0 - activity
+    }
+}
+
+/// Decides whether the user is awake, drowsy or asleep, based on the score of a [SleepScoringModel].
+///
+/// State changes use hysteresis: a state is entered once the score passes its threshold, but only left once the
+/// score drops a margin below it. Falling asleep additionally requires the score to stay above the threshold for a
+/// while, so a few quiet moments don't count as sleep.
+pub struct SleepClassifier {
+    model: Box<dyn SleepScoringModel>,
+    pub baseline: SleepBaseline,
+    pub config: SleepClassifierConfig,
+    state: SleepState,
+    state_since: u128,
+    asleep_candidate_since: Option<u128>,
+    score: Option<f64>,
+}
+
+impl SleepClassifier {
+    pub fn new(model: Box<dyn SleepScoringModel>) -> Self {
+        Self {
+            model,
+            baseline: SleepBaseline::default(),
+            config: SleepClassifierConfig::default(),
+            state: SleepState::Awake,
+            state_since: 0,
+            asleep_candidate_since: None,
+            score: None,
+        }
+    }
+
+    pub fn classification(&self) -> SleepClassification {
+        SleepClassification {
+            state: self.state,
+            since: self.state_since,
+            score: self.score,
+        }
+    }
+
+    /// Resets to the awake state, for when tracking has been interrupted
+    pub fn reset(&mut self, now: u128) {
+        self.state = SleepState::Awake;
+        self.state_since = now;
+        self.asleep_candidate_since = None;
+        self.score = None;
+    }
+
+    /// Scores the given features, and returns the new classification if the state changed.
+    /// No score is given until the detector has been running for the configured warmup time.
+    pub fn update(
+        &mut self,
+        features: &SleepFeatures,
+        running_since: u128,
+        now: u128,
+    ) -> Option<SleepClassification> {
+        if now.saturating_sub(running_since) < self.config.warmup_ms as u128 {
+            self.score = None;
+            return None;
+        }
+        let running_seconds = now.saturating_sub(running_since) as f64 / 1000.0;
+        let score = self.model.score(features, &self.baseline, running_seconds);
+        self.score = Some(score);
+        let config = &self.config;
+        let drowsy_exit = config.drowsy_threshold - config.hysteresis_margin;
+        let asleep_exit = config.asleep_threshold - config.hysteresis_margin;
+        // Track how long the score has been high enough to fall asleep
+        if score >= config.asleep_threshold {
+            self.asleep_candidate_since.get_or_insert(now);
+        } else {
+            self.asleep_candidate_since = None;
+        }
+        let asleep_long_enough = self
+            .asleep_candidate_since
+            .is_some_and(|since| now - since >= config.asleep_hold_ms as u128);
+        let next_state = match self.state {
+            SleepState::Awake if asleep_long_enough => SleepState::Asleep,
+            SleepState::Awake if score >= config.drowsy_threshold => SleepState::Drowsy,
+            SleepState::Drowsy if asleep_long_enough => SleepState::Asleep,
+            SleepState::Drowsy if score < drowsy_exit => SleepState::Awake,
+            SleepState::Asleep if score < drowsy_exit => SleepState::Awake,
+            SleepState::Asleep if score < asleep_exit => SleepState::Drowsy,
+            state => state,
+        };
+        if next_state == self.state {
+            return None;
+        }
+        self.state = next_state;
+        self.state_since = now;
+        Some(self.classification())
+    }
+}
+
+/// Collects movement while the user is in a known state, to derive a personal baseline from
+pub struct SleepCalibration {
+    pub label: SleepCalibrationLabel,
+    distance_rates: Vec<f64>,
+    rotation_rates: Vec<f64>,
+}
+
+impl SleepCalibration {
+    const MIN_SAMPLES: usize = 10;
+
+    pub fn new(label: SleepCalibrationLabel) -> Self {
+        Self {
+            label,
+            distance_rates: Vec::new(),
+            rotation_rates: Vec::new(),
+        }
+    }
+
+    /// Records the movement rate over the last 10 seconds
+    pub fn add_sample(&mut self, features: &SleepFeatures) {
+        self.distance_rates
+            .push(features.distance_in_last_10_seconds / 10.0);
+        self.rotation_rates
+            .push(features.rotation_in_last_10_seconds / 10.0);
+    }
+
+    /// Applies the median of the collected rates to the part of the baseline matching the label
+    pub fn apply_to(mut self, baseline: &SleepBaseline) -> Result<SleepBaseline, String> {
+        if self.distance_rates.len() < Self::MIN_SAMPLES {
+            return Err(String::from("NOT_ENOUGH_DATA"));
+        }
+        let distance_rate = median(&mut self.distance_rates);
+        let rotation_rate = median(&mut self.rotation_rates);
+        let mut baseline = baseline.clone();
+        match self.label {
+            SleepCalibrationLabel::Resting => {
+                baseline.resting_distance_per_second = distance_rate;
+                baseline.resting_rotation_per_second = rotation_rate;
+            }
+            SleepCalibrationLabel::Awake => {
+                baseline.awake_distance_per_second = distance_rate;
+                baseline.awake_rotation_per_second = rotation_rate;
+            }
+        }
+        Ok(baseline)
+    }
+}
+
+fn median(values: &mut [f64]) -> f64 {
+    values.sort_by(|a, b| a.total_cmp(b));
+    values[values.len() / 2]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Uses the 15 minute distance as the score, so tests can steer the classifier directly
+    struct FixedScoreModel;
+
+    impl SleepScoringModel for FixedScoreModel {
+        fn score(&self, features: &SleepFeatures, _: &SleepBaseline, _: f64) -> f64 {
+            features.distance_in_last_15_minutes
+        }
+    }
+
+    /// Features for moving at a constant rate for the given number of seconds
+    fn moving(distance_per_second: f64, rotation_per_second: f64, seconds: f64) -> SleepFeatures {
+        let distance = |window: f64| distance_per_second * window.min(seconds);
+        let rotation = |window: f64| rotation_per_second * window.min(seconds);
+        SleepFeatures {
+            distance_in_last_15_minutes: distance(900.0),
+            distance_in_last_5_minutes: distance(300.0),
+            distance_in_last_1_minute: distance(60.0),
+            distance_in_last_10_seconds: distance(10.0),
+            rotation_in_last_15_minutes: rotation(900.0),
+            rotation_in_last_5_minutes: rotation(300.0),
+            rotation_in_last_1_minute: rotation(60.0),
+            rotation_in_last_10_seconds: rotation(10.0),
+        }
+    }
+
+    fn score(score: f64) -> SleepFeatures {
+        SleepFeatures {
+            distance_in_last_15_minutes: score,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn scores_movement_against_the_baseline() {
+        let baseline = SleepBaseline::default();
+        let model = MovementScoringModel;
+        let resting = moving(0.001, 0.05, 900.0);
+        let awake = moving(0.05, 5.0, 900.0);
+        // Halfway between resting and awake, on a logarithmic scale
+        let halfway = moving((0.001f64 * 0.05).sqrt(), (0.05f64 * 5.0).sqrt(), 900.0);
+        assert_eq!(model.score(&resting, &baseline, 900.0), 1.0);
+        assert_eq!(model.score(&awake, &baseline, 900.0), 0.0);
+        assert!((model.score(&halfway, &baseline, 900.0) - 0.5).abs() < 1e-9);
+        // Either kind of movement is enough to count as awake
+        let turning_in_place = moving(0.001, 5.0, 900.0);
+        assert_eq!(model.score(&turning_in_place, &baseline, 900.0), 0.0);
+    }
+
+    #[test]
+    fn scores_partially_filled_windows_by_the_elapsed_time() {
+        let baseline = SleepBaseline::default();
+        let model = MovementScoringModel;
+        // Two minutes of moving around shouldn't be averaged out over the full 5 and 15 minute windows
+        let awake = moving(0.05, 5.0, 120.0);
+        assert_eq!(model.score(&awake, &baseline, 120.0), 0.0);
+        assert!(model.score(&awake, &baseline, 900.0) > 0.1);
+    }
+
+    #[test]
+    fn waits_for_the_warmup_before_scoring() {
+        let mut classifier = SleepClassifier::new(Box::new(FixedScoreModel));
+        assert!(classifier.update(&score(1.0), 0, 59999).is_none());
+        assert_eq!(classifier.classification().score, None);
+        assert_eq!(classifier.classification().state, SleepState::Awake);
+        classifier.update(&score(0.2), 0, 60000);
+        assert_eq!(classifier.classification().score, Some(0.2));
+    }
+
+    #[test]
+    fn applies_hysteresis_to_state_changes() {
+        let mut classifier = SleepClassifier::new(Box::new(FixedScoreModel));
+        let mut state_at = |value: f64, now: u128| {
+            classifier.update(&score(value), 0, now);
+            classifier.classification().state
+        };
+        // Drowsy is entered at 0.5, and only left below 0.35
+        assert_eq!(state_at(0.49, 60000), SleepState::Awake);
+        assert_eq!(state_at(0.5, 61000), SleepState::Drowsy);
+        assert_eq!(state_at(0.36, 62000), SleepState::Drowsy);
+        assert_eq!(state_at(0.34, 63000), SleepState::Awake);
+        // Asleep is entered once the score has stayed at 0.8 for two minutes, and only left below 0.65
+        assert_eq!(state_at(0.8, 100000), SleepState::Drowsy);
+        assert_eq!(state_at(0.9, 219999), SleepState::Drowsy);
+        assert_eq!(state_at(0.9, 220000), SleepState::Asleep);
+        assert_eq!(state_at(0.66, 221000), SleepState::Asleep);
+        assert_eq!(state_at(0.64, 222000), SleepState::Drowsy);
+        // Dropping below the drowsy exit threshold wakes up straight away
+        assert_eq!(state_at(0.9, 300000), SleepState::Drowsy);
+        assert_eq!(state_at(0.9, 420000), SleepState::Asleep);
+        assert_eq!(state_at(0.34, 421000), SleepState::Awake);
+    }
+
+    #[test]
+    fn restarts_the_asleep_hold_when_the_score_dips() {
+        let mut classifier = SleepClassifier::new(Box::new(FixedScoreModel));
+        let mut state_at = |value: f64, now: u128| {
+            classifier.update(&score(value), 0, now);
+            classifier.classification().state
+        };
+        assert_eq!(state_at(0.9, 60000), SleepState::Drowsy);
+        assert_eq!(state_at(0.7, 120000), SleepState::Drowsy);
+        assert_eq!(state_at(0.9, 150000), SleepState::Drowsy);
+        assert_eq!(state_at(0.9, 269999), SleepState::Drowsy);
+        assert_eq!(state_at(0.9, 270000), SleepState::Asleep);
+    }
+
+    #[test]
+    fn reports_state_changes_once() {
+        let mut classifier = SleepClassifier::new(Box::new(FixedScoreModel));
+        let change = classifier.update(&score(0.6), 0, 60000).unwrap();
+        assert_eq!(change.state, SleepState::Drowsy);
+        assert_eq!(change.since, 60000);
+        assert!(classifier.update(&score(0.6), 0, 61000).is_none());
+        classifier.reset(62000);
+        assert_eq!(classifier.classification().state, SleepState::Awake);
+        assert_eq!(classifier.classification().since, 62000);
+        assert_eq!(classifier.classification().score, None);
+    }
+
+    #[test]
+    fn calibrates_the_baseline_from_the_median_rate() {
+        let mut calibration = SleepCalibration::new(SleepCalibrationLabel::Resting);
+        for i in 0..9 {
+            calibration.add_sample(&moving(0.002 + i as f64 * 0.0001, 0.1, 10.0));
+        }
+        let baseline = SleepBaseline::default();
+        // A single outlier from shifting position doesn't affect the median
+        calibration.add_sample(&moving(1.0, 50.0, 10.0));
+        let calibrated = calibration.apply_to(&baseline).unwrap();
+        assert!((calibrated.resting_distance_per_second - 0.0025).abs() < 1e-9);
+        assert!((calibrated.resting_rotation_per_second - 0.1).abs() < 1e-9);
+        // The awake part of the baseline is left alone
+        assert_eq!(
+            calibrated.awake_distance_per_second,
+            baseline.awake_distance_per_second
+        );
+        assert_eq!(
+            calibrated.awake_rotation_per_second,
+            baseline.awake_rotation_per_second
+        );
+    }
+
+    #[test]
+    fn needs_enough_samples_to_calibrate() {
+        let mut calibration = SleepCalibration::new(SleepCalibrationLabel::Awake);
+        for _ in 0..9 {
+            calibration.add_sample(&moving(0.05, 5.0, 10.0));
+        }
+        assert_eq!(
+            calibration.apply_to(&SleepBaseline::default()).err(),
+            Some(String::from("NOT_ENOUGH_DATA"))
+        );
+    }
+}
@@ -1,37 +1,29 @@
-use crate::utils::{get_time, send_event};
+use log::{error, warn};
 
-use super::models::SleepDetectorStateReport;
+use crate::utils::{get_data_file_path, get_time, send_event};
 
-const MAX_EVENT_AGE_MS: u128 = 900000; // 15 minutes
+use super::{
+    models::{
+        SleepBaseline, SleepCalibrationLabel, SleepClassification, SleepClassifierConfig,
+        SleepDetectorSettings, SleepDetectorStateReport,
+    },
+    pose_statistics::WindowedPoseStatistics,
+    sleep_classifier::{MovementScoringModel, SleepCalibration, SleepClassifier, SleepFeatures},
+};
 
-#[derive(Clone, Copy)]
-struct PoseEvent {
-    x: f32,
-    y: f32,
-    z: f32,
-    quaternion: [f64; 4],
-    timestamp: u128, // in milliseconds
-}
-
-impl PoseEvent {
-    fn distance_to(&self, other: &PoseEvent) -> f64 {
-        let dx: f64 = (self.x - other.x).into();
-        let dy: f64 = (self.y - other.y).into();
-        let dz: f64 = (self.z - other.z).into();
-        (dx * dx + dy * dy + dz * dz).sqrt()
-    }
-    fn angular_distance_degrees(&self, other: &PoseEvent) -> f64 {
-        let q1 = self.quaternion;
-        let q2 = other.quaternion;
-        let dot_product = q1[0] * q2[0] + q1[1] * q2[1] + q1[2] * q2[2] + q1[3] * q2[3];
-        let angle = 2.0 * dot_product.abs().clamp(-1.0, 1.0).acos();
+const SETTINGS_FILE_NAME: &str = "sleep_detector.json";
 
-        angle * 180.0 / std::f64::consts::PI
-    }
+/// What happened while processing a pose
+#[derive(Default)]
+pub struct SleepDetectorOutput {
+    /// Set roughly once per second
+    pub state_report: Option<SleepDetectorStateReport>,
+    /// Set when the classified sleep state changed
+    pub state_change: Option<SleepClassification>,
 }
 
 pub struct SleepDetector {
-    events: Vec<PoseEvent>,
+    statistics: WindowedPoseStatistics,
     distance_in_last_15_minutes: f64,
     distance_in_last_10_minutes: f64,
     distance_in_last_5_minutes: f64,
@@ -43,6 +35,8 @@ pub struct SleepDetector {
     rotation_in_last_1_minute: f64,
     rotation_in_last_10_seconds: f64,
     // reqwest_client: reqwest::Client,
+    classifier: SleepClassifier,
+    calibration: Option<SleepCalibration>,
     start_time: u128,
     last_log: u128,
     next_state_report: u128,
@@ -51,7 +45,7 @@ pub struct SleepDetector {
 impl SleepDetector {
     pub fn new() -> Self {
         Self {
-            events: Vec::new(),
+            statistics: WindowedPoseStatistics::new(),
             distance_in_last_10_seconds: 0.0,
             distance_in_last_1_minute: 0.0,
             distance_in_last_5_minutes: 0.0,
@@ -63,6 +57,8 @@ impl SleepDetector {
             rotation_in_last_10_minutes: 0.0,
             rotation_in_last_15_minutes: 0.0,
             // reqwest_client: reqwest::Client::new(),
+            classifier: SleepClassifier::new(Box::new(MovementScoringModel)),
+            calibration: None,
             start_time: 0,
             last_log: 0,
             next_state_report: 0,
@@ -70,107 +66,137 @@ impl SleepDetector {
     }
 
     pub async fn log_pose(&mut self, position: [f32; 3], quaternion: [f64; 4]) {
-        // Add the event
-        let event = PoseEvent {
-            x: position[0],
-            y: position[1],
-            z: position[2],
-            quaternion,
-            timestamp: get_time(),
-        };
-        self.events.push(event);
-        // Remove old events
-        let oldest_time = event.timestamp - MAX_EVENT_AGE_MS;
-        let old_event_count = self
-            .events
-            .iter()
-            .take_while(|e| e.timestamp < oldest_time)
-            .count();
-        self.events.drain(..old_event_count);
-        // Calculate new distances
-        self.distance_in_last_15_minutes = self.distance_in_window(900000);
-        self.distance_in_last_10_minutes = self.distance_in_window(600000);
-        self.distance_in_last_5_minutes = self.distance_in_window(300000);
-        self.distance_in_last_1_minute = self.distance_in_window(60000);
-        self.distance_in_last_10_seconds = self.distance_in_window(10000);
-        self.rotation_in_last_15_minutes = self.rotation_in_window(900000);
-        self.rotation_in_last_10_minutes = self.rotation_in_window(600000);
-        self.rotation_in_last_5_minutes = self.rotation_in_window(300000);
-        self.rotation_in_last_1_minute = self.rotation_in_window(60000);
-        self.rotation_in_last_10_seconds = self.rotation_in_window(10000);
+        let output = self.process_pose(position, quaternion, get_time());
+        if let Some(classification) = output.state_change {
+            send_event("SLEEP_DETECTOR_STATE_CHANGED", classification).await;
+        }
+        if let Some(report) = output.state_report {
+            send_event("SLEEP_DETECTOR_STATE_REPORT", report).await;
+            // self.send_influxdb_report();
+        }
+    }
+
+    /// Processes a pose sampled at the given time (in milliseconds).
+    /// Doesn't read the system clock, so recorded poses can be replayed on a simulated one.
+    pub fn process_pose(
+        &mut self,
+        position: [f32; 3],
+        quaternion: [f64; 4],
+        now: u128,
+    ) -> SleepDetectorOutput {
+        self.statistics.add_pose(position, quaternion, now);
+        self.distance_in_last_15_minutes = self.statistics.distance_in_window(900000);
+        self.distance_in_last_10_minutes = self.statistics.distance_in_window(600000);
+        self.distance_in_last_5_minutes = self.statistics.distance_in_window(300000);
+        self.distance_in_last_1_minute = self.statistics.distance_in_window(60000);
+        self.distance_in_last_10_seconds = self.statistics.distance_in_window(10000);
+        self.rotation_in_last_15_minutes = self.statistics.rotation_in_window(900000);
+        self.rotation_in_last_10_minutes = self.statistics.rotation_in_window(600000);
+        self.rotation_in_last_5_minutes = self.statistics.rotation_in_window(300000);
+        self.rotation_in_last_1_minute = self.statistics.rotation_in_window(60000);
+        self.rotation_in_last_10_seconds = self.statistics.rotation_in_window(10000);
         // Set new start time if there hasn't been any data in over a minute
-        if get_time().saturating_sub(self.last_log) > 60000 {
-            self.start_time = get_time();
+        if now.saturating_sub(self.last_log) > 60000 {
+            self.start_time = now;
+            self.classifier.reset(self.start_time);
         }
         // Update the last log time
-        self.last_log = event.timestamp;
-        // Send a state report if it's been over a second since the last one
-        if get_time() > self.next_state_report {
-            self.next_state_report = get_time() + 1000;
-            self.send_state_report().await;
+        self.last_log = now;
+        // Classify and report the state if it's been over a second since the last report
+        let mut output = SleepDetectorOutput::default();
+        if now > self.next_state_report {
+            self.next_state_report = now + 1000;
+            output.state_change = self.classify(now);
+            output.state_report = Some(self.state_report());
+        }
+        output
+    }
+
+    fn features(&self) -> SleepFeatures {
+        SleepFeatures {
+            distance_in_last_15_minutes: self.distance_in_last_15_minutes,
+            distance_in_last_5_minutes: self.distance_in_last_5_minutes,
+            distance_in_last_1_minute: self.distance_in_last_1_minute,
+            distance_in_last_10_seconds: self.distance_in_last_10_seconds,
+            rotation_in_last_15_minutes: self.rotation_in_last_15_minutes,
+            rotation_in_last_5_minutes: self.rotation_in_last_5_minutes,
+            rotation_in_last_1_minute: self.rotation_in_last_1_minute,
+            rotation_in_last_10_seconds: self.rotation_in_last_10_seconds,
+        }
+    }
+
+    fn classify(&mut self, now: u128) -> Option<SleepClassification> {
+        let features = self.features();
+        if let Some(calibration) = self.calibration.as_mut() {
+            calibration.add_sample(&features);
         }
+        self.classifier.update(&features, self.start_time, now)
+    }
+
+    pub fn classification(&self) -> SleepClassification {
+        self.classifier.classification()
+    }
+
+    pub fn baseline(&self) -> SleepBaseline {
+        self.classifier.baseline.clone()
+    }
+
+    pub fn set_baseline(&mut self, baseline: SleepBaseline) {
+        self.classifier.baseline = baseline;
+    }
+
+    pub fn classifier_config(&self) -> SleepClassifierConfig {
+        self.classifier.config.clone()
+    }
+
+    pub fn set_classifier_config(&mut self, config: SleepClassifierConfig) {
+        self.classifier.config = config;
     }
 
-    fn distance_in_window(&mut self, window_ms: u128) -> f64 {
-        let start_time = get_time() - window_ms;
-        let start_index = self
-            .events
-            .iter()
-            .position(|e| e.timestamp >= start_time)
-            .unwrap_or(0);
-        let events = &self.events[start_index..];
-        let mut total_distance = 0.0;
-        let mut i = 0;
-        while i < events.len() - 1 {
-            let event_a = &events[i];
-            let event_b = &events[i + 1];
-            let distance = event_a.distance_to(event_b);
-            total_distance += distance;
-            i += 1;
+    pub fn settings(&self) -> SleepDetectorSettings {
+        SleepDetectorSettings {
+            baseline: self.baseline(),
+            classifier_config: self.classifier_config(),
         }
-        total_distance
-    }
-
-    fn rotation_in_window(&mut self, window_ms: u128) -> f64 {
-        let start_time = get_time() - window_ms;
-        let start_index = self
-            .events
-            .iter()
-            .position(|e| e.timestamp >= start_time)
-            .unwrap_or(0);
-        let events = &self.events[start_index..];
-        let mut total_rotation = 0.0;
-        let mut i = 0;
-        while i < events.len() - 1 {
-            let event_a = &events[i];
-            let event_b = &events[i + 1];
-            let rotation = event_a.angular_distance_degrees(event_b);
-            total_rotation += rotation;
-            i += 1;
+    }
+
+    pub fn apply_settings(&mut self, settings: SleepDetectorSettings) {
+        self.classifier.baseline = settings.baseline;
+        self.classifier.config = settings.classifier_config;
+    }
+
+    /// Starts collecting movement data for the given label, until the calibration is finished
+    pub fn start_calibration(&mut self, label: SleepCalibrationLabel) {
+        self.calibration = Some(SleepCalibration::new(label));
+    }
+
+    /// Derives a new baseline from the data collected since the calibration started, and starts using it
+    pub fn finish_calibration(&mut self) -> Result<SleepBaseline, String> {
+        let calibration = self
+            .calibration
+            .take()
+            .ok_or_else(|| String::from("NOT_CALIBRATING"))?;
+        let baseline = calibration.apply_to(&self.classifier.baseline)?;
+        self.classifier.baseline = baseline.clone();
+        Ok(baseline)
+    }
+
+    fn state_report(&self) -> SleepDetectorStateReport {
+        SleepDetectorStateReport {
+            distance_in_last_15_minutes: self.distance_in_last_15_minutes,
+            distance_in_last_10_minutes: self.distance_in_last_10_minutes,
+            distance_in_last_5_minutes: self.distance_in_last_5_minutes,
+            distance_in_last_1_minute: self.distance_in_last_1_minute,
+            distance_in_last_10_seconds: self.distance_in_last_10_seconds,
+            rotation_in_last_15_minutes: self.rotation_in_last_15_minutes,
+            rotation_in_last_10_minutes: self.rotation_in_last_10_minutes,
+            rotation_in_last_5_minutes: self.rotation_in_last_5_minutes,
+            rotation_in_last_1_minute: self.rotation_in_last_1_minute,
+            rotation_in_last_10_seconds: self.rotation_in_last_10_seconds,
+            start_time: self.start_time,
+            last_log: self.last_log,
+            classification: self.classifier.classification(),
         }
-        total_rotation
-    }
-
-    async fn send_state_report(&self) {
-        send_event(
-            "SLEEP_DETECTOR_STATE_REPORT",
-            SleepDetectorStateReport {
-                distance_in_last_15_minutes: self.distance_in_last_15_minutes,
-                distance_in_last_10_minutes: self.distance_in_last_10_minutes,
-                distance_in_last_5_minutes: self.distance_in_last_5_minutes,
-                distance_in_last_1_minute: self.distance_in_last_1_minute,
-                distance_in_last_10_seconds: self.distance_in_last_10_seconds,
-                rotation_in_last_15_minutes: self.rotation_in_last_15_minutes,
-                rotation_in_last_10_minutes: self.rotation_in_last_10_minutes,
-                rotation_in_last_5_minutes: self.rotation_in_last_5_minutes,
-                rotation_in_last_1_minute: self.rotation_in_last_1_minute,
-                rotation_in_last_10_seconds: self.rotation_in_last_10_seconds,
-                start_time: self.start_time,
-                last_log: self.last_log,
-            },
-        )
-        .await;
-        // self.send_influxdb_report();
     }
 
     // #[tokio::main]
@@ -199,3 +225,83 @@ impl SleepDetector {
     //     let _ = futures::executor::block_on(f);
     // }
 }
+
+/// Reads the baseline and classifier configuration stored by a previous session, if any
+pub async fn load_settings() -> Option<SleepDetectorSettings> {
+    let path = get_data_file_path(SETTINGS_FILE_NAME).await?;
+    let contents = tokio::fs::read_to_string(&path).await.ok()?;
+    match serde_json::from_str(&contents) {
+        Ok(settings) => Some(settings),
+        Err(e) => {
+            warn!("[Core] Could not read sleep detector settings: {e}");
+            None
+        }
+    }
+}
+
+pub async fn save_settings(settings: &SleepDetectorSettings) {
+    let Some(path) = get_data_file_path(SETTINGS_FILE_NAME).await else {
+        return;
+    };
+    if let Err(e) = tokio::fs::write(&path, serde_json::to_string(settings).unwrap()).await {
+        error!("[Core] Could not write sleep detector settings: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openvr::models::SleepState;
+
+    #[test]
+    fn restores_the_calibration_from_stored_settings() {
+        let mut detector = SleepDetector::new();
+        detector.start_calibration(SleepCalibrationLabel::Resting);
+        // Lie still for a minute, with a little sensor noise
+        for i in 0..=60000u128 / 100 {
+            let offset = (i % 2) as f32 * 0.0005;
+            detector.process_pose([0.0, 1.0 + offset, 0.0], [1.0, 0.0, 0.0, 0.0], i * 100);
+        }
+        let baseline = detector.finish_calibration().unwrap();
+        detector.set_classifier_config(SleepClassifierConfig {
+            asleep_threshold: 0.9,
+            ..Default::default()
+        });
+
+        // Store and load the settings, like between sessions
+        let stored = serde_json::to_string(&detector.settings()).unwrap();
+        let mut restored = SleepDetector::new();
+        restored.apply_settings(serde_json::from_str(&stored).unwrap());
+
+        assert_eq!(
+            serde_json::to_value(restored.baseline()).unwrap(),
+            serde_json::to_value(baseline).unwrap()
+        );
+        assert_eq!(restored.classifier_config().asleep_threshold, 0.9);
+        assert_eq!(restored.classification().state, SleepState::Awake);
+    }
+
+    #[test]
+    fn falls_back_to_defaults_for_missing_settings() {
+        let settings: SleepDetectorSettings = serde_json::from_str("{}").unwrap();
+        assert_eq!(
+            serde_json::to_value(settings).unwrap(),
+            serde_json::to_value(SleepDetectorSettings::default()).unwrap()
+        );
+        let stored = r#"{
+            "classifierConfig": {
+                "drowsyThreshold": 0.4,
+                "asleepThreshold": 0.7,
+                "hysteresisMargin": 0.1,
+                "asleepHoldMs": 60000,
+                "warmupMs": 30000
+            }
+        }"#;
+        let settings: SleepDetectorSettings = serde_json::from_str(stored).unwrap();
+        assert_eq!(settings.classifier_config.asleep_threshold, 0.7);
+        assert_eq!(
+            serde_json::to_value(settings.baseline).unwrap(),
+            serde_json::to_value(SleepBaseline::default()).unwrap()
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::{HashMap, HashSet};
+
+use regex::RegexSet;
+
+const DEFAULT_SUBSCRIBER: &str = "default";
+
+/// Matches OSC addresses against a set of OSC 1.0 address patterns.
+///
+/// Supported are `?` (any single character), `*` (any sequence of characters within a single part of the address),
+/// `[abc]`, `[a-z]` and `[!a-z]` (character sets), and `{foo,bar}` (alternatives). As an extension, `**` matches
+/// any sequence of characters across parts of the address, so `/tracking/**` matches everything below `/tracking`.
+/// Patterns without any of these are matched exactly, without involving a regex.
+pub struct AddressFilter {
+    exact: HashSet<String>,
+    patterns: RegexSet,
+}
+
+impl AddressFilter {
+    pub fn new<'a>(patterns: impl IntoIterator<Item = &'a String>) -> Result<Self, String> {
+        let mut exact = HashSet::new();
+        let mut regexes = Vec::new();
+        for pattern in patterns {
+            if is_literal(pattern) {
+                exact.insert(pattern.clone());
+            } else {
+                regexes.push(compile_pattern(pattern)?);
+            }
+        }
+        let patterns = RegexSet::new(regexes).map_err(|_| String::from("INVALID_PATTERN"))?;
+        Ok(Self { exact, patterns })
+    }
+
+    pub fn is_match(&self, address: &str) -> bool {
+        self.exact.contains(address) || self.patterns.is_match(address)
+    }
+}
+
+impl Default for AddressFilter {
+    fn default() -> Self {
+        Self {
+            exact: HashSet::new(),
+            patterns: RegexSet::empty(),
+        }
+    }
+}
+
+/// The address filters of every subscriber, combined into a single filter.
+#[derive(Default)]
+pub struct ReceiveAddressFilters {
+    subscribers: HashMap<String, Vec<String>>,
+    filter: AddressFilter,
+}
+
+impl ReceiveAddressFilters {
+    pub fn set_default(&mut self, patterns: Vec<String>) -> Result<(), String> {
+        self.set(DEFAULT_SUBSCRIBER.into(), patterns)
+    }
+
+    pub fn set(&mut self, subscriber: String, patterns: Vec<String>) -> Result<(), String> {
+        // Validate the new patterns before replacing the old ones
+        AddressFilter::new(&patterns)?;
+        self.subscribers.insert(subscriber, patterns);
+        self.rebuild()
+    }
+
+    pub fn remove(&mut self, subscriber: &str) -> Result<(), String> {
+        if self.subscribers.remove(subscriber).is_some() {
+            self.rebuild()?;
+        }
+        Ok(())
+    }
+
+    pub fn is_match(&self, address: &str) -> bool {
+        self.filter.is_match(address)
+    }
+
+    fn rebuild(&mut self) -> Result<(), String> {
+        self.filter = AddressFilter::new(self.subscribers.values().flatten())?;
+        Ok(())
+    }
+}
+
+fn is_literal(pattern: &str) -> bool {
+    !pattern.contains(['*', '?', '[', ']', '{', '}'])
+}
+
+/// Translates an OSC address pattern into an (anchored) regular expression
+fn compile_pattern(pattern: &str) -> Result<String, String> {
+    let invalid = || String::from("INVALID_PATTERN");
+    let mut regex = String::from("^");
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => {
+                if chars.next_if_eq(&'*').is_some() {
+                    regex.push_str(".*");
+                } else {
+                    regex.push_str("[^/]*");
+                }
+            }
+            '?' => regex.push_str("[^/]"),
+            '[' => {
+                regex.push('[');
+                if chars.next_if_eq(&'!').is_some() {
+                    regex.push('^');
+                }
+                let mut empty = true;
+                loop {
+                    match chars.next().ok_or_else(invalid)? {
+                        ']' if !empty => break,
+                        // A dash is only a range operator in between two characters
+                        '-' if !empty && chars.peek().is_some_and(|c| *c != ']') => regex.push('-'),
+                        c => regex.push_str(&regex::escape(&c.to_string())),
+                    }
+                    empty = false;
+                }
+                regex.push(']');
+            }
+            '{' => {
+                let mut alternatives = String::new();
+                loop {
+                    match chars.next().ok_or_else(invalid)? {
+                        '}' => break,
+                        c => alternatives.push(c),
+                    }
+                }
+                let alternatives: Vec<String> =
+                    alternatives.split(',').map(regex::escape).collect();
+                regex.push_str(&format!("(?:{})", alternatives.join("|")));
+            }
+            ']' | '}' => return Err(invalid()),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    Ok(regex)
+}
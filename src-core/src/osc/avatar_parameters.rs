@@ -0,0 +1,166 @@
+use std::{collections::HashMap, sync::LazyLock, time::Duration};
+
+use log::{debug, info};
+use rosc::{OscMessage, OscType};
+use serde_json::Value;
+use tokio::sync::Mutex;
+
+use crate::utils::{get_time, send_event};
+
+use super::{
+    address_pattern::ReceiveAddressFilters,
+    models::{OSCValue, VRCAvatarParameter, VRCAvatarParameterChangedEvent, VRCAvatarState},
+    oscquery::{fetch_tree, parse_host_port},
+    VRC_OSCQUERY_ADDRESS,
+};
+
+const AVATAR_CHANGE_ADDRESS: &str = "/avatar/change";
+const AVATAR_PARAMETERS_PREFIX: &str = "/avatar/parameters/";
+/// VRChat needs a moment to update its OSCQuery tree after switching avatars
+const SEED_DELAY_AFTER_AVATAR_CHANGE: Duration = Duration::from_secs(2);
+
+static AVATAR_STATE: LazyLock<Mutex<VRCAvatarState>> = LazyLock::new(Default::default);
+static PARAMETER_SUBSCRIPTIONS: LazyLock<Mutex<ReceiveAddressFilters>> =
+    LazyLock::new(Default::default);
+
+/// Keeps track of the active avatar and the values of its parameters, based on incoming OSC messages
+pub async fn process_message(msg: &OscMessage) {
+    if msg.addr == AVATAR_CHANGE_ADDRESS {
+        let avatar_id = match msg.args.first() {
+            Some(OscType::String(id)) => id.clone(),
+            _ => return,
+        };
+        on_avatar_changed(Some(avatar_id)).await;
+        tokio::spawn(async {
+            tokio::time::sleep(SEED_DELAY_AFTER_AVATAR_CHANGE).await;
+            seed_from_oscquery().await;
+        });
+        return;
+    }
+    let name = match msg.addr.strip_prefix(AVATAR_PARAMETERS_PREFIX) {
+        Some(name) => name,
+        None => return,
+    };
+    let value = match msg.args.first() {
+        Some(value) => OSCValue::from(value),
+        None => return,
+    };
+    let mut state = AVATAR_STATE.lock().await;
+    let changed = state
+        .parameters
+        .get(name)
+        .is_none_or(|parameter| parameter.value != value);
+    state.parameters.insert(
+        name.to_string(),
+        VRCAvatarParameter {
+            value: value.clone(),
+            updated_at: get_time() as u64,
+        },
+    );
+    drop(state);
+    // VRChat repeats parameter values frequently, so we only notify about actual changes
+    if changed && PARAMETER_SUBSCRIPTIONS.lock().await.is_match(name) {
+        send_event(
+            "VRC_AVATAR_PARAMETER_CHANGED",
+            VRCAvatarParameterChangedEvent {
+                name: name.to_string(),
+                value,
+            },
+        )
+        .await;
+    }
+}
+
+async fn on_avatar_changed(avatar_id: Option<String>) {
+    let mut state = AVATAR_STATE.lock().await;
+    if state.avatar_id == avatar_id {
+        return;
+    }
+    info!("[Core] VRChat avatar changed to {avatar_id:?}");
+    state.avatar_id = avatar_id.clone();
+    state.parameters.clear();
+    drop(state);
+    send_event("VRC_AVATAR_CHANGED", avatar_id).await;
+}
+
+pub async fn get_state() -> VRCAvatarState {
+    AVATAR_STATE.lock().await.clone()
+}
+
+pub async fn set_subscription(subscriber: String, patterns: Vec<String>) -> Result<(), String> {
+    PARAMETER_SUBSCRIPTIONS
+        .lock()
+        .await
+        .set(subscriber, patterns)
+}
+
+pub async fn remove_subscription(subscriber: String) -> Result<(), String> {
+    PARAMETER_SUBSCRIPTIONS.lock().await.remove(&subscriber)
+}
+
+/// Fills in the active avatar and any parameters we haven't received a value for yet, from VRChat's OSCQuery tree.
+/// Values that were already received over OSC are newer, so they are left untouched.
+pub async fn seed_from_oscquery() {
+    let (host, port) = match VRC_OSCQUERY_ADDRESS
+        .lock()
+        .await
+        .as_deref()
+        .and_then(parse_host_port)
+    {
+        Some(address) => address,
+        None => return,
+    };
+    let tree = match fetch_tree(&host, port, "/avatar").await {
+        Ok(tree) => tree,
+        Err(e) => {
+            debug!("[Core] Could not fetch VRChat OSCQuery avatar tree: {e}");
+            return;
+        }
+    };
+    let nodes: Vec<(String, OscType)> = tree
+        .flatten()
+        .into_iter()
+        .filter_map(|node| {
+            let value = parse_oscquery_value(node.type_tag.as_deref()?, node.value.first()?)?;
+            Some((node.full_path.clone(), value))
+        })
+        .collect();
+    let avatar_id = nodes
+        .iter()
+        .find(|(path, _)| path == AVATAR_CHANGE_ADDRESS)
+        .and_then(|(_, value)| match value {
+            OscType::String(id) => Some(id.clone()),
+            _ => None,
+        });
+    if avatar_id.is_some() {
+        on_avatar_changed(avatar_id).await;
+    }
+    let now = get_time() as u64;
+    let mut state = AVATAR_STATE.lock().await;
+    let mut seeded = 0;
+    for (path, value) in nodes {
+        if let Some(name) = path.strip_prefix(AVATAR_PARAMETERS_PREFIX) {
+            if !state.parameters.contains_key(name) {
+                state.parameters.insert(
+                    name.to_string(),
+                    VRCAvatarParameter {
+                        value: OSCValue::from(&value),
+                        updated_at: now,
+                    },
+                );
+                seeded += 1;
+            }
+        }
+    }
+    debug!("[Core] Seeded {seeded} avatar parameters from VRChat OSCQuery tree");
+}
+
+fn parse_oscquery_value(type_tag: &str, value: &Value) -> Option<OscType> {
+    Some(match type_tag {
+        "f" => OscType::Float(value.as_f64()? as f32),
+        "i" => OscType::Int(value.as_i64()? as i32),
+        "T" | "F" => OscType::Bool(value.as_bool()?),
+        "s" => OscType::String(value.as_str()?.to_string()),
+        _ => return None,
+    })
+}
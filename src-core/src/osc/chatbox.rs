@@ -0,0 +1,126 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::LazyLock,
+    time::Duration,
+};
+
+use log::error;
+use rosc::OscType;
+use tokio::{
+    sync::{Mutex, Notify},
+    time::Instant,
+};
+
+use super::commands::osc_send;
+
+const CHATBOX_INPUT_ADDRESS: &str = "/chatbox/input";
+const CHATBOX_TYPING_ADDRESS: &str = "/chatbox/typing";
+/// The maximum number of characters VRChat shows in a single chatbox message
+const CHATBOX_MAX_LENGTH: usize = 144;
+/// VRChat temporarily blocks the chatbox when messages are sent faster than this
+const CHATBOX_MIN_INTERVAL: Duration = Duration::from_millis(1500);
+const CHATBOX_QUEUE_SIZE: usize = 32;
+
+static CHATBOX_QUEUE: LazyLock<Mutex<VecDeque<ChatboxMessage>>> = LazyLock::new(Default::default);
+static CHATBOX_QUEUE_NOTIFY: LazyLock<Notify> = LazyLock::new(Notify::new);
+
+struct ChatboxMessage {
+    addr: String,
+    text: String,
+    notify_sound: bool,
+}
+
+/// Queues a chatbox message for sending. Messages longer than VRChat allows are split into multiple chunks,
+/// preferably on whitespace. Returns the number of chunks that were queued.
+pub async fn queue_message(
+    addr: String,
+    text: String,
+    notify_sound: bool,
+) -> Result<usize, String> {
+    let chunks = split_into_chunks(&text);
+    if chunks.is_empty() {
+        return Err(String::from("EMPTY_MESSAGE"));
+    }
+    let mut queue = CHATBOX_QUEUE.lock().await;
+    if queue.len() + chunks.len() > CHATBOX_QUEUE_SIZE {
+        return Err(String::from("QUEUE_FULL"));
+    }
+    let chunk_count = chunks.len();
+    queue.extend(chunks.into_iter().map(|text| ChatboxMessage {
+        addr: addr.clone(),
+        text,
+        notify_sound,
+    }));
+    CHATBOX_QUEUE_NOTIFY.notify_one();
+    Ok(chunk_count)
+}
+
+pub async fn clear_queue() {
+    CHATBOX_QUEUE.lock().await.clear();
+}
+
+pub async fn set_typing(addr: String, typing: bool) -> Result<bool, String> {
+    osc_send(
+        addr,
+        CHATBOX_TYPING_ADDRESS.into(),
+        vec![OscType::Bool(typing)],
+    )
+    .await
+}
+
+/// Sends queued chatbox messages, keeping to VRChat's rate limit for each target
+pub fn spawn_queue_task() {
+    tokio::spawn(async {
+        let mut last_sent: HashMap<String, Instant> = HashMap::new();
+        loop {
+            let message = CHATBOX_QUEUE.lock().await.pop_front();
+            let message = match message {
+                Some(message) => message,
+                None => {
+                    CHATBOX_QUEUE_NOTIFY.notified().await;
+                    continue;
+                }
+            };
+            if let Some(last_sent) = last_sent.get(&message.addr) {
+                tokio::time::sleep_until(*last_sent + CHATBOX_MIN_INTERVAL).await;
+            }
+            let result = osc_send(
+                message.addr.clone(),
+                CHATBOX_INPUT_ADDRESS.into(),
+                vec![
+                    OscType::String(message.text),
+                    // Send the message immediately, rather than opening the keyboard
+                    OscType::Bool(true),
+                    OscType::Bool(message.notify_sound),
+                ],
+            )
+            .await;
+            if let Err(e) = result {
+                error!("[Core] Could not send chatbox message: {e}");
+            }
+            last_sent.insert(message.addr, Instant::now());
+        }
+    });
+}
+
+fn split_into_chunks(text: &str) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut rest = text.trim();
+    while !rest.is_empty() {
+        let limit = match rest.char_indices().nth(CHATBOX_MAX_LENGTH) {
+            Some((index, _)) => index,
+            None => {
+                chunks.push(rest.to_string());
+                break;
+            }
+        };
+        // Break on the last whitespace that fits, unless that would leave an empty chunk
+        let split = rest[..limit]
+            .rfind(char::is_whitespace)
+            .filter(|index| *index > 0)
+            .unwrap_or(limit);
+        chunks.push(rest[..split].trim_end().to_string());
+        rest = rest[split..].trim_start();
+    }
+    chunks
+}
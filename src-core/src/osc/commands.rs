@@ -1,12 +1,21 @@
-use super::{OSC_RECEIVE_SOCKET, OSC_SEND_SOCKET};
+use super::{
+    models::{
+        parse_osc_value, OSCMessage, OSCQueryHostInfo, OSCQueryNode, OSCQueryService,
+        OSCReceiverStats, OSCRelayTarget, OSCRelayTargetStatus, OSCTargetOverride, OSCValue,
+        VRCAvatarState,
+    },
+    OSC_SEND_SOCKET,
+};
 use log::{debug, error, info};
 use oyasumivr_oscquery::OSCMethod;
-use rosc::{encoder, OscMessage, OscPacket, OscType};
+use rosc::{encoder, OscBundle, OscMessage, OscPacket, OscTime, OscType};
 use serde::{Deserialize, Serialize};
 use std::{
     net::{SocketAddrV4, UdpSocket},
+    path::PathBuf,
     str::FromStr,
     sync::LazyLock,
+    time::Duration,
 };
 use tokio::sync::Mutex;
 use tokio_util::sync::CancellationToken;
@@ -19,6 +28,35 @@ pub enum SupportedOscType {
     Float,
     Boolean,
     String,
+    Long,
+    Double,
+    Char,
+    Blob,
+    Time,
+    Color,
+    Midi,
+    Nil,
+    Inf,
+}
+
+impl SupportedOscType {
+    fn kind(&self) -> &'static str {
+        match self {
+            SupportedOscType::Int => "int",
+            SupportedOscType::Float => "float",
+            SupportedOscType::Boolean => "bool",
+            SupportedOscType::String => "string",
+            SupportedOscType::Long => "long",
+            SupportedOscType::Double => "double",
+            SupportedOscType::Char => "char",
+            SupportedOscType::Blob => "blob",
+            SupportedOscType::Time => "time",
+            SupportedOscType::Color => "color",
+            SupportedOscType::Midi => "midi",
+            SupportedOscType::Nil => "nil",
+            SupportedOscType::Inf => "inf",
+        }
+    }
 }
 
 static CANCELLATION_TOKEN: LazyLock<Mutex<Option<CancellationToken>>> =
@@ -38,9 +76,65 @@ pub async fn get_vrchat_oscquery_address() -> Option<String> {
     guard.as_ref().cloned()
 }
 
+/// All OSC and OSCQuery services currently advertised on the network
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn osc_get_discovered_services() -> Vec<OSCQueryService> {
+    super::oscquery::get_services().await
+}
+
+/// Targets the discovered OSCQuery service with the given name instead of the detected VRChat client.
+/// Passing no name goes back to the VRChat client.
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn osc_select_discovered_service(name: Option<String>) {
+    super::oscquery::select_service(name).await;
+}
+
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn osc_get_selected_discovered_service() -> Option<String> {
+    super::oscquery::get_selected_service().await
+}
+
+/// Fetches the `HOST_INFO` of any OSCQuery server (e.g. one found through `osc_get_discovered_services`)
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn oscquery_get_host_info(host: String, port: u16) -> Result<OSCQueryHostInfo, String> {
+    super::oscquery::fetch_host_info(&host, port)
+        .await
+        .ok_or_else(|| String::from("REQUEST_FAILED"))
+}
+
+/// Fetches the node tree of any OSCQuery server, starting at the given path (the root by default)
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn oscquery_get_tree(
+    host: String,
+    port: u16,
+    path: Option<String>,
+) -> Result<OSCQueryNode, String> {
+    super::oscquery::fetch_tree(&host, port, path.as_deref().unwrap_or("/")).await
+}
+
+/// Pins the OSC and/or OSCQuery address (host:port) to target, bypassing discovery
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn osc_set_target_override(target_override: OSCTargetOverride) -> Result<(), String> {
+    super::oscquery::set_target_override(target_override).await
+}
+
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn osc_get_target_override() -> OSCTargetOverride {
+    super::oscquery::get_target_override().await
+}
+
 #[tauri::command]
 #[oyasumivr_macros::command_profiling]
 pub async fn stop_osc_server() {
+    // Make sure no inputs are left held in VRChat
+    super::input::release_all().await;
     // Terminate existing task if it exists
     let mut cancellation_token = CANCELLATION_TOKEN.lock().await;
     if let Some(token) = cancellation_token.as_ref() {
@@ -56,8 +150,6 @@ pub async fn stop_osc_server() {
             error!("[Core] Could not terminate OSCQuery client: {err:#?}")
         };
     }
-    let mut receive_socket_guard = OSC_RECEIVE_SOCKET.lock().await;
-    *receive_socket_guard = None;
 }
 
 #[tauri::command]
@@ -85,10 +177,16 @@ pub async fn start_osc_server() -> Option<(String, String)> {
     receive_socket.set_nonblocking(true).unwrap();
     let osc_addr_string = receive_socket.local_addr().unwrap().to_string();
     let osc_addr_port = receive_socket.local_addr().unwrap().port();
+    let receive_socket = match tokio::net::UdpSocket::from_std(receive_socket) {
+        Ok(s) => s,
+        Err(err) => {
+            error!("[Core] Could not initialize receive socket for OSC module (tokio init): {err}");
+            return None;
+        }
+    };
     info!("[Core] OSC server listening on {osc_addr_string}");
-    *OSC_RECEIVE_SOCKET.lock().await = Some(receive_socket);
     // Process incoming messages
-    let cancellation_token = super::spawn_receiver_task().await;
+    let cancellation_token = super::spawn_receiver_task(receive_socket);
     *CANCELLATION_TOKEN.lock().await = Some(cancellation_token);
     // Start the OSCQuery server
     let osc_query_addr_string =
@@ -128,6 +226,135 @@ pub async fn start_osc_server() -> Option<(String, String)> {
     Some((osc_addr_string, osc_query_addr_string))
 }
 
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn get_osc_receiver_stats() -> OSCReceiverStats {
+    super::get_receiver_stats()
+}
+
+/// Configures the targets that received OSC packets are forwarded to, replacing any previously configured ones.
+/// Fails with `RELAY_LOOP` for targets that point back at OyasumiVR's own OSC receiver.
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn set_osc_relay_targets(targets: Vec<OSCRelayTarget>) -> Result<(), String> {
+    super::relay::set_targets(targets).await
+}
+
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn get_osc_relay_status() -> Vec<OSCRelayTargetStatus> {
+    super::relay::get_status().await
+}
+
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn osc_start_recording(path: String) -> Result<(), String> {
+    super::recorder::start_recording(PathBuf::from(path)).await
+}
+
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn osc_stop_recording() -> Result<u64, String> {
+    super::recorder::stop_recording().await
+}
+
+/// Replays a recording made with `osc_start_recording`. The speed defaults to 1.0 (the original speed).
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn osc_start_replay(path: String, speed: Option<f64>) -> Result<(), String> {
+    super::recorder::start_replay(PathBuf::from(path), speed.unwrap_or(1.0)).await
+}
+
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn osc_stop_replay() {
+    super::recorder::stop_replay().await;
+}
+
+/// The active VRChat avatar, and the last known values of its parameters
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn vrc_avatar_get_state() -> VRCAvatarState {
+    super::avatar_parameters::get_state().await
+}
+
+/// Emits `VRC_AVATAR_PARAMETER_CHANGED` events whenever a parameter with a name matching any of the
+/// given OSC address patterns (e.g. `Voice` or `Sleep*`) changes value
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn vrc_avatar_subscribe_parameters(
+    subscriber: String,
+    patterns: Vec<String>,
+) -> Result<(), String> {
+    super::avatar_parameters::set_subscription(subscriber, patterns).await
+}
+
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn vrc_avatar_unsubscribe_parameters(subscriber: String) -> Result<(), String> {
+    super::avatar_parameters::remove_subscription(subscriber).await
+}
+
+/// Queues a message for VRChat's chatbox. Long messages are split into multiple messages, which are sent
+/// one after the other at a rate VRChat accepts. Returns the number of messages queued.
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn vrc_chatbox_send(
+    addr: String,
+    text: String,
+    notify_sound: Option<bool>,
+) -> Result<usize, String> {
+    super::chatbox::queue_message(addr, text, notify_sound.unwrap_or(true)).await
+}
+
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn vrc_chatbox_clear_queue() {
+    super::chatbox::clear_queue().await;
+}
+
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn vrc_chatbox_set_typing(addr: String, typing: bool) -> Result<bool, String> {
+    super::chatbox::set_typing(addr, typing).await
+}
+
+/// Presses a VRChat input button (e.g. `Jump`), and releases it after the given duration
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn vrc_input_press_button(
+    addr: String,
+    button: String,
+    duration_ms: Option<u64>,
+) -> Result<bool, String> {
+    let duration = duration_ms
+        .map(Duration::from_millis)
+        .unwrap_or(super::input::DEFAULT_PRESS_DURATION);
+    super::input::set_button(addr, button, true, Some(duration)).await
+}
+
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn vrc_input_set_button(
+    addr: String,
+    button: String,
+    pressed: bool,
+) -> Result<bool, String> {
+    super::input::set_button(addr, button, pressed, None).await
+}
+
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn vrc_input_set_axis(addr: String, axis: String, value: f32) -> Result<bool, String> {
+    super::input::set_axis(addr, axis, value).await
+}
+
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn vrc_input_release_all() {
+    super::input::release_all().await;
+}
+
 #[tauri::command]
 #[oyasumivr_macros::command_profiling]
 pub async fn add_osc_method(method: OSCMethod) {
@@ -150,37 +377,49 @@ pub async fn osc_send_command(
 ) -> Result<bool, String> {
     debug!("[Core] Sending OSC command (address={osc_addr}, types={types:?}, values={values:?})");
 
-    let mut data = Vec::new();
-
-    for (osc_type, value) in types.into_iter().zip(values.into_iter()) {
-        let osc_value = match osc_type {
-            SupportedOscType::Int => value
-                .parse::<i32>()
-                .map(OscType::Int)
-                .map_err(|_| String::from("INVALID_INT_VALUE"))?,
-            SupportedOscType::Float => value
-                .parse::<f32>()
-                .map(OscType::Float)
-                .map_err(|_| String::from("INVALID_FLOAT_VALUE"))?,
-            SupportedOscType::Boolean => value
-                .parse::<bool>()
-                .map(OscType::Bool)
-                .map_err(|_| String::from("INVALID_BOOL_VALUE"))?,
-            SupportedOscType::String => OscType::String(value),
-        };
-        data.push(osc_value);
-    }
+    let data = types
+        .iter()
+        .zip(values.iter())
+        .map(|(osc_type, value)| parse_osc_value(osc_type.kind(), value))
+        .collect::<Result<Vec<_>, _>>()?;
 
     osc_send(addr, osc_addr, data).await
 }
 
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn osc_send_values(
+    addr: String,
+    osc_addr: String,
+    values: Vec<OSCValue>,
+) -> Result<bool, String> {
+    debug!("[Core] Sending OSC values (address={osc_addr}, values={values:?})");
+    let data = values
+        .iter()
+        .map(OscType::try_from)
+        .collect::<Result<Vec<_>, _>>()?;
+    osc_send(addr, osc_addr, data).await
+}
+
 #[tauri::command]
 #[oyasumivr_macros::command_profiling]
 pub async fn osc_valid_addr(addr: String) -> bool {
     SocketAddrV4::from_str(addr.as_str()).is_ok()
 }
 
-async fn osc_send(addr: String, osc_addr: String, data: Vec<OscType>) -> Result<bool, String> {
+pub(super) async fn osc_send(
+    addr: String,
+    osc_addr: String,
+    data: Vec<OscType>,
+) -> Result<bool, String> {
+    let packet = OscPacket::Message(OscMessage {
+        addr: osc_addr,
+        args: data,
+    });
+    osc_send_packet(addr, packet).await
+}
+
+async fn osc_send_packet(addr: String, packet: OscPacket) -> Result<bool, String> {
     // Get socket
     let socket_guard = OSC_SEND_SOCKET.lock().await;
     let socket = match socket_guard.as_ref() {
@@ -192,24 +431,83 @@ async fn osc_send(addr: String, osc_addr: String, data: Vec<OscType>) -> Result<
         Ok(addr) => addr,
         Err(_) => return Err(String::from("INVALID_ADDRESS")),
     };
-    // Construct message
-    let msg_buf = encoder::encode(&OscPacket::Message(OscMessage {
-        addr: osc_addr.clone(),
-        args: data.clone(),
-    }))
-    .unwrap();
-    // Send message
+    // Encode packet
+    let msg_buf = match encoder::encode(&packet) {
+        Ok(buf) => buf,
+        Err(err) => {
+            error!("[Core] Failed to encode OSC packet (addr={addr}, packet={packet:?}): {err}");
+            return Err(String::from("ENCODING_ERROR"));
+        }
+    };
+    // Send packet
     if socket.send_to(&msg_buf, to_addr).is_err() {
-        error!(
-            "[Core] Failed to send OSC message (addr={addr}, osc_addr={osc_addr}, data={data:?})"
-        );
+        error!("[Core] Failed to send OSC packet (addr={addr}, packet={packet:?})");
         return Err(String::from("SENDING_ERROR"));
     }
     Ok(true)
 }
 
+/// Sends multiple messages in a single bundle, so the receiver applies them at the same time.
+/// Without a timetag, the bundle is to be processed immediately.
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn osc_send_bundle(
+    addr: String,
+    messages: Vec<OSCMessage>,
+    timetag: Option<u64>,
+) -> Result<bool, String> {
+    debug!("[Core] Sending OSC bundle (messages={messages:?}, timetag={timetag:?})");
+    let content = messages
+        .iter()
+        .map(|message| {
+            Ok(OscPacket::Message(OscMessage {
+                addr: message.address.clone(),
+                args: message
+                    .values
+                    .iter()
+                    .map(OscType::try_from)
+                    .collect::<Result<Vec<_>, String>>()?,
+            }))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let timetag = match timetag {
+        Some(time) => OscTime {
+            seconds: (time >> 32) as u32,
+            fractional: time as u32,
+        },
+        None => super::OSC_TIMETAG_IMMEDIATELY,
+    };
+    osc_send_packet(addr, OscPacket::Bundle(OscBundle { timetag, content })).await
+}
+
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn set_osc_receive_address_whitelist(whitelist: Vec<String>) -> Result<(), String> {
+    super::OSC_RECEIVE_ADDRESS_FILTERS
+        .lock()
+        .await
+        .set_default(whitelist)
+}
+
+/// Sets the address patterns a subscriber wants to receive messages for, replacing its previous ones.
+/// Messages are forwarded to the frontend if they match the patterns of any subscriber.
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn set_osc_receive_address_filter(
+    subscriber: String,
+    patterns: Vec<String>,
+) -> Result<(), String> {
+    super::OSC_RECEIVE_ADDRESS_FILTERS
+        .lock()
+        .await
+        .set(subscriber, patterns)
+}
+
 #[tauri::command]
 #[oyasumivr_macros::command_profiling]
-pub async fn set_osc_receive_address_whitelist(whitelist: Vec<String>) {
-    *super::OSC_RECEIVE_ADDRESS_WHITELIST.lock().await = whitelist;
+pub async fn remove_osc_receive_address_filter(subscriber: String) -> Result<(), String> {
+    super::OSC_RECEIVE_ADDRESS_FILTERS
+        .lock()
+        .await
+        .remove(&subscriber)
 }
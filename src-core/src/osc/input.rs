@@ -0,0 +1,140 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        LazyLock,
+    },
+    time::Duration,
+};
+
+use log::{error, info};
+use rosc::OscType;
+use tokio::sync::Mutex;
+
+use super::commands::osc_send;
+
+const INPUT_ADDRESS_PREFIX: &str = "/input/";
+/// Held buttons and axes are released automatically after this time, so an input can never get stuck
+const MAX_HOLD_DURATION: Duration = Duration::from_secs(5);
+pub const DEFAULT_PRESS_DURATION: Duration = Duration::from_millis(100);
+
+/// Inputs that are currently held, by target and input name, with the press they belong to
+static HELD_INPUTS: LazyLock<Mutex<HashMap<(String, String), HeldInput>>> =
+    LazyLock::new(Default::default);
+static PRESS_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Clone, Copy)]
+struct HeldInput {
+    press_id: u64,
+    is_axis: bool,
+}
+
+/// Holds (or releases) a button. Buttons are released automatically after the given duration, which is capped
+/// to a few seconds.
+pub async fn set_button(
+    addr: String,
+    button: String,
+    pressed: bool,
+    hold_duration: Option<Duration>,
+) -> Result<bool, String> {
+    validate_input_name(&button)?;
+    let value = OscType::Int(pressed as i32);
+    if pressed {
+        hold(addr, button, value, false, hold_duration).await
+    } else {
+        release(addr, button, false).await
+    }
+}
+
+/// Sets an axis to a value between -1 and 1. Axes are reset to 0 automatically after a few seconds.
+pub async fn set_axis(addr: String, axis: String, value: f32) -> Result<bool, String> {
+    validate_input_name(&axis)?;
+    if !value.is_finite() {
+        return Err(String::from("INVALID_VALUE"));
+    }
+    let value = value.clamp(-1.0, 1.0);
+    if value == 0.0 {
+        release(addr, axis, true).await
+    } else {
+        hold(addr, axis, OscType::Float(value), true, None).await
+    }
+}
+
+pub async fn release_all() {
+    let held_inputs: Vec<_> = HELD_INPUTS.lock().await.drain().collect();
+    if !held_inputs.is_empty() {
+        info!(
+            "[Core] Releasing {} held VRChat input(s)",
+            held_inputs.len()
+        );
+    }
+    for ((addr, name), input) in held_inputs {
+        if let Err(e) = send_release(addr, &name, input.is_axis).await {
+            error!("[Core] Could not release VRChat input {name}: {e}");
+        }
+    }
+}
+
+async fn hold(
+    addr: String,
+    name: String,
+    value: OscType,
+    is_axis: bool,
+    hold_duration: Option<Duration>,
+) -> Result<bool, String> {
+    let result = osc_send(
+        addr.clone(),
+        format!("{INPUT_ADDRESS_PREFIX}{name}"),
+        vec![value],
+    )
+    .await?;
+    let press_id = PRESS_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let key = (addr, name);
+    HELD_INPUTS
+        .lock()
+        .await
+        .insert(key.clone(), HeldInput { press_id, is_axis });
+    let hold_duration = hold_duration
+        .unwrap_or(MAX_HOLD_DURATION)
+        .min(MAX_HOLD_DURATION);
+    tokio::spawn(async move {
+        tokio::time::sleep(hold_duration).await;
+        let mut held_inputs = HELD_INPUTS.lock().await;
+        // Only release if the input hasn't been pressed or released again in the meantime
+        if held_inputs.get(&key).map(|input| input.press_id) != Some(press_id) {
+            return;
+        }
+        held_inputs.remove(&key);
+        drop(held_inputs);
+        let (addr, name) = key;
+        if let Err(e) = send_release(addr, &name, is_axis).await {
+            error!("[Core] Could not release VRChat input {name}: {e}");
+        }
+    });
+    Ok(result)
+}
+
+async fn release(addr: String, name: String, is_axis: bool) -> Result<bool, String> {
+    HELD_INPUTS
+        .lock()
+        .await
+        .remove(&(addr.clone(), name.clone()));
+    // Release even if we don't know the input to be held, in case it was pressed by something else
+    send_release(addr, &name, is_axis).await
+}
+
+async fn send_release(addr: String, name: &str, is_axis: bool) -> Result<bool, String> {
+    let value = if is_axis {
+        OscType::Float(0.0)
+    } else {
+        OscType::Int(0)
+    };
+    osc_send(addr, format!("{INPUT_ADDRESS_PREFIX}{name}"), vec![value]).await
+}
+
+fn validate_input_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(String::from("INVALID_INPUT"));
+    }
+    Ok(())
+}
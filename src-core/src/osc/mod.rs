@@ -1,24 +1,53 @@
+mod address_pattern;
+mod avatar_parameters;
+mod chatbox;
 pub mod commands;
+mod input;
 mod models;
+mod oscquery;
+mod recorder;
+mod relay;
 mod vrchat;
 
 use std::{
-    io,
     net::{Ipv4Addr, UdpSocket},
-    sync::LazyLock,
+    sync::{
+        atomic::{AtomicU16, AtomicU64, Ordering},
+        LazyLock,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
+use address_pattern::ReceiveAddressFilters;
 use log::{error, info, warn};
-use models::{OSCMessage, OSCValue};
-use rosc::{OscPacket, OscType};
-use tokio::sync::Mutex;
+use models::{OSCMessage, OSCReceiverStats, OSCValue};
+use rosc::{OscMessage, OscPacket, OscTime};
+use tokio::sync::{
+    broadcast::{self, error::RecvError},
+    Mutex,
+};
 use tokio_util::sync::CancellationToken;
 
 use crate::utils::send_event;
 
+/// Timetag that indicates a bundle should be processed immediately
+pub const OSC_TIMETAG_IMMEDIATELY: OscTime = OscTime {
+    seconds: 0,
+    fractional: 1,
+};
+/// Seconds between the NTP epoch (1900) used by OSC timetags, and the UNIX epoch (1970)
+const NTP_UNIX_EPOCH_OFFSET: u64 = 2_208_988_800;
+const MAX_BUNDLE_DELAY: Duration = Duration::from_secs(10);
+/// The number of received packets that can be waiting to be processed, before the oldest get dropped
+const RECEIVE_QUEUE_SIZE: usize = 1024;
+
 pub static OSC_SEND_SOCKET: LazyLock<Mutex<Option<UdpSocket>>> = LazyLock::new(Default::default);
-pub static OSC_RECEIVE_SOCKET: LazyLock<Mutex<Option<UdpSocket>>> = LazyLock::new(Default::default);
-pub static OSC_RECEIVE_ADDRESS_WHITELIST: LazyLock<Mutex<Vec<String>>> =
+/// The port OyasumiVR receives OSC on, or 0 while the OSC server isn't running
+pub static OSC_RECEIVE_PORT: AtomicU16 = AtomicU16::new(0);
+static PACKETS_RECEIVED: AtomicU64 = AtomicU64::new(0);
+static PACKETS_DROPPED: AtomicU64 = AtomicU64::new(0);
+static PACKETS_MALFORMED: AtomicU64 = AtomicU64::new(0);
+pub static OSC_RECEIVE_ADDRESS_FILTERS: LazyLock<Mutex<ReceiveAddressFilters>> =
     LazyLock::new(Default::default);
 pub static VRC_OSC_ADDRESS: LazyLock<Mutex<Option<String>>> = LazyLock::new(|| Mutex::new(None));
 pub static VRC_OSCQUERY_ADDRESS: LazyLock<Mutex<Option<String>>> =
@@ -33,117 +62,185 @@ pub async fn init() {
             return;
         }
     };
+    // Start browsing for OSCQuery services
+    oscquery::spawn_discovery_task();
     // Spawn OSCQuery client task
     spawn_oscquery_client_task().await;
+    // Spawn chatbox queue task
+    chatbox::spawn_queue_task();
 }
 
 async fn spawn_oscquery_client_task() {
     tokio::spawn(async {
         loop {
             tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
-            let mut oscquery_addr = oyasumivr_oscquery::client::get_vrchat_oscquery_address().await;
-            if let Some((host, port)) = oscquery_addr.clone() {
-                let response = reqwest::get(format!("http://{host}:{port}/?HOST_INFO")).await;
-                if response.is_err() || response.as_ref().unwrap().status() != 200 {
-                    oscquery_addr = None;
-                }
-            }
-            let osc_addr = if oscquery_addr.is_some() {
-                oyasumivr_oscquery::client::get_vrchat_osc_address().await
-            } else {
-                None
-            };
+            let (oscquery_addr, osc_addr) = oscquery::resolve_target().await;
             set_vr_chat_osc_query_address(oscquery_addr).await;
             set_vr_chat_osc_address(osc_addr).await;
         }
     });
 }
 
-async fn spawn_receiver_task() -> CancellationToken {
+/// Spawns the tasks for receiving and processing OSC packets on the given socket.
+///
+/// Packets are decoded as soon as they arrive, and handed to the processing task through a bounded queue.
+/// If processing can't keep up, the oldest queued packets are dropped so the newest state always gets through.
+fn spawn_receiver_task(socket: tokio::net::UdpSocket) -> CancellationToken {
     info!("[Core] Starting OSC receiver task");
+    reset_receiver_stats();
+    let receive_port = socket.local_addr().map(|addr| addr.port()).unwrap_or(0);
+    OSC_RECEIVE_PORT.store(receive_port, Ordering::Relaxed);
     let cancellation_token = CancellationToken::new();
+    let (tx, mut rx) = broadcast::channel::<OscPacket>(RECEIVE_QUEUE_SIZE);
+    let relay_tx = relay::spawn_relay_task();
+    // Receive packets
     let cancellation_token_internal = cancellation_token.clone();
     tokio::spawn(async move {
-        'outer: while !cancellation_token_internal.is_cancelled() {
-            tokio::time::sleep(tokio::time::Duration::from_millis(16)).await;
-            let socket_guard = OSC_RECEIVE_SOCKET.lock().await;
-            let socket = match socket_guard.as_ref() {
-                Some(s) => s,
-                None => continue,
-            };
-            loop {
-                let mut buf = [0u8; rosc::decoder::MTU];
-                match socket.recv(&mut buf) {
-                    Ok(size) => {
-                        let result = match rosc::decoder::decode_udp(&buf[..size]) {
-                            Ok((_, packet)) => Some(packet),
-                            Err(err) => {
-                                warn!(
-                                    "[Core] Expected OSC packet, but UDP packet was malformed: {err}"
-                                );
-                                None
-                            }
-                        };
-                        if let Some(OscPacket::Message(msg)) = result {
-                            vrchat::process_event(msg.clone()).await;
-                            // check if address is whitelisted
-                            let address_whitelist_guard =
-                                OSC_RECEIVE_ADDRESS_WHITELIST.lock().await;
-                            let address_whitelist = address_whitelist_guard.clone();
-                            drop(address_whitelist_guard);
-                            if address_whitelist.contains(&msg.addr) {
-                                send_event(
-                                    "OSC_MESSAGE",
-                                    OSCMessage {
-                                        address: msg.addr,
-                                        values: msg
-                                            .args
-                                            .iter()
-                                            .map(|value| match value {
-                                                OscType::Int(v) => OSCValue {
-                                                    kind: "int".into(),
-                                                    value: Some(v.to_string()),
-                                                },
-                                                OscType::Float(v) => OSCValue {
-                                                    kind: "float".into(),
-                                                    value: Some(v.to_string()),
-                                                },
-                                                OscType::String(v) => OSCValue {
-                                                    kind: "string".into(),
-                                                    value: Some(v.clone()),
-                                                },
-                                                OscType::Bool(v) => OSCValue {
-                                                    kind: "bool".into(),
-                                                    value: Some(v.to_string()),
-                                                },
-                                                _ => OSCValue {
-                                                    kind: "unsupported".into(),
-                                                    value: None,
-                                                },
-                                            })
-                                            .collect(),
-                                    },
-                                )
-                                .await;
-                            }
-                        }
-                    }
-                    Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
-                        break;
-                    }
+        let mut buf = [0u8; rosc::decoder::MTU];
+        loop {
+            let size = tokio::select! {
+                _ = cancellation_token_internal.cancelled() => break,
+                result = socket.recv(&mut buf) => match result {
+                    Ok(size) => size,
                     Err(e) => {
                         error!("[Core] Error receiving on OSC socket: {e}");
-                        error!("[Core] Terminated OSC receiver task.");
-                        break 'outer;
+                        break;
                     }
+                },
+            };
+            PACKETS_RECEIVED.fetch_add(1, Ordering::Relaxed);
+            match rosc::decoder::decode_udp(&buf[..size]) {
+                Ok((_, packet)) => {
+                    if relay::is_active() {
+                        // If relaying can't keep up, packets are dropped rather than holding up receiving
+                        let _ = relay_tx.try_send((packet.clone(), buf[..size].to_vec()));
+                    }
+                    recorder::record(&buf[..size]).await;
+                    // Sending only fails if the processing task has already stopped
+                    let _ = tx.send(packet);
+                }
+                Err(err) => {
+                    PACKETS_MALFORMED.fetch_add(1, Ordering::Relaxed);
+                    warn!("[Core] Expected OSC packet, but UDP packet was malformed: {err}");
                 }
             }
         }
+        // Unless a new receiver has been started in the meantime
+        let _ = OSC_RECEIVE_PORT.compare_exchange(
+            receive_port,
+            0,
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+        );
         info!("[Core] Terminated OSC receiver task");
     });
+    // Process packets
+    let cancellation_token_internal = cancellation_token.clone();
+    tokio::spawn(async move {
+        loop {
+            let result = tokio::select! {
+                _ = cancellation_token_internal.cancelled() => break,
+                result = rx.recv() => result,
+            };
+            match result {
+                Ok(packet) => process_packet(packet).await,
+                Err(RecvError::Lagged(count)) => {
+                    PACKETS_DROPPED.fetch_add(count, Ordering::Relaxed);
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
     cancellation_token
 }
 
+fn reset_receiver_stats() {
+    PACKETS_RECEIVED.store(0, Ordering::Relaxed);
+    PACKETS_DROPPED.store(0, Ordering::Relaxed);
+    PACKETS_MALFORMED.store(0, Ordering::Relaxed);
+}
+
+pub fn get_receiver_stats() -> OSCReceiverStats {
+    OSCReceiverStats {
+        packets_received: PACKETS_RECEIVED.load(Ordering::Relaxed),
+        packets_dropped: PACKETS_DROPPED.load(Ordering::Relaxed),
+        packets_malformed: PACKETS_MALFORMED.load(Ordering::Relaxed),
+        queue_size: RECEIVE_QUEUE_SIZE,
+    }
+}
+
+async fn process_packet(packet: OscPacket) {
+    let mut messages = Vec::new();
+    flatten_packet(packet, None, &mut messages);
+    let mut scheduled: Vec<(Duration, Vec<OscMessage>)> = Vec::new();
+    for (timetag, msg) in messages {
+        match timetag.and_then(get_bundle_delay) {
+            Some(delay) => match scheduled.last_mut() {
+                Some((last_delay, msgs)) if *last_delay == delay => msgs.push(msg),
+                _ => scheduled.push((delay, vec![msg])),
+            },
+            None => process_message(msg).await,
+        }
+    }
+    // Deliver bundles with a timetag in the future once their time has come, keeping their messages in order
+    for (delay, msgs) in scheduled {
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            for msg in msgs {
+                process_message(msg).await;
+            }
+        });
+    }
+}
+
+/// Unpacks (nested) bundles into their messages, each paired with the timetag of the bundle containing it.
+fn flatten_packet(
+    packet: OscPacket,
+    timetag: Option<OscTime>,
+    messages: &mut Vec<(Option<OscTime>, OscMessage)>,
+) {
+    match packet {
+        OscPacket::Message(msg) => messages.push((timetag, msg)),
+        OscPacket::Bundle(bundle) => {
+            for packet in bundle.content {
+                flatten_packet(packet, Some(bundle.timetag), messages);
+            }
+        }
+    }
+}
+
+/// The time until a bundle with the given timetag should be processed, if that is in the future.
+fn get_bundle_delay(timetag: OscTime) -> Option<Duration> {
+    if timetag == OSC_TIMETAG_IMMEDIATELY {
+        return None;
+    }
+    let time = UNIX_EPOCH
+        + Duration::from_secs((timetag.seconds as u64).checked_sub(NTP_UNIX_EPOCH_OFFSET)?)
+        + Duration::from_nanos(((timetag.fractional as u64) * 1_000_000_000) >> 32);
+    let delay = time.duration_since(SystemTime::now()).ok()?;
+    if delay > MAX_BUNDLE_DELAY {
+        warn!("[Core] Received OSC bundle scheduled {delay:?} ahead. Delivering it in {MAX_BUNDLE_DELAY:?} instead.");
+        return Some(MAX_BUNDLE_DELAY);
+    }
+    Some(delay)
+}
+
+async fn process_message(msg: OscMessage) {
+    vrchat::process_event(msg.clone()).await;
+    // check if address matches any of the subscribed patterns
+    let is_match = OSC_RECEIVE_ADDRESS_FILTERS.lock().await.is_match(&msg.addr);
+    if is_match {
+        send_event(
+            "OSC_MESSAGE",
+            OSCMessage {
+                address: msg.addr,
+                values: msg.args.iter().map(OSCValue::from).collect(),
+            },
+        )
+        .await;
+    }
+}
+
 pub async fn set_vr_chat_osc_query_address(addr: Option<(String, u16)>) {
     let address = addr.map(|(host, port)| format!("{host}:{port}"));
     let current_address = VRC_OSCQUERY_ADDRESS.lock().await.clone();
@@ -154,6 +251,9 @@ pub async fn set_vr_chat_osc_query_address(addr: Option<(String, u16)>) {
         }
         *VRC_OSCQUERY_ADDRESS.lock().await = address.clone();
         send_event("VRC_OSCQUERY_ADDRESS_CHANGED", address.clone()).await;
+        if address.is_some() {
+            tokio::spawn(avatar_parameters::seed_from_oscquery());
+        }
     }
 }
 
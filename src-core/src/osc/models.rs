@@ -1,10 +1,19 @@
+use rosc::{OscArray, OscColor, OscMidiMessage, OscTime, OscType};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
+/// An OSC argument in a form that can be passed to and from the frontend.
+///
+/// Scalar values are encoded as strings in `value`: numbers in their decimal form, blobs as hex strings,
+/// timetags as a single 64-bit NTP timestamp, and colours and MIDI messages as 8 hex digits (RRGGBBAA and
+/// PPSSD1D2 respectively). Nil and infinitum have no value, and arrays carry their contents in `values`.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct OSCValue {
     pub kind: String,
     pub value: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub values: Option<Vec<OSCValue>>,
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -13,3 +22,342 @@ pub struct OSCMessage {
     pub address: String,
     pub values: Vec<OSCValue>,
 }
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OSCReceiverStats {
+    pub packets_received: u64,
+    pub packets_dropped: u64,
+    pub packets_malformed: u64,
+    pub queue_size: usize,
+}
+
+/// A destination that received OSC packets are forwarded to.
+/// If any filters are given, only messages matching them are forwarded. Addresses starting with the `from` of a
+/// rewrite rule get that prefix replaced with its `to` (only the first matching rule is applied).
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OSCRelayTarget {
+    pub id: String,
+    pub address: String,
+    pub enabled: bool,
+    #[serde(default)]
+    pub filters: Vec<String>,
+    #[serde(default)]
+    pub rewrites: Vec<OSCAddressRewrite>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OSCAddressRewrite {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OSCRelayTargetStatus {
+    pub id: String,
+    pub address: String,
+    /// One of `IDLE`, `OK`, `ERROR` or `DISABLED`. `ERROR` means the last few sends in a row have failed.
+    pub health: String,
+    pub packets_forwarded: u64,
+    pub send_errors: u64,
+    /// Failed sends since the last successful one
+    pub consecutive_send_errors: u64,
+    /// When a packet was last sent successfully
+    pub last_forwarded_at: Option<u64>,
+    /// The error of the last failed send, cleared once a send succeeds
+    pub last_error: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct VRCAvatarState {
+    pub avatar_id: Option<String>,
+    /// Parameters by name (without the `/avatar/parameters/` prefix)
+    pub parameters: HashMap<String, VRCAvatarParameter>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VRCAvatarParameter {
+    pub value: OSCValue,
+    pub updated_at: u64,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VRCAvatarParameterChangedEvent {
+    pub name: String,
+    pub value: OSCValue,
+}
+
+/// An application advertising an OSCQuery server and/or an OSC receiver on the network
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct OSCQueryService {
+    /// The mDNS instance name, shared by the OSCQuery and OSC services of the same application
+    pub name: String,
+    /// Advertised as `_oscjson._tcp`
+    pub oscquery: Option<OSCServiceAddress>,
+    /// Advertised as `_osc._udp`
+    pub osc: Option<OSCServiceAddress>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct OSCServiceAddress {
+    pub host: String,
+    pub port: u16,
+}
+
+/// Fixed OSC and OSCQuery addresses (host:port) to use instead of discovering them
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct OSCTargetOverride {
+    pub osc_address: Option<String>,
+    pub oscquery_address: Option<String>,
+}
+
+/// The `HOST_INFO` an OSCQuery server provides about itself
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OSCQueryHostInfo {
+    #[serde(alias = "NAME")]
+    pub name: Option<String>,
+    #[serde(alias = "OSC_IP")]
+    pub osc_ip: Option<String>,
+    #[serde(alias = "OSC_PORT")]
+    pub osc_port: Option<u16>,
+    #[serde(alias = "OSC_TRANSPORT")]
+    pub osc_transport: Option<String>,
+    #[serde(alias = "EXTENSIONS", default)]
+    pub extensions: HashMap<String, bool>,
+}
+
+impl OSCQueryHostInfo {
+    /// The address the server receives OSC on. If it listens on all interfaces, the given host is used instead.
+    pub fn osc_address(&self, host: &str) -> Option<(String, u16)> {
+        let port = self.osc_port?;
+        let ip = match self.osc_ip.as_deref() {
+            Some(ip) if ip != "0.0.0.0" => ip,
+            _ => host,
+        };
+        Some((ip.to_string(), port))
+    }
+}
+
+/// A node in an OSCQuery tree. Only nodes that represent OSC methods have a type tag.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OSCQueryNode {
+    #[serde(alias = "FULL_PATH")]
+    pub full_path: String,
+    #[serde(alias = "DESCRIPTION")]
+    pub description: Option<String>,
+    #[serde(alias = "TYPE")]
+    pub type_tag: Option<String>,
+    /// 0 (no access), 1 (read-only), 2 (write-only) or 3 (read and write)
+    #[serde(alias = "ACCESS")]
+    pub access: Option<u8>,
+    #[serde(alias = "RANGE", default, deserialize_with = "deserialize_one_or_many")]
+    pub range: Vec<OSCQueryRange>,
+    /// One value for every argument in the type tag
+    #[serde(alias = "VALUE", default, deserialize_with = "deserialize_one_or_many")]
+    pub value: Vec<serde_json::Value>,
+    #[serde(
+        alias = "CONTENTS",
+        default,
+        deserialize_with = "deserialize_oscquery_contents"
+    )]
+    pub contents: Vec<OSCQueryNode>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OSCQueryRange {
+    #[serde(alias = "MIN")]
+    pub min: Option<serde_json::Value>,
+    #[serde(alias = "MAX")]
+    pub max: Option<serde_json::Value>,
+    #[serde(alias = "VALS")]
+    pub vals: Option<Vec<serde_json::Value>>,
+}
+
+impl OSCQueryNode {
+    /// This node and all of its descendants
+    pub fn flatten(&self) -> Vec<&OSCQueryNode> {
+        let mut nodes = vec![self];
+        for child in self.contents.iter() {
+            nodes.extend(child.flatten());
+        }
+        nodes
+    }
+}
+
+/// OSCQuery servers send child nodes as a map by name, but the frontend is served a list sorted by path.
+/// Accepts both, so trees can be read back after being serialized.
+fn deserialize_oscquery_contents<'de, D>(deserializer: D) -> Result<Vec<OSCQueryNode>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Contents {
+        Map(HashMap<String, OSCQueryNode>),
+        List(Vec<OSCQueryNode>),
+    }
+    let mut nodes = match Contents::deserialize(deserializer)? {
+        Contents::Map(map) => map.into_values().collect(),
+        Contents::List(list) => list,
+    };
+    nodes.sort_by(|a, b| a.full_path.cmp(&b.full_path));
+    Ok(nodes)
+}
+
+/// The spec sends a list with an entry per argument, but some servers send a single value for methods with one
+/// argument, or `null` when there is no value.
+fn deserialize_one_or_many<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany<T> {
+        Many(Vec<T>),
+        One(T),
+    }
+    Ok(match Option::<OneOrMany<T>>::deserialize(deserializer)? {
+        Some(OneOrMany::Many(values)) => values,
+        Some(OneOrMany::One(value)) => vec![value],
+        None => Vec::new(),
+    })
+}
+
+impl OSCValue {
+    fn scalar(kind: &str, value: String) -> Self {
+        Self {
+            kind: kind.into(),
+            value: Some(value),
+            values: None,
+        }
+    }
+
+    fn empty(kind: &str) -> Self {
+        Self {
+            kind: kind.into(),
+            value: None,
+            values: None,
+        }
+    }
+}
+
+impl From<&OscType> for OSCValue {
+    fn from(value: &OscType) -> Self {
+        match value {
+            OscType::Int(v) => OSCValue::scalar("int", v.to_string()),
+            OscType::Float(v) => OSCValue::scalar("float", v.to_string()),
+            OscType::String(v) => OSCValue::scalar("string", v.clone()),
+            OscType::Bool(v) => OSCValue::scalar("bool", v.to_string()),
+            OscType::Long(v) => OSCValue::scalar("long", v.to_string()),
+            OscType::Double(v) => OSCValue::scalar("double", v.to_string()),
+            OscType::Char(v) => OSCValue::scalar("char", v.to_string()),
+            OscType::Blob(v) => OSCValue::scalar("blob", encode_hex(v)),
+            OscType::Time(v) => OSCValue::scalar(
+                "time",
+                (((v.seconds as u64) << 32) | v.fractional as u64).to_string(),
+            ),
+            OscType::Color(v) => {
+                OSCValue::scalar("color", encode_hex(&[v.red, v.green, v.blue, v.alpha]))
+            }
+            OscType::Midi(v) => {
+                OSCValue::scalar("midi", encode_hex(&[v.port, v.status, v.data1, v.data2]))
+            }
+            OscType::Nil => OSCValue::empty("nil"),
+            OscType::Inf => OSCValue::empty("inf"),
+            OscType::Array(v) => OSCValue {
+                kind: "array".into(),
+                value: None,
+                values: Some(v.content.iter().map(OSCValue::from).collect()),
+            },
+        }
+    }
+}
+
+impl TryFrom<&OSCValue> for OscType {
+    type Error = String;
+
+    fn try_from(value: &OSCValue) -> Result<Self, Self::Error> {
+        let kind = value.kind.as_str();
+        if kind == "array" {
+            let content = value
+                .values
+                .as_ref()
+                .ok_or_else(|| String::from("INVALID_ARRAY_VALUE"))?
+                .iter()
+                .map(OscType::try_from)
+                .collect::<Result<Vec<_>, _>>()?;
+            return Ok(OscType::Array(OscArray { content }));
+        }
+        parse_osc_value(kind, value.value.as_deref().unwrap_or_default())
+    }
+}
+
+/// Parses a scalar OSC argument from its string representation (See [OSCValue])
+pub fn parse_osc_value(kind: &str, value: &str) -> Result<OscType, String> {
+    let invalid = || format!("INVALID_{}_VALUE", kind.to_uppercase());
+    Ok(match kind {
+        "int" => OscType::Int(value.parse().map_err(|_| invalid())?),
+        "float" => OscType::Float(value.parse().map_err(|_| invalid())?),
+        "string" => OscType::String(value.to_string()),
+        "bool" => OscType::Bool(value.parse().map_err(|_| invalid())?),
+        "long" => OscType::Long(value.parse().map_err(|_| invalid())?),
+        "double" => OscType::Double(value.parse().map_err(|_| invalid())?),
+        "char" => OscType::Char(value.parse().map_err(|_| invalid())?),
+        "blob" => OscType::Blob(decode_hex(value).ok_or_else(invalid)?),
+        "time" => {
+            let time: u64 = value.parse().map_err(|_| invalid())?;
+            OscType::Time(OscTime {
+                seconds: (time >> 32) as u32,
+                fractional: time as u32,
+            })
+        }
+        "color" => match decode_hex(value).as_deref() {
+            Some(&[red, green, blue, alpha]) => OscType::Color(OscColor {
+                red,
+                green,
+                blue,
+                alpha,
+            }),
+            _ => return Err(invalid()),
+        },
+        "midi" => match decode_hex(value).as_deref() {
+            Some(&[port, status, data1, data2]) => OscType::Midi(OscMidiMessage {
+                port,
+                status,
+                data1,
+                data2,
+            }),
+            _ => return Err(invalid()),
+        },
+        "nil" => OscType::Nil,
+        "inf" => OscType::Inf,
+        _ => return Err(String::from("UNSUPPORTED_TYPE")),
+    })
+}
+
+pub fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+pub fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    if value.len() % 2 != 0 || !value.is_ascii() {
+        return None;
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&value[i..i + 2], 16).ok())
+        .collect()
+}
@@ -0,0 +1,401 @@
+use std::{collections::HashMap, sync::LazyLock};
+
+use log::{error, info, warn};
+use mdns_sd::{ServiceDaemon, ServiceEvent};
+use tokio::sync::Mutex;
+
+use crate::utils::send_event;
+
+use super::models::{
+    OSCQueryHostInfo, OSCQueryNode, OSCQueryService, OSCServiceAddress, OSCTargetOverride,
+};
+
+const OSCQUERY_SERVICE_TYPE: &str = "_oscjson._tcp.local.";
+const OSC_SERVICE_TYPE: &str = "_osc._udp.local.";
+
+/// All OSC and OSCQuery services currently advertised on the network, by their instance name
+static DISCOVERED_SERVICES: LazyLock<Mutex<HashMap<String, OSCQueryService>>> =
+    LazyLock::new(Default::default);
+/// The name of the discovered service to use as the OSC target, instead of the detected VRChat client
+static SELECTED_SERVICE: LazyLock<Mutex<Option<String>>> = LazyLock::new(Default::default);
+/// Fixed addresses to use, regardless of what is discovered
+static TARGET_OVERRIDE: LazyLock<Mutex<OSCTargetOverride>> = LazyLock::new(Default::default);
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum ServiceKind {
+    /// An OSCQuery (HTTP) server, advertised as `_oscjson._tcp`
+    OSCQueryServer,
+    /// An OSC (UDP) receiver, advertised as `_osc._udp`
+    OSCReceiver,
+}
+
+/// Browses the network for OSCQuery and OSC services, keeping [DISCOVERED_SERVICES] up to date
+pub fn spawn_discovery_task() {
+    let daemon = match ServiceDaemon::new() {
+        Ok(daemon) => daemon,
+        Err(e) => {
+            error!("[Core] Could not start mDNS daemon for OSCQuery discovery: {e}");
+            return;
+        }
+    };
+    let (oscquery_receiver, osc_receiver) = match (
+        daemon.browse(OSCQUERY_SERVICE_TYPE),
+        daemon.browse(OSC_SERVICE_TYPE),
+    ) {
+        (Ok(oscquery_receiver), Ok(osc_receiver)) => (oscquery_receiver, osc_receiver),
+        (Err(e), _) | (_, Err(e)) => {
+            error!("[Core] Could not browse for OSCQuery services: {e}");
+            return;
+        }
+    };
+    tokio::spawn(async move {
+        // Keep the daemon alive for as long as we're browsing
+        let _daemon = daemon;
+        loop {
+            let event = tokio::select! {
+                event = oscquery_receiver.recv_async() => event,
+                event = osc_receiver.recv_async() => event,
+            };
+            let Ok(event) = event else {
+                break;
+            };
+            let changed = {
+                let mut services = DISCOVERED_SERVICES.lock().await;
+                update_services(&mut services, event)
+            };
+            if changed {
+                send_event("OSCQUERY_SERVICES_CHANGED", get_services().await).await;
+            }
+        }
+        warn!("[Core] Stopped browsing for OSCQuery services");
+    });
+}
+
+/// Applies a discovery event to the given services, and returns whether anything changed.
+/// Applications usually advertise their OSC and OSCQuery services under the same instance name, so both end up in
+/// the same entry.
+fn update_services(services: &mut HashMap<String, OSCQueryService>, event: ServiceEvent) -> bool {
+    match event {
+        ServiceEvent::ServiceResolved(info) => {
+            let Some((name, kind)) = parse_fullname(info.get_fullname()) else {
+                return false;
+            };
+            let Some(ip) = info.get_addresses_v4().into_iter().next() else {
+                return false;
+            };
+            let address = OSCServiceAddress {
+                host: ip.to_string(),
+                port: info.get_port(),
+            };
+            let service = services
+                .entry(name.clone())
+                .or_insert_with(|| OSCQueryService {
+                    name,
+                    oscquery: None,
+                    osc: None,
+                });
+            let slot = match kind {
+                ServiceKind::OSCQueryServer => &mut service.oscquery,
+                ServiceKind::OSCReceiver => &mut service.osc,
+            };
+            slot.replace(address.clone()) != Some(address)
+        }
+        ServiceEvent::ServiceRemoved(_, fullname) => {
+            let Some((name, kind)) = parse_fullname(&fullname) else {
+                return false;
+            };
+            let Some(service) = services.get_mut(&name) else {
+                return false;
+            };
+            let removed = match kind {
+                ServiceKind::OSCQueryServer => service.oscquery.take(),
+                ServiceKind::OSCReceiver => service.osc.take(),
+            };
+            if service.oscquery.is_none() && service.osc.is_none() {
+                services.remove(&name);
+            }
+            removed.is_some()
+        }
+        _ => false,
+    }
+}
+
+/// Splits a full mDNS service name (e.g. `VRChat-Client-ABC123._oscjson._tcp.local.`) into its instance name and kind
+fn parse_fullname(fullname: &str) -> Option<(String, ServiceKind)> {
+    let (name, kind) = if let Some(name) = fullname.strip_suffix(OSCQUERY_SERVICE_TYPE) {
+        (name, ServiceKind::OSCQueryServer)
+    } else {
+        (
+            fullname.strip_suffix(OSC_SERVICE_TYPE)?,
+            ServiceKind::OSCReceiver,
+        )
+    };
+    let name = name.strip_suffix('.')?;
+    if name.is_empty() {
+        return None;
+    }
+    Some((name.to_string(), kind))
+}
+
+pub async fn get_services() -> Vec<OSCQueryService> {
+    let mut services: Vec<OSCQueryService> =
+        DISCOVERED_SERVICES.lock().await.values().cloned().collect();
+    services.sort_by(|a, b| a.name.cmp(&b.name));
+    services
+}
+
+pub async fn select_service(name: Option<String>) {
+    if let Some(name) = &name {
+        info!("[Core] Using OSCQuery service \"{name}\" as OSC target");
+    }
+    *SELECTED_SERVICE.lock().await = name;
+}
+
+pub async fn get_selected_service() -> Option<String> {
+    SELECTED_SERVICE.lock().await.clone()
+}
+
+pub async fn set_target_override(target_override: OSCTargetOverride) -> Result<(), String> {
+    for address in [
+        &target_override.osc_address,
+        &target_override.oscquery_address,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        parse_host_port(address).ok_or_else(|| String::from("INVALID_ADDRESS"))?;
+    }
+    *TARGET_OVERRIDE.lock().await = target_override;
+    Ok(())
+}
+
+pub async fn get_target_override() -> OSCTargetOverride {
+    TARGET_OVERRIDE.lock().await.clone()
+}
+
+/// Determines the OSCQuery and OSC addresses to target.
+///
+/// Configured overrides take precedence, followed by the selected discovered service. Otherwise, the VRChat
+/// client found by the OSCQuery client is used. An OSCQuery address is only returned if it responds. Unless
+/// overridden or advertised by the selected service, the OSC address is taken from its host info.
+/// The host info is fetched only once per call.
+pub async fn resolve_target() -> (Option<(String, u16)>, Option<(String, u16)>) {
+    let target_override = TARGET_OVERRIDE.lock().await.clone();
+    let selected_service = match SELECTED_SERVICE.lock().await.clone() {
+        Some(name) => DISCOVERED_SERVICES.lock().await.get(&name).cloned(),
+        None => None,
+    };
+    let use_vrchat_client =
+        target_override.oscquery_address.is_none() && selected_service.is_none();
+    let mut oscquery_addr = match target_override
+        .oscquery_address
+        .as_deref()
+        .and_then(parse_host_port)
+    {
+        Some(addr) => Some(addr),
+        None => match &selected_service {
+            Some(service) => service
+                .oscquery
+                .clone()
+                .map(|address| (address.host, address.port)),
+            None => oyasumivr_oscquery::client::get_vrchat_oscquery_address().await,
+        },
+    };
+    let host_info = match oscquery_addr.clone() {
+        Some((host, port)) => fetch_host_info(&host, port).await,
+        None => None,
+    };
+    if host_info.is_none() {
+        oscquery_addr = None;
+    }
+    let advertised_osc_addr = selected_service
+        .and_then(|service| service.osc)
+        .map(|address| (address.host, address.port));
+    let osc_addr = match target_override
+        .osc_address
+        .as_deref()
+        .and_then(parse_host_port)
+    {
+        Some(addr) => Some(addr),
+        // Services that advertise their OSC receiver can be sent to without asking their OSCQuery server
+        None if advertised_osc_addr.is_some() => advertised_osc_addr,
+        None if oscquery_addr.is_none() => None,
+        None if use_vrchat_client => oyasumivr_oscquery::client::get_vrchat_osc_address().await,
+        None => host_info.and_then(|info| {
+            let (host, _) = oscquery_addr.clone()?;
+            info.osc_address(&host)
+        }),
+    };
+    (oscquery_addr, osc_addr)
+}
+
+pub async fn fetch_host_info(host: &str, port: u16) -> Option<OSCQueryHostInfo> {
+    let response = reqwest::get(format!("http://{host}:{port}/?HOST_INFO"))
+        .await
+        .ok()?;
+    if response.status() != 200 {
+        return None;
+    }
+    response.json::<OSCQueryHostInfo>().await.ok()
+}
+
+/// Fetches the OSCQuery tree of a server, starting at the given path
+pub async fn fetch_tree(host: &str, port: u16, path: &str) -> Result<OSCQueryNode, String> {
+    let path = if path.starts_with('/') {
+        path.to_string()
+    } else {
+        format!("/{path}")
+    };
+    let response = reqwest::get(format!("http://{host}:{port}{path}"))
+        .await
+        .map_err(|_| String::from("REQUEST_FAILED"))?;
+    match response.status().as_u16() {
+        200 => {}
+        404 => return Err(String::from("NOT_FOUND")),
+        _ => return Err(String::from("REQUEST_FAILED")),
+    }
+    response.json::<OSCQueryNode>().await.map_err(|e| {
+        warn!("[Core] Received invalid OSCQuery tree from {host}:{port}: {e}");
+        String::from("INVALID_RESPONSE")
+    })
+}
+
+pub fn parse_host_port(address: &str) -> Option<(String, u16)> {
+    let (host, port) = address.rsplit_once(':')?;
+    if host.is_empty() {
+        return None;
+    }
+    Some((host.to_string(), port.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use mdns_sd::ServiceInfo;
+
+    use super::*;
+
+    fn resolved(service_type: &str, name: &str, ip: &str, port: u16) -> ServiceEvent {
+        let info = ServiceInfo::new(
+            service_type,
+            name,
+            "host.local.",
+            ip,
+            port,
+            None::<HashMap<String, String>>,
+        )
+        .unwrap();
+        ServiceEvent::ServiceResolved(info)
+    }
+
+    fn removed(service_type: &str, name: &str) -> ServiceEvent {
+        ServiceEvent::ServiceRemoved(service_type.into(), format!("{name}.{service_type}"))
+    }
+
+    fn address(host: &str, port: u16) -> Option<OSCServiceAddress> {
+        Some(OSCServiceAddress {
+            host: host.into(),
+            port,
+        })
+    }
+
+    #[test]
+    fn parses_full_service_names() {
+        assert_eq!(
+            parse_fullname("VRChat-Client-ABC123._oscjson._tcp.local."),
+            Some(("VRChat-Client-ABC123".into(), ServiceKind::OSCQueryServer))
+        );
+        assert_eq!(
+            parse_fullname("VRChat-Client-ABC123._osc._udp.local."),
+            Some(("VRChat-Client-ABC123".into(), ServiceKind::OSCReceiver))
+        );
+        assert_eq!(parse_fullname("._osc._udp.local."), None);
+        assert_eq!(parse_fullname("Printer._ipp._tcp.local."), None);
+    }
+
+    #[test]
+    fn keeps_every_discovered_instance() {
+        let mut services = HashMap::new();
+        assert!(update_services(
+            &mut services,
+            resolved(
+                OSCQUERY_SERVICE_TYPE,
+                "VRChat-Client-ABC123",
+                "192.168.1.10",
+                9010
+            )
+        ));
+        assert!(update_services(
+            &mut services,
+            resolved(
+                OSC_SERVICE_TYPE,
+                "VRChat-Client-ABC123",
+                "192.168.1.10",
+                9000
+            )
+        ));
+        assert!(update_services(
+            &mut services,
+            resolved(
+                OSCQUERY_SERVICE_TYPE,
+                "VRCFaceTracking",
+                "192.168.1.20",
+                9020
+            )
+        ));
+        assert!(update_services(
+            &mut services,
+            resolved(OSC_SERVICE_TYPE, "TouchOSC", "192.168.1.30", 8000)
+        ));
+        // Resolving the same address again is not a change
+        assert!(!update_services(
+            &mut services,
+            resolved(OSC_SERVICE_TYPE, "TouchOSC", "192.168.1.30", 8000)
+        ));
+
+        assert_eq!(services.len(), 3);
+        let vrchat = &services["VRChat-Client-ABC123"];
+        assert_eq!(vrchat.oscquery, address("192.168.1.10", 9010));
+        assert_eq!(vrchat.osc, address("192.168.1.10", 9000));
+        assert_eq!(services["VRCFaceTracking"].osc, None);
+        assert_eq!(services["TouchOSC"].oscquery, None);
+        assert_eq!(services["TouchOSC"].osc, address("192.168.1.30", 8000));
+    }
+
+    #[test]
+    fn forgets_instances_once_all_their_services_are_removed() {
+        let mut services = HashMap::new();
+        update_services(
+            &mut services,
+            resolved(
+                OSCQUERY_SERVICE_TYPE,
+                "VRChat-Client-ABC123",
+                "192.168.1.10",
+                9010,
+            ),
+        );
+        update_services(
+            &mut services,
+            resolved(
+                OSC_SERVICE_TYPE,
+                "VRChat-Client-ABC123",
+                "192.168.1.10",
+                9000,
+            ),
+        );
+
+        assert!(update_services(
+            &mut services,
+            removed(OSCQUERY_SERVICE_TYPE, "VRChat-Client-ABC123")
+        ));
+        assert_eq!(services["VRChat-Client-ABC123"].oscquery, None);
+        assert!(!update_services(
+            &mut services,
+            removed(OSCQUERY_SERVICE_TYPE, "VRChat-Client-ABC123")
+        ));
+        assert!(update_services(
+            &mut services,
+            removed(OSC_SERVICE_TYPE, "VRChat-Client-ABC123")
+        ));
+        assert!(services.is_empty());
+    }
+}
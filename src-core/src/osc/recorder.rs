@@ -0,0 +1,159 @@
+use std::{path::PathBuf, sync::LazyLock, time::Duration};
+
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    fs::File,
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter},
+    sync::Mutex,
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::utils::{get_time, send_event};
+
+use super::models::{decode_hex, encode_hex};
+
+static RECORDING: LazyLock<Mutex<Option<Recording>>> = LazyLock::new(Default::default);
+static REPLAY_CANCELLATION_TOKEN: LazyLock<Mutex<Option<CancellationToken>>> =
+    LazyLock::new(Default::default);
+
+/// A single line in a recording file.
+/// The raw packet is stored (hex encoded) instead of its decoded form, so bundles and argument types are
+/// replayed exactly as they were received.
+#[derive(Serialize, Deserialize)]
+struct RecordedPacket {
+    time: u64,
+    packet: String,
+}
+
+struct Recording {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    packets: u64,
+}
+
+/// Starts writing every received OSC packet to the given file (as JSON lines), replacing any existing file.
+pub async fn start_recording(path: PathBuf) -> Result<(), String> {
+    let mut recording = RECORDING.lock().await;
+    if recording.is_some() {
+        return Err(String::from("ALREADY_RECORDING"));
+    }
+    let file = File::create(&path).await.map_err(|e| {
+        error!("[Core] Could not create OSC recording file: {e}");
+        String::from("WRITE_ERROR")
+    })?;
+    info!("[Core] Recording OSC packets to {}", path.display());
+    *recording = Some(Recording {
+        path,
+        writer: BufWriter::new(file),
+        packets: 0,
+    });
+    Ok(())
+}
+
+/// Stops the active recording, and returns the number of packets it contains
+pub async fn stop_recording() -> Result<u64, String> {
+    let mut recording = match RECORDING.lock().await.take() {
+        Some(recording) => recording,
+        None => return Err(String::from("NOT_RECORDING")),
+    };
+    if let Err(e) = recording.writer.flush().await {
+        error!("[Core] Could not write OSC recording file: {e}");
+        return Err(String::from("WRITE_ERROR"));
+    }
+    info!(
+        "[Core] Stopped recording OSC packets. Recorded {} packets to {}",
+        recording.packets,
+        recording.path.display()
+    );
+    Ok(recording.packets)
+}
+
+pub async fn record(raw: &[u8]) {
+    let mut recording_guard = RECORDING.lock().await;
+    let recording = match recording_guard.as_mut() {
+        Some(recording) => recording,
+        None => return,
+    };
+    let mut line = serde_json::to_string(&RecordedPacket {
+        time: get_time() as u64,
+        packet: encode_hex(raw),
+    })
+    .unwrap();
+    line.push('\n');
+    if let Err(e) = recording.writer.write_all(line.as_bytes()).await {
+        error!("[Core] Could not write to OSC recording file, stopping recording: {e}");
+        *recording_guard = None;
+        return;
+    }
+    recording.packets += 1;
+}
+
+/// Feeds a recording back through the regular packet processing, as if the packets were received again.
+/// The original timing between packets is kept, scaled by the given speed (2.0 replays twice as fast).
+pub async fn start_replay(path: PathBuf, speed: f64) -> Result<(), String> {
+    if !speed.is_finite() || speed <= 0.0 {
+        return Err(String::from("INVALID_SPEED"));
+    }
+    let file = File::open(&path)
+        .await
+        .map_err(|_| String::from("READ_ERROR"))?;
+    stop_replay().await;
+    let cancellation_token = CancellationToken::new();
+    let cancellation_token_internal = cancellation_token.clone();
+    *REPLAY_CANCELLATION_TOKEN.lock().await = Some(cancellation_token);
+    info!(
+        "[Core] Replaying OSC recording {} at {speed}x speed",
+        path.display()
+    );
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(file).lines();
+        let mut first_time = None;
+        let start = tokio::time::Instant::now();
+        let mut packets = 0;
+        loop {
+            let line = match lines.next_line().await {
+                Ok(Some(line)) => line,
+                Ok(None) => break,
+                Err(e) => {
+                    error!("[Core] Could not read OSC recording: {e}");
+                    break;
+                }
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (time, packet) = match parse_recorded_packet(&line) {
+                Some(entry) => entry,
+                None => {
+                    warn!("[Core] Skipping unreadable entry in OSC recording");
+                    continue;
+                }
+            };
+            let offset = time.saturating_sub(*first_time.get_or_insert(time));
+            let due = start + Duration::from_millis(offset).div_f64(speed);
+            tokio::select! {
+                _ = cancellation_token_internal.cancelled() => break,
+                _ = tokio::time::sleep_until(due) => {}
+            }
+            super::process_packet(packet).await;
+            packets += 1;
+        }
+        info!("[Core] Finished replaying {packets} OSC packets");
+        send_event("OSC_REPLAY_FINISHED", packets).await;
+    });
+    Ok(())
+}
+
+pub async fn stop_replay() {
+    if let Some(token) = REPLAY_CANCELLATION_TOKEN.lock().await.take() {
+        token.cancel();
+    }
+}
+
+fn parse_recorded_packet(line: &str) -> Option<(u64, rosc::OscPacket)> {
+    let entry: RecordedPacket = serde_json::from_str(line).ok()?;
+    let raw = decode_hex(&entry.packet)?;
+    let (_, packet) = rosc::decoder::decode_udp(&raw).ok()?;
+    Some((entry.time, packet))
+}
@@ -0,0 +1,252 @@
+use std::{
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        LazyLock,
+    },
+};
+
+use log::{info, warn};
+use rosc::{encoder, OscBundle, OscPacket};
+use tokio::sync::{mpsc, Mutex};
+
+use crate::utils::get_time;
+
+use super::{
+    address_pattern::AddressFilter,
+    models::{OSCRelayTarget, OSCRelayTargetStatus},
+    OSC_RECEIVE_PORT, OSC_SEND_SOCKET,
+};
+
+/// The number of received packets that can be waiting to be relayed, before new ones get dropped
+const RELAY_QUEUE_SIZE: usize = 1024;
+/// How many sends in a row have to fail before a target is reported as unhealthy.
+/// A single failure is often caused by the target briefly not listening, and resolves itself.
+const UNHEALTHY_CONSECUTIVE_SEND_ERRORS: u64 = 3;
+
+static RELAY_TARGETS: LazyLock<Mutex<Vec<RelayTarget>>> = LazyLock::new(Default::default);
+/// Whether any relay target is enabled, so the receiver can skip copying packets when there is nothing to relay
+static RELAY_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// A received packet, along with its raw bytes
+pub type RelayPacket = (OscPacket, Vec<u8>);
+
+/// A target that received OSC packets get forwarded to
+struct RelayTarget {
+    config: OSCRelayTarget,
+    socket_addr: SocketAddr,
+    // No filter means every message gets forwarded
+    filter: Option<AddressFilter>,
+    /// Whether the target address belongs to this machine, in which case it could be OyasumiVR's own receiver
+    is_local: bool,
+    packets_forwarded: u64,
+    send_errors: u64,
+    consecutive_send_errors: u64,
+    last_forwarded_at: Option<u64>,
+    last_error: Option<String>,
+}
+
+impl RelayTarget {
+    fn new(config: OSCRelayTarget) -> Result<Self, String> {
+        let socket_addr = config
+            .address
+            .to_socket_addrs()
+            .ok()
+            .and_then(|mut addrs| addrs.next())
+            .ok_or_else(|| String::from("INVALID_ADDRESS"))?;
+        let filter = if config.filters.is_empty() {
+            None
+        } else {
+            Some(AddressFilter::new(&config.filters)?)
+        };
+        // Only addresses of this machine can be bound to
+        let ip = socket_addr.ip();
+        let is_local = ip.is_loopback()
+            || ip.is_unspecified()
+            || UdpSocket::bind(SocketAddr::new(ip, 0)).is_ok();
+        let target = Self {
+            config,
+            socket_addr,
+            filter,
+            is_local,
+            packets_forwarded: 0,
+            send_errors: 0,
+            consecutive_send_errors: 0,
+            last_forwarded_at: None,
+            last_error: None,
+        };
+        if target.loops_back(OSC_RECEIVE_PORT.load(Ordering::Relaxed)) {
+            return Err(String::from("RELAY_LOOP"));
+        }
+        Ok(target)
+    }
+
+    /// Whether packets sent to this target would be received by OyasumiVR itself, relaying them forever
+    fn loops_back(&self, receive_port: u16) -> bool {
+        receive_port != 0 && self.is_local && self.socket_addr.port() == receive_port
+    }
+
+    fn status(&self) -> OSCRelayTargetStatus {
+        let health = match self.last_forwarded_at {
+            _ if !self.config.enabled => "DISABLED",
+            _ if self.consecutive_send_errors >= UNHEALTHY_CONSECUTIVE_SEND_ERRORS => "ERROR",
+            Some(_) => "OK",
+            None => "IDLE",
+        };
+        OSCRelayTargetStatus {
+            id: self.config.id.clone(),
+            address: self.config.address.clone(),
+            health: health.into(),
+            packets_forwarded: self.packets_forwarded,
+            send_errors: self.send_errors,
+            consecutive_send_errors: self.consecutive_send_errors,
+            last_forwarded_at: self.last_forwarded_at,
+            last_error: self.last_error.clone(),
+        }
+    }
+
+    /// Builds the packet to forward to this target, with only the messages that pass its filter and with
+    /// their addresses rewritten. Returns `None` if nothing is left to forward.
+    fn transform(&self, packet: &OscPacket) -> Option<OscPacket> {
+        match packet {
+            OscPacket::Message(msg) => {
+                if !self.filter.as_ref().is_none_or(|f| f.is_match(&msg.addr)) {
+                    return None;
+                }
+                let mut msg = msg.clone();
+                if let Some(rule) = self
+                    .config
+                    .rewrites
+                    .iter()
+                    .find(|rule| msg.addr.starts_with(&rule.from))
+                {
+                    msg.addr = format!("{}{}", rule.to, &msg.addr[rule.from.len()..]);
+                }
+                Some(OscPacket::Message(msg))
+            }
+            OscPacket::Bundle(bundle) => {
+                let content: Vec<OscPacket> = bundle
+                    .content
+                    .iter()
+                    .filter_map(|packet| self.transform(packet))
+                    .collect();
+                if content.is_empty() {
+                    return None;
+                }
+                Some(OscPacket::Bundle(OscBundle {
+                    timetag: bundle.timetag,
+                    content,
+                }))
+            }
+        }
+    }
+
+    fn is_passthrough(&self) -> bool {
+        self.filter.is_none() && self.config.rewrites.is_empty()
+    }
+}
+
+/// Replaces the configured relay targets. The statistics of targets that remain configured are kept.
+pub async fn set_targets(configs: Vec<OSCRelayTarget>) -> Result<(), String> {
+    let mut targets = Vec::with_capacity(configs.len());
+    for config in configs {
+        targets.push(RelayTarget::new(config)?);
+    }
+    let mut targets_guard = RELAY_TARGETS.lock().await;
+    for target in targets.iter_mut() {
+        if let Some(old) = targets_guard
+            .iter()
+            .find(|old| old.config.id == target.config.id && old.socket_addr == target.socket_addr)
+        {
+            target.packets_forwarded = old.packets_forwarded;
+            target.send_errors = old.send_errors;
+            target.consecutive_send_errors = old.consecutive_send_errors;
+            target.last_forwarded_at = old.last_forwarded_at;
+            target.last_error = old.last_error.clone();
+        }
+    }
+    info!("[Core] Relaying OSC to {} target(s)", targets.len());
+    RELAY_ACTIVE.store(targets.iter().any(|t| t.config.enabled), Ordering::Relaxed);
+    *targets_guard = targets;
+    Ok(())
+}
+
+pub async fn get_status() -> Vec<OSCRelayTargetStatus> {
+    RELAY_TARGETS
+        .lock()
+        .await
+        .iter()
+        .map(RelayTarget::status)
+        .collect()
+}
+
+pub fn is_active() -> bool {
+    RELAY_ACTIVE.load(Ordering::Relaxed)
+}
+
+/// Spawns the task that forwards received packets to the relay targets, and returns the queue to hand packets to.
+/// Sending is done on a blocking thread, so it never holds up receiving. The task stops once the queue is dropped.
+pub fn spawn_relay_task() -> mpsc::Sender<RelayPacket> {
+    let (tx, mut rx) = mpsc::channel::<RelayPacket>(RELAY_QUEUE_SIZE);
+    tokio::task::spawn_blocking(move || {
+        while let Some((packet, raw)) = rx.blocking_recv() {
+            forward(&packet, &raw);
+        }
+    });
+    tx
+}
+
+/// Forwards a received packet to every enabled relay target.
+/// The raw packet is passed on as-is to targets that don't filter or rewrite, to avoid re-encoding it.
+fn forward(packet: &OscPacket, raw: &[u8]) {
+    let mut targets = RELAY_TARGETS.blocking_lock();
+    if targets.is_empty() {
+        return;
+    }
+    let socket_guard = OSC_SEND_SOCKET.blocking_lock();
+    let socket = match socket_guard.as_ref() {
+        Some(socket) => socket,
+        None => return,
+    };
+    let receive_port = OSC_RECEIVE_PORT.load(Ordering::Relaxed);
+    for target in targets
+        .iter_mut()
+        .filter(|t| t.config.enabled && !t.loops_back(receive_port))
+    {
+        let result = if target.is_passthrough() {
+            socket.send_to(raw, target.socket_addr)
+        } else {
+            let buf = match target.transform(packet).map(|p| encoder::encode(&p)) {
+                Some(Ok(buf)) => buf,
+                Some(Err(e)) => {
+                    warn!(
+                        "[Core] Could not encode OSC packet for relay target {}: {e}",
+                        target.config.address
+                    );
+                    continue;
+                }
+                None => continue,
+            };
+            socket.send_to(&buf, target.socket_addr)
+        };
+        match result {
+            Ok(_) => {
+                target.packets_forwarded += 1;
+                target.last_forwarded_at = Some(get_time() as u64);
+                target.consecutive_send_errors = 0;
+                target.last_error = None;
+            }
+            Err(e) => {
+                if target.last_error.is_none() {
+                    warn!(
+                        "[Core] Could not relay OSC packet to {}: {e}",
+                        target.config.address
+                    );
+                }
+                target.send_errors += 1;
+                target.consecutive_send_errors += 1;
+                target.last_error = Some(e.to_string());
+            }
+        }
+    }
+}
@@ -43,6 +43,7 @@ async fn process_voice_parameter(value: Option<f32>) {
 }
 
 pub async fn process_event(msg: rosc::OscMessage) {
+    super::avatar_parameters::process_message(&msg).await;
     if msg.addr.as_str() == "/avatar/parameters/Voice" {
         let value = msg.args[0].clone();
         process_voice_parameter(value.float()).await;
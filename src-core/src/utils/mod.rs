@@ -1,13 +1,14 @@
-use log::error;
+use log::{error, warn};
 use serde::Serialize;
 use std::{
     ffi::OsStr,
     os::raw::c_char,
+    path::PathBuf,
     sync::LazyLock,
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
 use sysinfo::{ProcessesToUpdate, Signal, System};
-use tauri::Emitter;
+use tauri::{Emitter, Manager};
 use tokio::sync::Mutex;
 
 use crate::globals::{TAURI_APP_HANDLE, TAURI_CLI_MATCHES};
@@ -64,10 +65,25 @@ pub fn get_time() -> u128 {
     since_the_epoch.as_millis()
 }
 
+/// Path for storing a file in the application's local data directory
+pub async fn get_data_file_path(file_name: &str) -> Option<PathBuf> {
+    let app_handle_guard = TAURI_APP_HANDLE.lock().await;
+    let app_handle = app_handle_guard.as_ref()?;
+    let dir = app_handle.path().app_local_data_dir().ok()?;
+    if let Err(e) = std::fs::create_dir_all(&dir) {
+        warn!("[Core] Could not create local data directory: {e}");
+        return None;
+    }
+    Some(dir.join(file_name))
+}
+
 pub async fn send_event<S: Serialize + Clone>(event: &str, payload: S) {
     profiling::register_event(event).await;
     let app_handle_guard = TAURI_APP_HANDLE.lock().await;
-    let app_handle = app_handle_guard.as_ref().unwrap();
+    // There is nobody to send events to before the app has started (or in tests)
+    let Some(app_handle) = app_handle_guard.as_ref() else {
+        return;
+    };
     match app_handle.emit(event, payload) {
         Ok(_) => {}
         Err(e) => {
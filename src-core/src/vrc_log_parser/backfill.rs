@@ -0,0 +1,237 @@
+use log::{info, warn};
+use std::{
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::utils::send_event;
+
+use super::{
+    history::{self, HistoryEntry},
+    locator, log_file_name, matchers,
+    models::{VRCLogEvent, VRCLogImportProgressEvent, VRCLogImportResult},
+    tailer,
+};
+
+static IMPORTING: AtomicBool = AtomicBool::new(false);
+
+enum LogSource {
+    File(PathBuf),
+    ZipEntry(PathBuf, usize, String),
+}
+
+impl LogSource {
+    fn name(&self) -> String {
+        match self {
+            LogSource::File(path) => path.display().to_string(),
+            LogSource::ZipEntry(path, _, name) => format!("{}:{}", path.display(), name),
+        }
+    }
+}
+
+/// Parses every VRChat log found at the given path (a log directory, a zip archive or a single log file),
+/// and stores the events in the session history with their original timestamps.
+/// If no path is given, the VRChat log directory is used.
+pub async fn import_logs(path: Option<PathBuf>) -> Result<VRCLogImportResult, String> {
+    if IMPORTING.swap(true, Ordering::Relaxed) {
+        return Err(String::from("ALREADY_IMPORTING"));
+    }
+    let result = run_import(path).await;
+    IMPORTING.store(false, Ordering::Relaxed);
+    result
+}
+
+async fn run_import(path: Option<PathBuf>) -> Result<VRCLogImportResult, String> {
+    let path = match path {
+        Some(path) => path,
+        None => locator::resolve_log_dir()
+            .await
+            .ok_or_else(|| String::from("LOG_DIR_NOT_FOUND"))?,
+    };
+    info!("[Core] Importing VRChat logs from {}", path.display());
+    let sources = tokio::task::spawn_blocking(move || list_sources(&path))
+        .await
+        .map_err(|_| String::from("READ_ERROR"))??;
+    let mut result = VRCLogImportResult {
+        files_processed: 0,
+        files_failed: 0,
+        lines_processed: 0,
+        events_found: 0,
+        events_imported: 0,
+    };
+    let files_total = sources.len();
+    for source in sources {
+        let name = source.name();
+        match tokio::task::spawn_blocking(move || parse_source(&source)).await {
+            Ok(Ok((lines, entries))) => {
+                result.files_processed += 1;
+                result.lines_processed += lines;
+                result.events_found += entries.len();
+                result.events_imported += history::record_events(&entries).await;
+            }
+            Ok(Err(e)) => {
+                warn!("[Core] Could not import VRChat log ({name}): {e}");
+                result.files_failed += 1;
+            }
+            Err(e) => {
+                warn!("[Core] Could not import VRChat log ({name}): {e}");
+                result.files_failed += 1;
+            }
+        }
+        send_event(
+            "VRC_LOG_IMPORT_PROGRESS",
+            VRCLogImportProgressEvent {
+                files_processed: result.files_processed + result.files_failed,
+                files_total,
+                events_imported: result.events_imported,
+            },
+        )
+        .await;
+    }
+    info!("[Core] Finished importing VRChat logs: {result:?}");
+    Ok(result)
+}
+
+fn is_log_file_name(name: &str) -> bool {
+    name.starts_with("output_log_") && name.ends_with(".txt")
+}
+
+fn list_sources(path: &Path) -> Result<Vec<LogSource>, String> {
+    if path.is_dir() {
+        let mut files = locator::get_log_files(path);
+        files.sort_by_key(|entry| (locator::get_file_time(entry), entry.file_name()));
+        return Ok(files
+            .into_iter()
+            .map(|entry| LogSource::File(entry.path()))
+            .collect());
+    }
+    if !path.is_file() {
+        return Err(String::from("NOT_FOUND"));
+    }
+    let is_zip = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("zip"));
+    if !is_zip {
+        return Ok(vec![LogSource::File(path.to_path_buf())]);
+    }
+    let file = File::open(path).map_err(|_| String::from("READ_ERROR"))?;
+    let mut archive = zip::ZipArchive::new(file).map_err(|_| String::from("INVALID_ARCHIVE"))?;
+    let mut entries = Vec::new();
+    for index in 0..archive.len() {
+        let entry = match archive.by_index(index) {
+            Ok(entry) => entry,
+            Err(_) => continue,
+        };
+        let name = entry.name().to_string();
+        let file_name = name.rsplit(['/', '\\']).next().unwrap_or_default();
+        if entry.is_file() && is_log_file_name(file_name) {
+            entries.push((file_name.to_string(), index, name));
+        }
+    }
+    // Log file names contain the time they were created at, so sorting them by name puts them in chronological order
+    entries.sort();
+    Ok(entries
+        .into_iter()
+        .map(|(_, index, name)| LogSource::ZipEntry(path.to_path_buf(), index, name))
+        .collect())
+}
+
+fn parse_source(source: &LogSource) -> Result<(usize, Vec<HistoryEntry>), String> {
+    let mut bytes = Vec::new();
+    match source {
+        LogSource::File(path) => {
+            bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+        }
+        LogSource::ZipEntry(path, index, _) => {
+            let file = File::open(path).map_err(|e| e.to_string())?;
+            let mut archive = zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
+            let mut entry = archive.by_index(*index).map_err(|e| e.to_string())?;
+            entry.read_to_end(&mut bytes).map_err(|e| e.to_string())?;
+        }
+    };
+    let log_file = match source {
+        LogSource::File(path) => log_file_name(&path.to_string_lossy()),
+        LogSource::ZipEntry(_, _, name) => log_file_name(name),
+    };
+    let mut lines = 0;
+    let mut offset = 0;
+    let mut entries = Vec::new();
+    // Lines are split on the raw bytes, so their offsets match those of the log tailer
+    for line in bytes.split_inclusive(|byte| *byte == b'\n') {
+        lines += 1;
+        if let Some((time, data)) = matchers::parse_log_line(&tailer::decode_line(line)) {
+            entries.push(HistoryEntry {
+                event: VRCLogEvent {
+                    time,
+                    data,
+                    initial_load: true,
+                },
+                log_file: Some(log_file.clone()),
+                offset: Some(offset),
+            });
+        }
+        offset += line.len() as u64;
+    }
+    Ok((lines, entries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CRASHED_SESSION: &str = "\
+2024.06.14 21:00:00 Log        -  [Behaviour] Joining wrld_6caf5200-70e1-46c2-b043-e3c4abe69e0f:1~region(eu)
+2024.06.14 21:00:05 Log        -  [Behaviour] Entering Room: Pug World
+2024.06.14 21:00:10 Log        -  [Behaviour] OnPlayerJoined Sleepy Fox (usr_0b83d9be-9852-42dd-98e2-625062400acc)
+2024.06.14 21:30:00 Log        -  [Behaviour] Switching Sleepy Fox to avatar Sleeping Bag
+";
+    const NEXT_SESSION: &str = "\
+2024.06.15 20:00:00 Log        -  [Behaviour] Joining wrld_1e8ba3a1-1a5b-4c3d-9d1e-2a4f7b9c0d12:7~region(eu)
+2024.06.15 20:00:05 Log        -  [Behaviour] Entering Room: Sleep World
+2024.06.15 20:10:00 Log        -  VRCApplication: OnApplicationQuit at 600.0
+";
+
+    fn time_of(line: &str) -> u64 {
+        matchers::parse_log_line(line).unwrap().0
+    }
+
+    #[tokio::test]
+    async fn closes_visits_of_log_files_that_end_without_leaving() {
+        let dir = std::env::temp_dir().join(format!("oyasumivr_backfill_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("output_log_2024-06-14_20-59-58.txt"),
+            CRASHED_SESSION,
+        )
+        .unwrap();
+        std::fs::write(dir.join("output_log_2024-06-15_19-59-58.txt"), NEXT_SESSION).unwrap();
+        history::use_history_file(dir.join("history.jsonl")).await;
+
+        let result = import_logs(Some(dir.clone())).await.unwrap();
+        assert_eq!(result.files_processed, 2);
+        assert_eq!(result.events_imported, 7);
+
+        let crashed_at = time_of(CRASHED_SESSION.lines().last().unwrap());
+        let visits = history::get_visits(0, u64::MAX).await;
+        assert_eq!(visits.len(), 2);
+        assert_eq!(visits[0].world_name.as_deref(), Some("Pug World"));
+        assert_eq!(visits[0].left_at, Some(crashed_at));
+        assert_eq!(visits[0].players[0].left_at, Some(crashed_at));
+        assert_eq!(visits[1].world_name.as_deref(), Some("Sleep World"));
+        assert_eq!(
+            visits[1].left_at,
+            Some(time_of(NEXT_SESSION.lines().last().unwrap()))
+        );
+        assert_eq!(
+            history::get_world_duration(String::from("Pug World"), 0, u64::MAX).await,
+            30 * 60 * 1000
+        );
+
+        // Importing the same logs again doesn't add anything
+        let result = import_logs(Some(dir.clone())).await.unwrap();
+        assert_eq!(result.events_imported, 0);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -1,14 +1,18 @@
 use log::info;
-use std::sync::LazyLock;
+use std::{path::PathBuf, sync::LazyLock};
 use tokio::sync::Mutex;
 use tokio_util::sync::CancellationToken;
 
+use super::models::{VRCInstanceVisit, VRCLogImportResult, VRCPlayerPresence};
+
 static CANCELLATION_TOKEN: LazyLock<Mutex<Option<CancellationToken>>> =
     LazyLock::new(Default::default);
 
+/// Starts watching the VRChat logs. Unless disabled, reading resumes where the previous session left off, after
+/// replaying the events the front needs to restore the current world and player list.
 #[tauri::command]
 #[oyasumivr_macros::command_profiling]
-pub async fn init_vrc_log_watcher() {
+pub async fn init_vrc_log_watcher(resume_from_last_position: Option<bool>) {
     info!("[Core] Initializing VRChat Log Watcher...");
     // Terminate existing task if it exists
     let cancellation_token_guard = CANCELLATION_TOKEN.lock().await;
@@ -18,6 +22,51 @@ pub async fn init_vrc_log_watcher() {
     }
     drop(cancellation_token_guard);
     // Start new locator task
-    let cancellation_token = super::start_log_locator_task();
+    let cancellation_token =
+        super::start_log_locator_task(resume_from_last_position.unwrap_or(true));
     *CANCELLATION_TOKEN.lock().await = Some(cancellation_token);
 }
+
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn set_vrc_log_directory_override(path: Option<String>) {
+    super::locator::set_log_dir_override(path.map(PathBuf::from)).await;
+}
+
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn get_vrc_log_directory() -> Option<String> {
+    super::locator::resolve_log_dir()
+        .await
+        .and_then(|path| path.to_str().map(String::from))
+}
+
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn vrc_history_get_instance_visits(from: u64, to: u64) -> Vec<VRCInstanceVisit> {
+    super::history::get_visits(from, to).await
+}
+
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn vrc_history_get_players_at(time: u64) -> Vec<VRCPlayerPresence> {
+    super::history::get_players_at(time).await
+}
+
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn vrc_history_get_world_duration(world_name: String, from: u64, to: u64) -> u64 {
+    super::history::get_world_duration(world_name, from, to).await
+}
+
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn vrc_history_clear() {
+    super::history::clear().await
+}
+
+#[tauri::command]
+#[oyasumivr_macros::command_profiling]
+pub async fn vrc_log_import(path: Option<String>) -> Result<VRCLogImportResult, String> {
+    super::backfill::import_logs(path.map(PathBuf::from)).await
+}
@@ -0,0 +1,486 @@
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet},
+    hash::{Hash, Hasher},
+    path::PathBuf,
+    sync::LazyLock,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::{
+    io::AsyncWriteExt,
+    sync::{Mutex, MutexGuard},
+};
+
+use crate::utils::get_data_file_path;
+
+use super::models::{VRCInstanceVisit, VRCLogEvent, VRCLogEventData, VRCPlayerPresence};
+
+const HISTORY_FILE_NAME: &str = "vrc_session_history.jsonl";
+const HISTORY_RETENTION: Duration = Duration::from_secs(60 * 60 * 24 * 90); // 90 days
+
+static HISTORY: LazyLock<Mutex<Option<SessionHistory>>> = LazyLock::new(Default::default);
+
+/// A VRChat log event as stored in the session history
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    #[serde(flatten)]
+    pub event: VRCLogEvent,
+    /// The name of the log file the event was read from. Missing for entries recorded by older versions.
+    #[serde(default)]
+    pub log_file: Option<String>,
+    /// The byte offset of the event's line within its log file
+    #[serde(default)]
+    pub offset: Option<u64>,
+}
+
+/// Keeps every relevant VRChat log event in an append-only file, so sessions can be reconstructed later on.
+struct SessionHistory {
+    path: PathBuf,
+    /// Sorted by time, in the order they were logged
+    entries: Vec<HistoryEntry>,
+    entry_keys: HashSet<u64>,
+    /// Keys of the entries that don't know which line they were read from, by their time and data instead
+    legacy_entry_keys: HashSet<u64>,
+}
+
+impl SessionHistory {
+    async fn load(path: PathBuf) -> Self {
+        let mut history = Self {
+            path,
+            entries: Vec::new(),
+            entry_keys: HashSet::new(),
+            legacy_entry_keys: HashSet::new(),
+        };
+        let contents = match tokio::fs::read_to_string(&history.path).await {
+            Ok(contents) => contents,
+            Err(_) => return history,
+        };
+        let oldest_time = get_time_ms().saturating_sub(HISTORY_RETENTION.as_millis() as u64);
+        let mut expired_events = 0;
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            match serde_json::from_str::<HistoryEntry>(line) {
+                Ok(entry) if entry.event.time < oldest_time => expired_events += 1,
+                Ok(entry) => {
+                    match entry_key(&entry) {
+                        Some(key) => history.entry_keys.insert(key),
+                        None => history.legacy_entry_keys.insert(legacy_entry_key(&entry)),
+                    };
+                    history.entries.push(entry);
+                }
+                Err(e) => warn!("[Core] Skipping unreadable VRChat session history entry: {e}"),
+            }
+        }
+        history.entries.sort_by_key(|entry| entry.event.time);
+        if expired_events > 0 {
+            info!("[Core] Removing {expired_events} expired VRChat session history entries");
+            history.rewrite().await;
+        }
+        history
+    }
+
+    /// Stores the given entries, and returns how many of them were not known yet
+    async fn record(&mut self, entries: &[HistoryEntry]) -> usize {
+        let mut lines = String::new();
+        let mut recorded = 0;
+        for entry in entries {
+            // Events get replayed every time a log file is loaded, so we only keep those we haven't seen yet
+            if entry.event.data == VRCLogEventData::InitialLoadComplete
+                || self.legacy_entry_keys.contains(&legacy_entry_key(entry))
+            {
+                continue;
+            }
+            let key = entry_key(entry).unwrap_or_else(|| legacy_entry_key(entry));
+            if !self.entry_keys.insert(key) {
+                continue;
+            }
+            // Events mostly arrive in order, so this usually appends
+            let index = self
+                .entries
+                .partition_point(|e| e.event.time <= entry.event.time);
+            self.entries.insert(index, entry.clone());
+            lines.push_str(&serde_json::to_string(entry).unwrap());
+            lines.push('\n');
+            recorded += 1;
+        }
+        if recorded == 0 {
+            return 0;
+        }
+        let mut file = match tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+        {
+            Ok(file) => file,
+            Err(e) => {
+                error!("[Core] Could not open VRChat session history file: {e}");
+                return recorded;
+            }
+        };
+        if let Err(e) = file.write_all(lines.as_bytes()).await {
+            error!("[Core] Could not write to VRChat session history file: {e}");
+        }
+        recorded
+    }
+
+    async fn rewrite(&self) {
+        let contents: String = self
+            .entries
+            .iter()
+            .map(|entry| format!("{}\n", serde_json::to_string(entry).unwrap()))
+            .collect();
+        if let Err(e) = tokio::fs::write(&self.path, contents).await {
+            error!("[Core] Could not write VRChat session history file: {e}");
+        }
+    }
+
+    /// Reconstructs all instance visits from the recorded events.
+    ///
+    /// A visit that is still open when its log file ends (e.g. because VRChat crashed) is closed at the last event
+    /// of that log file, rather than when the next session starts.
+    fn visits(&self) -> Vec<VRCInstanceVisit> {
+        let mut last_event_times: HashMap<&str, u64> = HashMap::new();
+        for entry in self.entries.iter() {
+            if let Some(log_file) = entry.log_file.as_deref() {
+                let time = last_event_times.entry(log_file).or_default();
+                *time = (*time).max(entry.event.time);
+            }
+        }
+        let mut visits: Vec<VRCInstanceVisit> = Vec::new();
+        let mut visit_log_file: Option<&str> = None;
+        for entry in self.entries.iter() {
+            let event = &entry.event;
+            if let (Some(visit), Some(previous), Some(current)) =
+                (visits.last_mut(), visit_log_file, entry.log_file.as_deref())
+            {
+                if previous != current {
+                    close_visit(visit, last_event_times[previous]);
+                }
+            }
+            match &event.data {
+                VRCLogEventData::OnLocationChange { instance_id } => {
+                    if let Some(visit) = visits.last_mut() {
+                        close_visit(visit, event.time);
+                    }
+                    visit_log_file = entry.log_file.as_deref();
+                    visits.push(VRCInstanceVisit {
+                        instance_id: instance_id.clone(),
+                        world_name: None,
+                        joined_at: event.time,
+                        left_at: None,
+                        players: Vec::new(),
+                    });
+                }
+                VRCLogEventData::OnWorldEntered { world_name } => {
+                    if let Some(visit) = visits.last_mut().filter(|v| v.left_at.is_none()) {
+                        visit.world_name = Some(world_name.clone());
+                    }
+                }
+                VRCLogEventData::OnPlayerJoined {
+                    display_name,
+                    user_id,
+                } => {
+                    if let Some(visit) = visits.last_mut().filter(|v| v.left_at.is_none()) {
+                        visit.players.push(VRCPlayerPresence {
+                            display_name: display_name.clone(),
+                            user_id: user_id.clone(),
+                            joined_at: event.time,
+                            left_at: None,
+                        });
+                    }
+                }
+                VRCLogEventData::OnPlayerLeft {
+                    display_name,
+                    user_id,
+                } => {
+                    if let Some(visit) = visits.last_mut().filter(|v| v.left_at.is_none()) {
+                        if let Some(player) = visit.players.iter_mut().rev().find(|p| {
+                            p.left_at.is_none()
+                                && match (&p.user_id, user_id) {
+                                    (Some(a), Some(b)) => a == b,
+                                    _ => p.display_name == *display_name,
+                                }
+                        }) {
+                            player.left_at = Some(event.time);
+                        }
+                    }
+                }
+                VRCLogEventData::OnApplicationQuit => {
+                    if let Some(visit) = visits.last_mut() {
+                        close_visit(visit, event.time);
+                    }
+                }
+                _ => {}
+            }
+        }
+        visits
+    }
+}
+
+fn close_visit(visit: &mut VRCInstanceVisit, time: u64) {
+    if visit.left_at.is_some() {
+        return;
+    }
+    visit.left_at = Some(time);
+    for player in visit.players.iter_mut().filter(|p| p.left_at.is_none()) {
+        player.left_at = Some(time);
+    }
+}
+
+/// Identifies an entry by the line it was read from, as timestamps are neither unique nor, being in local time,
+/// unambiguous
+fn entry_key(entry: &HistoryEntry) -> Option<u64> {
+    let mut hasher = DefaultHasher::new();
+    entry.log_file.as_ref()?.hash(&mut hasher);
+    entry.offset?.hash(&mut hasher);
+    serde_json::to_string(&entry.event.data)
+        .unwrap()
+        .hash(&mut hasher);
+    Some(hasher.finish())
+}
+
+/// Identifies an entry by its time and data, for entries recorded without the line they were read from
+fn legacy_entry_key(entry: &HistoryEntry) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    entry.event.time.hash(&mut hasher);
+    serde_json::to_string(&entry.event.data)
+        .unwrap()
+        .hash(&mut hasher);
+    hasher.finish()
+}
+
+fn get_time_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+fn overlaps(joined_at: u64, left_at: Option<u64>, from: u64, to: u64) -> bool {
+    joined_at <= to && left_at.is_none_or(|left_at| left_at >= from)
+}
+
+async fn get_history() -> MutexGuard<'static, Option<SessionHistory>> {
+    let mut history_guard = HISTORY.lock().await;
+    if history_guard.is_none() {
+        if let Some(path) = get_data_file_path(HISTORY_FILE_NAME).await {
+            *history_guard = Some(SessionHistory::load(path).await);
+        }
+    }
+    history_guard
+}
+
+/// Uses the given file for the session history, instead of the one in the data directory
+#[cfg(test)]
+pub async fn use_history_file(path: PathBuf) {
+    *HISTORY.lock().await = Some(SessionHistory::load(path).await);
+}
+
+pub async fn record_events(entries: &[HistoryEntry]) -> usize {
+    match get_history().await.as_mut() {
+        Some(history) => history.record(entries).await,
+        None => 0,
+    }
+}
+
+/// All instance visits that (partially) took place within the given time range
+pub async fn get_visits(from: u64, to: u64) -> Vec<VRCInstanceVisit> {
+    let history_guard = get_history().await;
+    let history = match history_guard.as_ref() {
+        Some(history) => history,
+        None => return vec![],
+    };
+    history
+        .visits()
+        .into_iter()
+        .filter(|visit| overlaps(visit.joined_at, visit.left_at, from, to))
+        .collect()
+}
+
+/// The players that were in the same instance as the user at the given time
+pub async fn get_players_at(time: u64) -> Vec<VRCPlayerPresence> {
+    get_visits(time, time)
+        .await
+        .into_iter()
+        .next_back()
+        .map(|visit| {
+            visit
+                .players
+                .into_iter()
+                .filter(|player| overlaps(player.joined_at, player.left_at, time, time))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The total time (in milliseconds) spent in the given world within the given time range
+pub async fn get_world_duration(world_name: String, from: u64, to: u64) -> u64 {
+    let now = get_time_ms();
+    get_visits(from, to)
+        .await
+        .into_iter()
+        .filter(|visit| visit.world_name.as_ref() == Some(&world_name))
+        .map(|visit| {
+            let start = visit.joined_at.max(from);
+            let end = visit.left_at.unwrap_or(now).min(to);
+            end.saturating_sub(start)
+        })
+        .sum()
+}
+
+pub async fn clear() {
+    if let Some(history) = get_history().await.as_mut() {
+        history.entries.clear();
+        history.entry_keys.clear();
+        history.legacy_entry_keys.clear();
+    }
+    if let Some(path) = get_data_file_path(HISTORY_FILE_NAME).await {
+        let _ = tokio::fs::remove_file(path).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_history_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "oyasumivr_history_{}_{name}.jsonl",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn entry(log_file: &str, offset: u64, time: u64, data: VRCLogEventData) -> HistoryEntry {
+        HistoryEntry {
+            event: VRCLogEvent {
+                time,
+                data,
+                initial_load: false,
+            },
+            log_file: Some(log_file.to_string()),
+            offset: Some(offset),
+        }
+    }
+
+    fn location(instance_id: &str) -> VRCLogEventData {
+        VRCLogEventData::OnLocationChange {
+            instance_id: instance_id.to_string(),
+        }
+    }
+
+    fn player_joined(display_name: &str) -> VRCLogEventData {
+        VRCLogEventData::OnPlayerJoined {
+            display_name: display_name.to_string(),
+            user_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn closes_visits_at_the_end_of_their_log_file() {
+        let path = temp_history_path("crashed");
+        let mut history = SessionHistory::load(path.clone()).await;
+        history
+            .record(&[
+                entry("output_log_1.txt", 0, 1000, location("wrld_1:1")),
+                entry("output_log_1.txt", 100, 2000, player_joined("Sleepy Fox")),
+                entry(
+                    "output_log_1.txt",
+                    200,
+                    5000,
+                    VRCLogEventData::OnPortalDropped,
+                ),
+                // VRChat crashed, and was only started again much later
+                entry("output_log_2.txt", 0, 100000, location("wrld_2:1")),
+            ])
+            .await;
+
+        let visits = history.visits();
+        assert_eq!(visits.len(), 2);
+        assert_eq!(visits[0].left_at, Some(5000));
+        assert_eq!(visits[0].players[0].left_at, Some(5000));
+        assert_eq!(visits[1].joined_at, 100000);
+        assert_eq!(visits[1].left_at, None);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn keeps_identical_events_from_different_lines() {
+        let path = temp_history_path("identical");
+        let mut history = SessionHistory::load(path.clone()).await;
+        // After a DST fall-back, the same local time occurs twice
+        let now = get_time_ms();
+        let events = [
+            entry("output_log_1.txt", 0, now, location("wrld_1:1")),
+            entry(
+                "output_log_1.txt",
+                100,
+                now + 1000,
+                player_joined("Sleepy Fox"),
+            ),
+            entry(
+                "output_log_1.txt",
+                500,
+                now + 1000,
+                player_joined("Sleepy Fox"),
+            ),
+        ];
+        assert_eq!(history.record(&events).await, 3);
+        // Reading the same lines again doesn't record them twice, also after a restart
+        assert_eq!(history.record(&events).await, 0);
+        let mut history = SessionHistory::load(path.clone()).await;
+        assert_eq!(history.record(&events).await, 0);
+        assert_eq!(history.entries.len(), 3);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn recognizes_entries_recorded_without_their_line() {
+        let path = temp_history_path("legacy");
+        let now = get_time_ms();
+        let legacy_entry = HistoryEntry {
+            log_file: None,
+            offset: None,
+            ..entry("", 0, now, location("wrld_1:1"))
+        };
+        std::fs::write(
+            &path,
+            format!("{}\n", serde_json::to_string(&legacy_entry.event).unwrap()),
+        )
+        .unwrap();
+        let mut history = SessionHistory::load(path.clone()).await;
+        assert_eq!(history.entries.len(), 1);
+        assert_eq!(
+            history
+                .record(&[entry("output_log_1.txt", 0, now, location("wrld_1:1"))])
+                .await,
+            0
+        );
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn keeps_entries_sorted_by_time() {
+        let path = temp_history_path("sorted");
+        let mut history = SessionHistory::load(path.clone()).await;
+        history
+            .record(&[
+                entry("output_log_2.txt", 0, 3000, location("wrld_2:1")),
+                entry("output_log_2.txt", 100, 4000, player_joined("Sleepy Fox")),
+            ])
+            .await;
+        // An older log file imported afterwards
+        history
+            .record(&[
+                entry("output_log_1.txt", 0, 1000, location("wrld_1:1")),
+                entry("output_log_1.txt", 100, 2000, player_joined("Sleepy Fox")),
+            ])
+            .await;
+        let times: Vec<u64> = history.entries.iter().map(|e| e.event.time).collect();
+        assert_eq!(times, [1000, 2000, 3000, 4000]);
+        let _ = std::fs::remove_file(path);
+    }
+}
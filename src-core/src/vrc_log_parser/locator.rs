@@ -0,0 +1,134 @@
+use log::{debug, warn};
+use std::{
+    fs::{read_dir, DirEntry},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        LazyLock,
+    },
+    time::{Duration, SystemTime},
+};
+use steamlocate::SteamDir;
+use tokio::sync::Mutex;
+
+const VRCHAT_STEAM_APP_ID: u32 = 438100;
+const VRCHAT_LOG_DIR: [&str; 4] = ["AppData", "LocalLow", "VRChat", "VRChat"];
+const PROTON_USER_DIR: [&str; 4] = ["pfx", "drive_c", "users", "steamuser"];
+
+static LOG_DIR_OVERRIDE: LazyLock<Mutex<Option<PathBuf>>> = LazyLock::new(Default::default);
+static MUTE_LOG_DIR_NO_EXIST_WARNINGS: AtomicBool = AtomicBool::new(false);
+
+pub async fn set_log_dir_override(path: Option<PathBuf>) {
+    *LOG_DIR_OVERRIDE.lock().await = path;
+    MUTE_LOG_DIR_NO_EXIST_WARNINGS.store(false, Ordering::Relaxed);
+}
+
+/// Determines the directory VRChat writes its logs to.
+///
+/// A user configured override always takes precedence. Otherwise, the regular Windows location is tried first,
+/// followed by the Wine prefix Steam Proton creates for VRChat in each of the Steam libraries.
+pub async fn resolve_log_dir() -> Option<PathBuf> {
+    if let Some(path) = LOG_DIR_OVERRIDE.lock().await.clone() {
+        return Some(path).filter(|path| path.is_dir());
+    }
+    if let Some(path) = dirs::home_dir()
+        .map(|home_dir| join_all(home_dir, &VRCHAT_LOG_DIR))
+        .filter(|path| path.is_dir())
+    {
+        return Some(path);
+    }
+    get_proton_log_dirs().into_iter().find(|path| path.is_dir())
+}
+
+fn get_proton_log_dirs() -> Vec<PathBuf> {
+    let steam_dir = match SteamDir::locate() {
+        Ok(dir) => dir,
+        Err(e) => {
+            debug!("[Core] Could not locate Steam for finding a Proton prefix for VRChat: {e}");
+            return vec![];
+        }
+    };
+    let libraries = match steam_dir.libraries() {
+        Ok(libraries) => libraries,
+        Err(e) => {
+            debug!(
+                "[Core] Could not read Steam libraries for finding a Proton prefix for VRChat: {e}"
+            );
+            return vec![];
+        }
+    };
+    libraries
+        .filter_map(|library| library.ok())
+        .map(|library| {
+            let prefix = library
+                .path()
+                .join("steamapps")
+                .join("compatdata")
+                .join(VRCHAT_STEAM_APP_ID.to_string());
+            join_all(join_all(prefix, &PROTON_USER_DIR), &VRCHAT_LOG_DIR)
+        })
+        .collect()
+}
+
+fn join_all(path: PathBuf, segments: &[&str]) -> PathBuf {
+    segments
+        .iter()
+        .fold(path, |path, segment| path.join(segment))
+}
+
+/// Returns all VRChat log files in the given directory
+pub fn get_log_files(dir: &Path) -> Vec<DirEntry> {
+    let entries = match read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            name.starts_with("output_log_") && name.ends_with(".txt")
+        })
+        .collect()
+}
+
+/// The creation time of a file, or its modification time on platforms that do not record creation times.
+pub fn get_file_time(entry: &DirEntry) -> Option<SystemTime> {
+    let metadata = entry.metadata().ok()?;
+    metadata.created().or_else(|_| metadata.modified()).ok()
+}
+
+pub async fn get_latest_log_path() -> Option<String> {
+    // If log directory doesn't exist, return no path
+    let dir = match resolve_log_dir().await {
+        Some(dir) => dir,
+        None => {
+            if !MUTE_LOG_DIR_NO_EXIST_WARNINGS.load(Ordering::Relaxed) {
+                warn!("[Core] VRChat log directory doesn't exist (yet)");
+                MUTE_LOG_DIR_NO_EXIST_WARNINGS.store(true, Ordering::Relaxed);
+            }
+            return None;
+        }
+    };
+    MUTE_LOG_DIR_NO_EXIST_WARNINGS.store(false, Ordering::Relaxed);
+    // Get the latest log file
+    get_log_files(&dir)
+        .into_iter()
+        // Only get files that are created in the last 24 hours
+        .filter(|entry| {
+            get_file_time(entry)
+                .and_then(|time| SystemTime::now().duration_since(time).ok())
+                .map(|duration| duration <= Duration::from_secs(24 * 60 * 60))
+                .unwrap_or(false)
+        })
+        // Ignore files that are 0 bytes
+        .filter(|entry| {
+            entry
+                .metadata()
+                .map(|metadata| metadata.len() > 0)
+                .unwrap_or(false)
+        })
+        // Find most recent log file
+        .max_by_key(get_file_time)
+        // Get the path for it
+        .and_then(|entry| entry.path().to_str().map(String::from))
+}
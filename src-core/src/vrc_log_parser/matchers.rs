@@ -0,0 +1,428 @@
+use std::sync::{LazyLock, RwLock};
+
+use chrono::{Local, LocalResult, NaiveDateTime, TimeZone};
+use log::trace;
+use regex::{Captures, Regex};
+
+use super::models::VRCLogEventData;
+
+/// Matches the header VRChat prefixes every log entry with, e.g.
+/// `2024.03.31 02:59:59 Log        -  [Behaviour] ...`
+static LOG_LINE_HEADER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^(?P<time>\d{4}\.\d{2}\.\d{2} \d{2}:\d{2}:\d{2}) [A-Za-z]+\s+-\s+(?P<message>.*?)\s*$",
+    )
+    .unwrap()
+});
+
+/// The parser used for all VRChat logs, in the local time zone
+static LOG_LINE_PARSER: LazyLock<RwLock<LogLineParser<Local>>> =
+    LazyLock::new(|| RwLock::new(LogLineParser::new(Local)));
+
+pub struct LogLineMatcher {
+    /// Name of the event kind this matcher produces, used for logging
+    pub kind: &'static str,
+    /// Pattern that is matched against the message part of a log line (without the header)
+    pub pattern: Regex,
+    /// Builds the event from the captures of the pattern
+    pub extract: fn(&Captures) -> Option<VRCLogEventData>,
+}
+
+impl LogLineMatcher {
+    pub fn new(
+        kind: &'static str,
+        pattern: &str,
+        extract: fn(&Captures) -> Option<VRCLogEventData>,
+    ) -> Self {
+        Self {
+            kind,
+            pattern: Regex::new(pattern).unwrap(),
+            extract,
+        }
+    }
+}
+
+/// Turns log lines into events using a registry of matchers, reading their timestamps in the given time zone
+pub struct LogLineParser<Tz: TimeZone> {
+    matchers: Vec<LogLineMatcher>,
+    time_zone: Tz,
+}
+
+impl<Tz: TimeZone> LogLineParser<Tz> {
+    /// Creates a parser with the matchers for all events we know of
+    pub fn new(time_zone: Tz) -> Self {
+        let mut parser = Self {
+            matchers: Vec::new(),
+            time_zone,
+        };
+        for matcher in default_matchers() {
+            parser.register_matcher(matcher);
+        }
+        parser
+    }
+
+    /// Registers an additional matcher. Matchers are tried in order of registration, so custom matchers only apply
+    /// to lines that none of the default matchers recognise.
+    pub fn register_matcher(&mut self, matcher: LogLineMatcher) {
+        self.matchers.push(matcher);
+    }
+
+    /// Parses a single log line into its timestamp (in milliseconds) and event, if recognised.
+    pub fn parse(&self, line: &str) -> Option<(u64, VRCLogEventData)> {
+        let header = LOG_LINE_HEADER.captures(line)?;
+        let message = header.name("message")?.as_str();
+        let data = self.matchers.iter().find_map(|matcher| {
+            let data = matcher
+                .pattern
+                .captures(message)
+                .and_then(|captures| (matcher.extract)(&captures))?;
+            trace!("[Core] VRC log line matched {}: {message}", matcher.kind);
+            Some(data)
+        })?;
+        let time = self.parse_datetime(header.name("time")?.as_str())?;
+        Some((time, data))
+    }
+
+    fn parse_datetime(&self, time: &str) -> Option<u64> {
+        let localtime = NaiveDateTime::parse_from_str(time, "%Y.%m.%d %H:%M:%S").ok()?;
+        // In the case of a DST rollback, we pick the latest possible time
+        // During this hour, the logs will still be parsed in order, but their timestamps will be out of order.
+        // (Compared explicitly, as the order of ambiguous local times differs between platforms.)
+        let latest = |time: &NaiveDateTime| match self.time_zone.from_local_datetime(time) {
+            LocalResult::Single(time) => Some(time.timestamp_millis()),
+            LocalResult::Ambiguous(a, b) => Some(a.timestamp_millis().max(b.timestamp_millis())),
+            LocalResult::None => None,
+        };
+        let time = latest(&localtime).or_else(|| {
+            // A time skipped by a DST rollforward can only show up if the clock was changed while
+            // VRChat was running. Shift it past the gap rather than dropping the line.
+            latest(&(localtime + chrono::Duration::hours(1)))
+        });
+        time.map(|v| v as u64)
+    }
+}
+
+/// Parses a single log line with the parser used for all VRChat logs.
+pub fn parse_log_line(line: &str) -> Option<(u64, VRCLogEventData)> {
+    LOG_LINE_PARSER.read().unwrap().parse(line)
+}
+
+fn capture(captures: &Captures, name: &str) -> Option<String> {
+    captures
+        .name(name)
+        .map(|m| m.as_str().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// The display name of a player. A line cut off within the user id that follows the name is rejected, rather than
+/// taking the partial id for part of the name.
+fn display_name(captures: &Captures) -> Option<String> {
+    capture(captures, "name").filter(|name| !name.contains(" (usr_"))
+}
+
+fn default_matchers() -> Vec<LogLineMatcher> {
+    vec![
+        LogLineMatcher::new(
+            "OnPlayerJoined",
+            r"^\[Behaviour\] OnPlayerJoined (?P<name>.+?)(?: \((?P<id>[^()\s]+)\))?$",
+            |c| {
+                Some(VRCLogEventData::OnPlayerJoined {
+                    display_name: display_name(c)?,
+                    user_id: capture(c, "id"),
+                })
+            },
+        ),
+        LogLineMatcher::new(
+            "OnPlayerLeft",
+            r"^\[Behaviour\] OnPlayerLeft (?P<name>.+?)(?: \((?P<id>[^()\s]+)\))?$",
+            |c| {
+                Some(VRCLogEventData::OnPlayerLeft {
+                    display_name: display_name(c)?,
+                    user_id: capture(c, "id"),
+                })
+            },
+        ),
+        LogLineMatcher::new(
+            "OnLocationChange",
+            r"^\[Behaviour\] Joining (?P<instance>\S+)$",
+            |c| {
+                Some(VRCLogEventData::OnLocationChange {
+                    instance_id: capture(c, "instance")?,
+                })
+            },
+        ),
+        LogLineMatcher::new(
+            "OnWorldEntered",
+            r"^\[Behaviour\] Entering Room: (?P<world>.+)$",
+            |c| {
+                Some(VRCLogEventData::OnWorldEntered {
+                    world_name: capture(c, "world")?,
+                })
+            },
+        ),
+        LogLineMatcher::new(
+            "OnAvatarChanged",
+            r"^\[Behaviour\] Switching (?P<name>.+) to avatar (?P<avatar>.+)$",
+            |c| {
+                Some(VRCLogEventData::OnAvatarChanged {
+                    display_name: capture(c, "name")?,
+                    avatar_name: capture(c, "avatar")?,
+                })
+            },
+        ),
+        LogLineMatcher::new(
+            "OnVideoPlayerUrl",
+            r"^\[Video Playback\] Attempting to resolve URL '(?P<url>.+)'$",
+            |c| {
+                Some(VRCLogEventData::OnVideoPlayerUrl {
+                    url: capture(c, "url")?,
+                })
+            },
+        ),
+        LogLineMatcher::new(
+            "OnPortalDropped",
+            r"^\[Behaviour\] Instantiated a \(Clone \[\d+\] Portals/PortalInternalDynamic\)$",
+            |_| Some(VRCLogEventData::OnPortalDropped),
+        ),
+        LogLineMatcher::new(
+            "OnVoteKickInitiated",
+            r"^\[ModerationManager\] A vote kick has been initiated against (?P<name>.+?)(?:, do you agree\?)?$",
+            |c| {
+                Some(VRCLogEventData::OnVoteKickInitiated {
+                    display_name: capture(c, "name")?,
+                })
+            },
+        ),
+        LogLineMatcher::new(
+            "OnModerationNotice",
+            r"^\[ModerationManager\] (?P<message>.+)$",
+            |c| {
+                Some(VRCLogEventData::OnModerationNotice {
+                    message: capture(c, "message")?,
+                })
+            },
+        ),
+        LogLineMatcher::new(
+            "OnUserAuthenticated",
+            r"^\[Behaviour\] User Authenticated: (?P<name>.+?)(?: \((?P<id>[^()\s]+)\))?$",
+            |c| {
+                Some(VRCLogEventData::OnUserAuthenticated {
+                    display_name: display_name(c)?,
+                    user_id: capture(c, "id"),
+                })
+            },
+        ),
+        LogLineMatcher::new(
+            "OnApplicationQuit",
+            r"^VRCApplication: OnApplicationQuit\b",
+            |_| Some(VRCLogEventData::OnApplicationQuit),
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use chrono::{
+        Datelike, Days, FixedOffset, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, TimeZone,
+    };
+    use regex::Captures;
+    use serde_json::Value;
+
+    use super::{LogLineMatcher, LogLineParser};
+    use crate::vrc_log_parser::models::VRCLogEventData;
+
+    /// Central European Time, with daylight saving time from the last Sunday of March to the last Sunday of October
+    /// (switching at 01:00 UTC). Used instead of the system time zone, so the DST fixture always crosses a rollover.
+    #[derive(Clone, Copy)]
+    struct CentralEuropeanTime;
+
+    impl CentralEuropeanTime {
+        const STANDARD_TIME: i32 = 3600;
+        const SUMMER_TIME: i32 = 7200;
+
+        /// When DST starts or ends in the given month, in UTC
+        fn rollover(year: i32, month: u32) -> NaiveDateTime {
+            let last_day = NaiveDate::from_ymd_opt(year, month + 1, 1).unwrap() - Days::new(1);
+            let last_sunday =
+                last_day - Days::new(last_day.weekday().num_days_from_sunday() as u64);
+            last_sunday.and_time(NaiveTime::from_hms_opt(1, 0, 0).unwrap())
+        }
+    }
+
+    impl TimeZone for CentralEuropeanTime {
+        type Offset = FixedOffset;
+
+        fn from_offset(_: &FixedOffset) -> Self {
+            Self
+        }
+
+        fn offset_from_local_date(&self, local: &NaiveDate) -> LocalResult<FixedOffset> {
+            self.offset_from_local_datetime(&local.and_time(NaiveTime::MIN))
+        }
+
+        fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<FixedOffset> {
+            // An offset applies if converting back with it ends up at the same offset
+            let offsets: Vec<FixedOffset> = [Self::SUMMER_TIME, Self::STANDARD_TIME]
+                .into_iter()
+                .map(|seconds| FixedOffset::east_opt(seconds).unwrap())
+                .filter(|offset| {
+                    let utc = *local - chrono::Duration::seconds(offset.local_minus_utc() as i64);
+                    self.offset_from_utc_datetime(&utc) == *offset
+                })
+                .collect();
+            match offsets[..] {
+                [offset] => LocalResult::Single(offset),
+                [earliest, latest] => LocalResult::Ambiguous(earliest, latest),
+                _ => LocalResult::None,
+            }
+        }
+
+        fn offset_from_utc_date(&self, utc: &NaiveDate) -> FixedOffset {
+            self.offset_from_utc_datetime(&utc.and_time(NaiveTime::MIN))
+        }
+
+        fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> FixedOffset {
+            let summer_time =
+                *utc >= Self::rollover(utc.year(), 3) && *utc < Self::rollover(utc.year(), 10);
+            FixedOffset::east_opt(if summer_time {
+                Self::SUMMER_TIME
+            } else {
+                Self::STANDARD_TIME
+            })
+            .unwrap()
+        }
+    }
+
+    fn parser() -> LogLineParser<CentralEuropeanTime> {
+        LogLineParser::new(CentralEuropeanTime)
+    }
+
+    fn fixture_path(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("src/vrc_log_parser/fixtures")
+            .join(name)
+    }
+
+    /// The local time a timestamp falls on, formatted like the time in a log line
+    fn local_time(time: u64) -> String {
+        CentralEuropeanTime
+            .timestamp_millis_opt(time as i64)
+            .unwrap()
+            .format("%Y.%m.%d %H:%M:%S")
+            .to_string()
+    }
+
+    /// The local time a log line with the given time should be parsed to.
+    /// Times skipped by DST are shifted past the gap.
+    fn expected_local_time(time: &str) -> String {
+        let time = NaiveDateTime::parse_from_str(time, "%Y.%m.%d %H:%M:%S").unwrap();
+        let time = match CentralEuropeanTime.from_local_datetime(&time).latest() {
+            Some(_) => time,
+            None => time + chrono::Duration::hours(1),
+        };
+        time.format("%Y.%m.%d %H:%M:%S").to_string()
+    }
+
+    fn assert_fixture(name: &str) {
+        let parser = parser();
+        // Lines are split on \n only, so CRLF line endings reach the parser as they would from the tailer
+        let log = fs::read_to_string(fixture_path(&format!("{name}.txt"))).unwrap();
+        let parsed: Vec<(String, VRCLogEventData)> = log
+            .split('\n')
+            .filter_map(|line| parser.parse(line))
+            .map(|(time, data)| (local_time(time), data))
+            .collect();
+        let expected_output =
+            fs::read_to_string(fixture_path(&format!("{name}.expected.jsonl"))).unwrap();
+        let expected: Vec<(String, VRCLogEventData)> = expected_output
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                let mut entry: Value = serde_json::from_str(line).unwrap();
+                let time = entry["localTime"].as_str().unwrap().to_string();
+                entry.as_object_mut().unwrap().remove("localTime");
+                (
+                    expected_local_time(&time),
+                    serde_json::from_value(entry).unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(parsed, expected, "Output for fixture {name} does not match");
+    }
+
+    #[test]
+    fn matches_session_fixture() {
+        assert_fixture("session");
+    }
+
+    #[test]
+    fn matches_dst_rollover_fixture() {
+        assert_fixture("dst_rollover");
+    }
+
+    #[test]
+    fn matches_truncated_lines_fixture() {
+        assert_fixture("truncated_lines");
+    }
+
+    #[test]
+    fn resolves_times_around_dst_rollovers() {
+        let parser = parser();
+        let time = |line: &str| parser.parse(line).unwrap().0;
+        // 02:00 to 03:00 happens twice when DST ends, the latest one is picked
+        let last_before_rollback =
+            time("2024.10.27 02:59:59 Log        -  [Behaviour] Entering Room: Sleep World");
+        // 01:59:59 UTC
+        assert_eq!(last_before_rollback, 1729994399000);
+        let first_after_rollback =
+            time("2024.10.27 02:00:01 Log        -  [Behaviour] Entering Room: Sleep World");
+        // 01:00:01 UTC
+        assert_eq!(first_after_rollback, 1729990801000);
+        // 02:00 to 03:00 doesn't exist when DST starts, it is shifted forward an hour
+        let skipped =
+            time("2024.03.31 02:30:00 Log        -  [Behaviour] Entering Room: Sleep World");
+        // 01:30:00 UTC
+        assert_eq!(skipped, 1711848600000);
+    }
+
+    #[test]
+    fn tries_custom_matchers_after_default_matchers() {
+        fn extract(captures: &Captures) -> Option<VRCLogEventData> {
+            Some(VRCLogEventData::OnModerationNotice {
+                message: captures.name("message")?.as_str().to_string(),
+            })
+        }
+        let mut parser = parser();
+        parser.register_matcher(LogLineMatcher::new(
+            "CustomNotice",
+            r"^\[CustomNotice\] (?P<message>.+)$",
+            extract,
+        ));
+        parser.register_matcher(LogLineMatcher::new(
+            "CustomEnteringRoom",
+            r"^\[Behaviour\] Entering Room: (?P<message>.+)$",
+            extract,
+        ));
+        let (_, data) = parser
+            .parse("2024.06.14 21:02:41 Log        -  [CustomNotice] Lights out")
+            .unwrap();
+        assert_eq!(
+            data,
+            VRCLogEventData::OnModerationNotice {
+                message: String::from("Lights out")
+            }
+        );
+        // The default matcher still handles lines it recognises
+        let (_, data) = parser
+            .parse("2024.06.14 21:02:41 Log        -  [Behaviour] Entering Room: Pug World")
+            .unwrap();
+        assert_eq!(
+            data,
+            VRCLogEventData::OnWorldEntered {
+                world_name: String::from("Pug World")
+            }
+        );
+    }
+}
@@ -1,207 +1,110 @@
+mod backfill;
 pub mod commands;
+mod history;
+mod locator;
+mod matchers;
+pub mod models;
+mod tailer;
 
 use crate::utils::send_event;
-use chrono::{Local, NaiveDateTime, TimeZone};
 use log::{debug, info, trace, warn};
-use serde::{Deserialize, Serialize};
-use std::sync::atomic::{AtomicBool, Ordering};
+use models::{VRCLogEvent, VRCLogEventData};
 use std::{
-    fs::{read_dir, File},
-    io::{BufRead, BufReader},
-    os::windows::prelude::MetadataExt,
     sync::Arc,
-    time::{Duration, SystemTime, UNIX_EPOCH},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 use tokio_util::sync::CancellationToken;
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
-#[serde(rename_all = "camelCase")]
-struct VRCLogEvent {
-    time: u64,
-    event: String,
-    data: String,
-    initial_load: bool,
-}
-
-static MUTE_LOG_DIR_NO_EXIST_WARNINGS: AtomicBool = AtomicBool::new(false);
+const MAX_LINES_PER_READ: usize = 1000;
+const POSITION_SAVE_INTERVAL: Duration = Duration::from_secs(10);
 
-fn get_latest_log_path() -> Option<String> {
-    // Get all files in the log directory
-    let home_dir = dirs::home_dir()?;
-    let dir = read_dir(home_dir.join("AppData\\LocalLow\\VRChat\\VRChat"));
-    // If log directory doesn't exist, return no path
-    if dir.is_err() {
-        if !MUTE_LOG_DIR_NO_EXIST_WARNINGS.load(Ordering::Relaxed) {
-            warn!("[Core] VRChat log directory doesn't exist (yet)");
-            MUTE_LOG_DIR_NO_EXIST_WARNINGS.store(true, Ordering::Relaxed);
-        }
-        return None;
+async fn process_log_line(log_file: &str, line: tailer::LogLine, initial_load: bool) {
+    let (time, data) = match matchers::parse_log_line(&line.text) {
+        Some(v) => v,
+        None => return,
+    };
+    let event = VRCLogEvent {
+        time,
+        data,
+        initial_load,
+    };
+    send_event("VRC_LOG_EVENT", event.clone()).await;
+    history::record_events(&[history::HistoryEntry {
+        event: event.clone(),
+        log_file: Some(log_file.to_string()),
+        offset: Some(line.offset),
+    }])
+    .await;
+    if initial_load {
+        trace!("[Core] VRC Log Event: {event:?}");
+    } else {
+        debug!("[Core] VRC Log Event: {event:?}");
     }
-    MUTE_LOG_DIR_NO_EXIST_WARNINGS.store(false, Ordering::Relaxed);
-    // Get the latest log file
-    dir.ok()?
-        .filter_map(|entry| entry.ok())
-        // Only get log files
-        .filter(|entry| {
-            let name = entry.file_name().to_string_lossy().to_string();
-            name.starts_with("output_log_") && name.ends_with(".txt")
-        })
-        // Only get files that are created in the last 24 hours
-        .filter(|entry| {
-            entry
-                .path()
-                .metadata()
-                .ok()
-                .and_then(|metadata| {
-                    metadata.created().ok().and_then(|created_time| {
-                        SystemTime::now()
-                            .duration_since(created_time)
-                            .ok()
-                            .map(|duration| duration <= Duration::from_secs(24 * 60 * 60))
-                    })
-                })
-                .unwrap_or(false)
-        })
-        // Ignore files that are 0 bytes
-        .filter(|entry| {
-            entry
-                .path()
-                .metadata()
-                .ok()
-                .map(|metadata| metadata.len() > 0)
-                .unwrap_or(false)
-        })
-        // Find most recent log file
-        .max_by_key(|entry| entry.path().metadata().ok().map(|m| m.creation_time()))
-        // Get the path for it
-        .and_then(|entry| entry.path().to_str().map(String::from))
-}
-
-fn parse_datetime_from_line(line: String) -> Option<u64> {
-    let localtime = NaiveDateTime::parse_from_str(&line[0..19], "%Y.%m.%d %H:%M:%S").unwrap();
-    // In the case of a DST rollback, we pick the latest possible time
-    // During this hour, the logs will still be parsed in order, but their timestamps will be out of order.
-    let time = Local.from_local_datetime(&localtime).latest();
-    time.map(|v| v.timestamp_millis() as u64)
 }
 
-async fn process_log_line(line: String, initial_load: bool) {
-    let _ = parse_on_player_joined(line.clone(), initial_load).await
-        || parse_on_player_left(line.clone(), initial_load).await
-        || parse_on_location_change(line.clone(), initial_load).await;
+/// The name a log file is known by in the session history, regardless of the directory it was read from
+fn log_file_name(path: &str) -> String {
+    path.rsplit(['/', '\\']).next().unwrap_or(path).to_string()
 }
 
-async fn parse_on_player_joined(line: String, initial_load: bool) -> bool {
-    if line.contains("[Behaviour] OnPlayerJoined") && !line.contains("] OnPlayerJoined:") {
-        let mut offset = match line.rfind("] OnPlayerJoined") {
-            Some(v) => v,
-            None => return true,
+/// Replays the events that describe the current state from the part of a log file that was processed before,
+/// so the front can rebuild it when resuming: the signed in user, and everything since the last location change
+/// (the current world and its players). All other events before the offset are skipped.
+async fn replay_current_session(path: &str, offset: u64) {
+    let mut authentication = None;
+    let mut events = Vec::new();
+    let result = tailer::for_each_line_before(path, offset, |line| {
+        let Some((time, data)) = matchers::parse_log_line(line) else {
+            return;
         };
-        offset += 17;
-        if offset >= line.len() {
-            return true;
-        }
-        let display_name = line[offset..].to_string();
-        let time = match parse_datetime_from_line(line) {
-            Some(v) => v,
-            None => return true,
-        };
-        let event = VRCLogEvent {
-            time,
-            event: String::from("OnPlayerJoined"),
-            data: display_name,
-            initial_load,
-        };
-        send_event("VRC_LOG_EVENT", event.clone()).await;
-        if initial_load {
-            trace!("[Core] VRC Log Event: {event:#?}");
-        } else {
-            debug!("[Core] VRC Log Event: {event:#?}");
-        }
-        return true;
-    }
-    false
-}
-
-async fn parse_on_player_left(line: String, initial_load: bool) -> bool {
-    if line.contains("[Behaviour] OnPlayerLeft")
-        && !line.contains("] OnPlayerLeft:")
-        && !line.contains("] OnPlayerLeftRoom")
-    {
-        let mut offset = match line.rfind("] OnPlayerLeft") {
-            Some(v) => v,
-            None => return true,
-        };
-        offset += 15;
-        if offset >= line.len() {
-            return true;
-        }
-        let display_name = line[offset..].to_string();
-        let time = match parse_datetime_from_line(line) {
-            Some(v) => v,
-            None => return true,
-        };
-        let event = VRCLogEvent {
-            time,
-            event: String::from("OnPlayerLeft"),
-            data: display_name,
-            initial_load,
-        };
-        send_event("VRC_LOG_EVENT", event.clone()).await;
-        if initial_load {
-            trace!("[Core] VRC Log Event: {event:#?}");
-        } else {
-            debug!("[Core] VRC Log Event: {event:#?}");
+        match data {
+            VRCLogEventData::OnUserAuthenticated { .. } => authentication = Some((time, data)),
+            VRCLogEventData::OnLocationChange { .. } => {
+                events.clear();
+                events.push((time, data));
+            }
+            _ => events.push((time, data)),
         }
-        return true;
+    });
+    if let Err(e) = result {
+        warn!("[Core] Could not replay the current VRChat session: {e} ({path})");
+        return;
     }
-    false
-}
-
-async fn parse_on_location_change(line: String, initial_load: bool) -> bool {
-    if line.contains("[Behaviour] Joining ")
-        && !line.contains("] Joining or Creating Room: ")
-        && !line.contains("] Joining friend: ")
-    {
-        let mut offset = match line.rfind("] Joining ") {
-            Some(v) => v,
-            None => return true,
-        };
-        offset += 10;
-        if offset >= line.len() {
-            return true;
-        }
-        let instance_id = line[offset..].to_string();
-        let time = match parse_datetime_from_line(line) {
-            Some(v) => v,
-            None => return true,
-        };
-        let event = VRCLogEvent {
-            time,
-            event: String::from("OnLocationChange"),
-            data: instance_id,
-            initial_load,
-        };
-        send_event("VRC_LOG_EVENT", event.clone()).await;
-        if initial_load {
-            trace!("[Core] VRC Log Event: {event:?}");
-        } else {
-            debug!("[Core] VRC Log Event: {event:?}");
-        }
-        return true;
+    for (time, data) in authentication.into_iter().chain(events) {
+        send_event(
+            "VRC_LOG_EVENT",
+            VRCLogEvent {
+                time,
+                data,
+                initial_load: true,
+            },
+        )
+        .await;
     }
-    false
 }
 
-fn start_log_watch_task(path: String) -> CancellationToken {
+fn start_log_watch_task(path: String, resume: bool) -> CancellationToken {
     let cancellation_token = CancellationToken::new();
     let cancellation_token_internal = cancellation_token.clone();
     tokio::spawn(async move {
-        let file = File::open(path.clone()).unwrap();
-        let reader = BufReader::new(file);
-        let lines = reader.lines();
-        let mut lines_iterator = lines;
+        // Continue where we left off if we've already processed part of this file before
+        let position = if resume {
+            tailer::load_position().await
+        } else {
+            None
+        };
+        let log_file = log_file_name(&path);
+        let mut tailer = tailer::LogTailer::new(path.clone(), position);
+        if tailer.position().offset > 0 {
+            info!(
+                "[Core] Resuming VRChat log file from offset {}. ({path})",
+                tailer.position().offset
+            );
+            replay_current_session(&path, tailer.position().offset).await;
+        }
         let mut first_run = true;
+        let mut saved_position = tailer.position();
+        let mut next_position_save = Instant::now();
 
         // Use an async block to make the loop asynchronous
         while !cancellation_token_internal.is_cancelled() {
@@ -211,12 +114,23 @@ fn start_log_watch_task(path: String) -> CancellationToken {
             }
 
             // Process new lines
-            for line in lines_iterator.by_ref() {
-                let line = line.unwrap();
-                if line.trim().is_empty() {
-                    continue;
+            loop {
+                let lines = match tailer.read_lines(MAX_LINES_PER_READ) {
+                    Ok(lines) => lines,
+                    Err(e) => {
+                        warn!("[Core] Could not read VRChat log file: {e} ({path})");
+                        break;
+                    }
+                };
+                if lines.is_empty() {
+                    break;
+                }
+                for line in lines {
+                    if line.text.trim().is_empty() {
+                        continue;
+                    }
+                    process_log_line(&log_file, line, first_run).await;
                 }
-                process_log_line(line, first_run).await;
             }
 
             if first_run {
@@ -228,22 +142,31 @@ fn start_log_watch_task(path: String) -> CancellationToken {
                             .duration_since(UNIX_EPOCH)
                             .unwrap()
                             .as_millis() as u64,
-                        event: String::from("InitialLoadComplete"),
-                        data: String::from(""),
+                        data: VRCLogEventData::InitialLoadComplete,
                         initial_load: true,
                     },
                 )
                 .await;
                 first_run = false;
             }
+
+            // Periodically store how far we got, so we can resume from there later
+            if tailer.position() != saved_position && Instant::now() >= next_position_save {
+                saved_position = tailer.position();
+                next_position_save = Instant::now() + POSITION_SAVE_INTERVAL;
+                tailer::save_position(&saved_position).await;
+            }
         }
 
+        if tailer.position() != saved_position {
+            tailer::save_position(&tailer.position()).await;
+        }
         info!("[Core] Log reader task terminated. ({path})");
     });
     cancellation_token
 }
 
-pub fn start_log_locator_task() -> CancellationToken {
+pub fn start_log_locator_task(resume: bool) -> CancellationToken {
     let cancellation_token = CancellationToken::new();
     let cancellation_token_internal = cancellation_token.clone();
     tokio::spawn(async move {
@@ -259,7 +182,7 @@ pub fn start_log_locator_task() -> CancellationToken {
         while !cancellation_token_internal.is_cancelled() {
             tokio::time::sleep(Duration::from_millis(1000)).await;
             // Check the current log file path
-            let log_path_option = get_latest_log_path();
+            let log_path_option = locator::get_latest_log_path().await;
             if log_path_option.is_none() {
                 // If we are currently reading a log file, stop the reader task
                 if ctx.current_log_path.is_some() {
@@ -289,7 +212,10 @@ pub fn start_log_locator_task() -> CancellationToken {
             info!("[Core] Starting VRChat log watcher. ({})", log_path.clone());
             *ctx = LoopContext {
                 current_log_path: Some(log_path.clone()),
-                reader_task_cancellation_token: Some(start_log_watch_task(log_path.clone())),
+                reader_task_cancellation_token: Some(start_log_watch_task(
+                    log_path.clone(),
+                    resume,
+                )),
             };
             // Inform the front of the current log path
             send_event("VRC_LOG_CURRENT_FILE", Some(log_path.clone())).await;
@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VRCLogEvent {
+    pub time: u64,
+    #[serde(flatten)]
+    pub data: VRCLogEventData,
+    pub initial_load: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "event", content = "data", rename_all_fields = "camelCase")]
+pub enum VRCLogEventData {
+    InitialLoadComplete,
+    OnPlayerJoined {
+        display_name: String,
+        user_id: Option<String>,
+    },
+    OnPlayerLeft {
+        display_name: String,
+        user_id: Option<String>,
+    },
+    OnLocationChange {
+        instance_id: String,
+    },
+    OnWorldEntered {
+        world_name: String,
+    },
+    OnAvatarChanged {
+        display_name: String,
+        avatar_name: String,
+    },
+    OnVideoPlayerUrl {
+        url: String,
+    },
+    OnPortalDropped,
+    OnVoteKickInitiated {
+        display_name: String,
+    },
+    OnModerationNotice {
+        message: String,
+    },
+    OnUserAuthenticated {
+        display_name: String,
+        user_id: Option<String>,
+    },
+    OnApplicationQuit,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VRCInstanceVisit {
+    pub instance_id: String,
+    pub world_name: Option<String>,
+    pub joined_at: u64,
+    pub left_at: Option<u64>,
+    pub players: Vec<VRCPlayerPresence>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VRCPlayerPresence {
+    pub display_name: String,
+    pub user_id: Option<String>,
+    pub joined_at: u64,
+    pub left_at: Option<u64>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VRCLogImportResult {
+    pub files_processed: usize,
+    pub files_failed: usize,
+    pub lines_processed: usize,
+    pub events_found: usize,
+    pub events_imported: usize,
+}
+
+//
+// Events
+//
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VRCLogImportProgressEvent {
+    pub files_processed: usize,
+    pub files_total: usize,
+    pub events_imported: usize,
+}
@@ -0,0 +1,315 @@
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{File, Metadata},
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom},
+};
+
+use crate::utils::get_data_file_path;
+
+const POSITION_FILE_NAME: &str = "vrc_log_position.json";
+
+/// The position up to which a log file has been processed.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LogTailPosition {
+    pub path: String,
+    pub identity: u64,
+    pub offset: u64,
+}
+
+/// A complete line read from a log file
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogLine {
+    /// The byte offset at which the line starts
+    pub offset: u64,
+    pub text: String,
+}
+
+/// Reads complete lines from a log file that is still being written to.
+///
+/// Only fully written lines (terminated by a newline) are returned, and the offset only advances past them,
+/// so a partially written line is picked up again once it's complete. If the file shrinks below the current
+/// offset, or is replaced by a different file on the same path, reading restarts from the beginning.
+pub struct LogTailer {
+    path: String,
+    reader: Option<BufReader<File>>,
+    identity: u64,
+    offset: u64,
+}
+
+impl LogTailer {
+    pub fn new(path: String, position: Option<LogTailPosition>) -> Self {
+        let (identity, offset) = match position {
+            Some(position) if position.path == path => (position.identity, position.offset),
+            _ => (0, 0),
+        };
+        Self {
+            path,
+            reader: None,
+            identity,
+            offset,
+        }
+    }
+
+    pub fn position(&self) -> LogTailPosition {
+        LogTailPosition {
+            path: self.path.clone(),
+            identity: self.identity,
+            offset: self.offset,
+        }
+    }
+
+    /// Reads up to `max_lines` new lines. Invalid UTF-8 sequences are replaced rather than rejected.
+    pub fn read_lines(&mut self, max_lines: usize) -> io::Result<Vec<LogLine>> {
+        self.check_file()?;
+        let reader = match self.reader.as_mut() {
+            Some(reader) => reader,
+            None => return Ok(vec![]),
+        };
+        let mut lines = Vec::new();
+        let mut buf = Vec::new();
+        while lines.len() < max_lines {
+            buf.clear();
+            let read = reader.read_until(b'\n', &mut buf)?;
+            if read == 0 {
+                break;
+            }
+            if buf.last() != Some(&b'\n') {
+                // The line is still being written, so we rewind and try again next time
+                reader.seek(SeekFrom::Start(self.offset))?;
+                break;
+            }
+            lines.push(LogLine {
+                offset: self.offset,
+                text: decode_line(&buf),
+            });
+            self.offset += read as u64;
+        }
+        Ok(lines)
+    }
+
+    fn check_file(&mut self) -> io::Result<()> {
+        let metadata = std::fs::metadata(&self.path)?;
+        let identity = file_identity(&metadata);
+        if self.reader.is_some() || self.offset > 0 {
+            if identity != self.identity {
+                info!(
+                    "[Core] VRChat log file was replaced. Reading from the start. ({})",
+                    self.path
+                );
+                self.reset();
+            } else if metadata.len() < self.offset {
+                warn!(
+                    "[Core] VRChat log file was truncated. Reading from the start. ({})",
+                    self.path
+                );
+                self.reset();
+            }
+        }
+        self.identity = identity;
+        if self.reader.is_none() {
+            let mut reader = BufReader::new(File::open(&self.path)?);
+            reader.seek(SeekFrom::Start(self.offset))?;
+            self.reader = Some(reader);
+        }
+        Ok(())
+    }
+
+    fn reset(&mut self) {
+        self.reader = None;
+        self.offset = 0;
+    }
+}
+
+/// Calls `f` for every complete line before the given offset, e.g. to look back at what was processed before
+/// resuming from that offset.
+pub fn for_each_line_before(path: &str, offset: u64, mut f: impl FnMut(&str)) -> io::Result<()> {
+    let mut reader = BufReader::new(File::open(path)?.take(offset));
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf)? == 0 || buf.last() != Some(&b'\n') {
+            return Ok(());
+        }
+        f(&decode_line(&buf));
+    }
+}
+
+/// Decodes a line without its line ending, replacing invalid UTF-8 sequences
+pub fn decode_line(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes)
+        .trim_end_matches(['\r', '\n'])
+        .to_string()
+}
+
+#[cfg(unix)]
+fn file_identity(metadata: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.ino()
+}
+
+#[cfg(not(unix))]
+fn file_identity(metadata: &Metadata) -> u64 {
+    // The file index is not available on stable Rust, so we use the creation time instead
+    metadata
+        .created()
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+pub async fn load_position() -> Option<LogTailPosition> {
+    let path = get_data_file_path(POSITION_FILE_NAME).await?;
+    let contents = tokio::fs::read_to_string(path).await.ok()?;
+    match serde_json::from_str(&contents) {
+        Ok(position) => Some(position),
+        Err(e) => {
+            warn!("[Core] Could not parse stored VRChat log position: {e}");
+            None
+        }
+    }
+}
+
+pub async fn save_position(position: &LogTailPosition) {
+    let path = match get_data_file_path(POSITION_FILE_NAME).await {
+        Some(path) => path,
+        None => return,
+    };
+    if let Err(e) = tokio::fs::write(path, serde_json::to_string(position).unwrap()).await {
+        error!("[Core] Could not store VRChat log position: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs::OpenOptions, io::Write, path::PathBuf};
+
+    /// A log file in the temp directory, removed again when dropped
+    struct TempLog(PathBuf);
+
+    impl TempLog {
+        fn new(name: &str, contents: &[u8]) -> Self {
+            let path = std::env::temp_dir().join(format!(
+                "oyasumivr_tailer_{}_{name}.txt",
+                std::process::id()
+            ));
+            std::fs::write(&path, contents).unwrap();
+            Self(path)
+        }
+
+        fn path(&self) -> String {
+            self.0.to_str().unwrap().to_string()
+        }
+
+        fn append(&self, contents: &[u8]) {
+            let mut file = OpenOptions::new().append(true).open(&self.0).unwrap();
+            file.write_all(contents).unwrap();
+        }
+    }
+
+    fn read_texts(tailer: &mut LogTailer, max_lines: usize) -> Vec<String> {
+        tailer
+            .read_lines(max_lines)
+            .unwrap()
+            .into_iter()
+            .map(|line| line.text)
+            .collect()
+    }
+
+    impl Drop for TempLog {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn waits_for_partial_trailing_lines_to_complete() {
+        let log = TempLog::new("partial", b"first\r\nsecond\nthi");
+        let mut tailer = LogTailer::new(log.path(), None);
+        let lines = tailer.read_lines(100).unwrap();
+        assert_eq!(lines[0].offset, 0);
+        assert_eq!(lines[0].text, "first");
+        assert_eq!(lines[1].offset, 7);
+        assert_eq!(lines[1].text, "second");
+        assert_eq!(tailer.position().offset, 14);
+        assert!(read_texts(&mut tailer, 100).is_empty());
+        log.append(b"rd\nfourth");
+        assert_eq!(read_texts(&mut tailer, 100), ["third"]);
+        assert_eq!(tailer.position().offset, 20);
+    }
+
+    #[test]
+    fn reads_at_most_the_requested_number_of_lines() {
+        let log = TempLog::new("max_lines", b"1\n2\n3\n");
+        let mut tailer = LogTailer::new(log.path(), None);
+        assert_eq!(read_texts(&mut tailer, 2), ["1", "2"]);
+        assert_eq!(read_texts(&mut tailer, 2), ["3"]);
+    }
+
+    #[test]
+    fn replaces_invalid_utf8() {
+        let log = TempLog::new(
+            "invalid_utf8",
+            b"valid\nin\xffvalid \xe3\x81\n\xf0\x9f\x92\xa4\n",
+        );
+        let mut tailer = LogTailer::new(log.path(), None);
+        assert_eq!(
+            read_texts(&mut tailer, 100),
+            ["valid", "in\u{fffd}valid \u{fffd}", "\u{1f4a4}"]
+        );
+    }
+
+    #[test]
+    fn restarts_when_the_file_is_truncated() {
+        let log = TempLog::new("truncated", b"old line 1\nold line 2\n");
+        let mut tailer = LogTailer::new(log.path(), None);
+        assert_eq!(read_texts(&mut tailer, 100).len(), 2);
+        std::fs::write(&log.0, b"new\n").unwrap();
+        assert_eq!(read_texts(&mut tailer, 100), ["new"]);
+        assert_eq!(tailer.position().offset, 4);
+    }
+
+    // On Windows, files are identified by their creation time, which file system tunneling carries over to a file
+    // that replaces another within seconds.
+    #[cfg(unix)]
+    #[test]
+    fn restarts_when_the_file_is_replaced() {
+        let log = TempLog::new("rotated", b"old\n");
+        let mut tailer = LogTailer::new(log.path(), None);
+        assert_eq!(read_texts(&mut tailer, 100), ["old"]);
+        // Replace the file with a longer one, so it can't be mistaken for a truncation
+        let replacement = TempLog::new("rotated_replacement", b"new line 1\nnew line 2\n");
+        std::fs::rename(&replacement.0, &log.0).unwrap();
+        assert_eq!(read_texts(&mut tailer, 100), ["new line 1", "new line 2"]);
+    }
+
+    #[test]
+    fn resumes_from_a_stored_position() {
+        let log = TempLog::new("resume", b"processed\nnew\n");
+        let mut tailer = LogTailer::new(log.path(), None);
+        read_texts(&mut tailer, 1);
+        let position = tailer.position();
+
+        let mut resumed = LogTailer::new(log.path(), Some(position.clone()));
+        assert_eq!(read_texts(&mut resumed, 100), ["new"]);
+        // Positions of other files are ignored
+        let other_file = LogTailPosition {
+            path: String::from("output_log_other.txt"),
+            ..position
+        };
+        let mut restarted = LogTailer::new(log.path(), Some(other_file));
+        assert_eq!(read_texts(&mut restarted, 100), ["processed", "new"]);
+    }
+
+    #[test]
+    fn reads_the_lines_before_an_offset() {
+        let log = TempLog::new("before_offset", b"first\r\nsecond\nthird\n");
+        let mut lines = Vec::new();
+        for_each_line_before(&log.path(), 17, |line| lines.push(line.to_string())).unwrap();
+        // The third line isn't complete before the offset
+        assert_eq!(lines, ["first", "second"]);
+    }
+}
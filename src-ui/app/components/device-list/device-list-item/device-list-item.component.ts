@@ -11,7 +11,7 @@ import { EventLogService } from '../../../services/event-log.service';
 import { LighthouseDevice, LighthouseDevicePowerState } from 'src-ui/app/models/lighthouse-device';
 import { LighthouseService } from 'src-ui/app/services/lighthouse.service';
 import { AppSettingsService } from 'src-ui/app/services/app-settings.service';
-import { distinctUntilChanged, firstValueFrom, map, skip } from 'rxjs';
+import { distinctUntilChanged, firstValueFrom, skip } from 'rxjs';
 import { ModalService } from 'src-ui/app/services/modal.service';
 import { OpenVRService } from '../../../services/openvr.service';
 import { takeUntilDestroyed } from '@angular/core/rxjs-interop';
@@ -85,10 +85,13 @@ export class DeviceListItemComponent implements OnInit {
     this.isCharging = false;
     this.batteryPercentage = 100;
     this.batteryPercentageString = '100%';
-    switch (device.powerState) {
+    switch (device.reachable ? device.powerState : 'unreachable') {
       case 'unknown':
         this.status = null;
         break;
+      case 'unreachable':
+        this.status = 'comp.device-list.lighthouseStatus.unreachable';
+        break;
       default:
         this.status = 'comp.device-list.lighthouseStatus.' + device.powerState;
         break;
@@ -171,10 +174,9 @@ export class DeviceListItemComponent implements OnInit {
     this.lighthouse.devices.pipe(takeUntilDestroyed(this.destroyRef)).subscribe(() => {
       if (this._lighthouseDevice) this.lighthouseDevice = this._lighthouseDevice;
     });
-    this.appSettings.settings
+    this.lighthouse.v1Configs
       .pipe(
         takeUntilDestroyed(this.destroyRef),
-        map((s) => s.v1LighthouseIdentifiers),
         distinctUntilChanged((a, b) => isEqual(a, b)),
         skip(1)
       )
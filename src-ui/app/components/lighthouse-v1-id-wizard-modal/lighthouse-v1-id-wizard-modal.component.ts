@@ -7,7 +7,6 @@ import { animate, style, transition, trigger } from '@angular/animations';
 import { OpenVRService } from '../../services/openvr.service';
 import { takeUntilDestroyed } from '@angular/core/rxjs-interop';
 import { combineLatest, debounceTime, delay, filter, firstValueFrom, interval } from 'rxjs';
-import { AppSettingsService } from '../../services/app-settings.service';
 
 export interface LighthouseV1IdWizardModalInputModel {
   device: LighthouseDevice;
@@ -82,8 +81,7 @@ export class LighthouseV1IdWizardModalComponent
   constructor(
     private lighthouseService: LighthouseService,
     private openvr: OpenVRService,
-    private destroyRef: DestroyRef,
-    private appSettings: AppSettingsService
+    private destroyRef: DestroyRef
   ) {
     super();
     this.device = {} as LighthouseDevice;
@@ -226,12 +224,10 @@ export class LighthouseV1IdWizardModalComponent
   }
 
   async saveId(v1Identifier: string) {
-    const lighthouseId = this.device.id;
-    const settings = await firstValueFrom(this.appSettings.settings);
-    const v1LighthouseIdentifiers = structuredClone(settings.v1LighthouseIdentifiers ?? {});
-    v1LighthouseIdentifiers[lighthouseId] = v1Identifier;
-    this.appSettings.updateSettings({
-      v1LighthouseIdentifiers,
+    const config = await firstValueFrom(this.lighthouseService.v1Configs);
+    await this.lighthouseService.setV1Config(this.device.id, {
+      identifier: v1Identifier,
+      timeout: config[this.device.id]?.timeout ?? null,
     });
   }
 
@@ -17,4 +17,31 @@ export interface SleepDetectorStateReport {
   rotationInLast10Seconds: number;
   startTime: number;
   lastLog: number;
+  classification: SleepClassification;
+}
+
+export type SleepState = 'AWAKE' | 'DROWSY' | 'ASLEEP';
+
+export interface SleepClassification {
+  state: SleepState;
+  since: number;
+  score: number | null;
+}
+
+export interface SleepBaseline {
+  restingDistancePerSecond: number;
+  restingRotationPerSecond: number;
+  awakeDistancePerSecond: number;
+  awakeRotationPerSecond: number;
+}
+
+export type Gesture =
+  | 'head_shake'
+  | 'head_nod'
+  | 'head_tilt'
+  | 'hands_above_head'
+  | 'hand_covering_face';
+
+export interface GestureDetected {
+  gesture: Gesture;
 }
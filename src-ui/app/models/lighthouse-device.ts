@@ -4,6 +4,11 @@ export interface LighthouseDevice {
   deviceType: LighthouseDeviceType;
   powerState: LighthouseDevicePowerState;
   v1Timeout: number | null;
+  channel: number | null;
+  firmwareRevision: string | null;
+  hardwareRevision: string | null;
+  // Whether the device responded recently enough
+  reachable: boolean;
   // For UI purposes only
   transitioningToPowerState: LighthouseDevicePowerState | undefined;
 }
@@ -11,3 +16,45 @@ export interface LighthouseDevice {
 export type LighthouseDeviceType = 'lighthouseV1' | 'lighthouseV2';
 
 export type LighthouseDevicePowerState = 'on' | 'unknown' | 'sleep' | 'standby' | 'booting';
+
+export interface LighthouseMonitorConfig {
+  // Polling interval right after a command was sent, or the power state changed
+  minPollIntervalMs: number;
+  // Polling interval to back off to while the power state stays the same
+  maxPollIntervalMs: number;
+  // How long a device can go without responding before it is considered unreachable
+  unreachableTimeoutMs: number;
+}
+
+export interface LighthouseV1DeviceConfig {
+  // The identifier printed on the back of the base station, as 8 hexadecimal digits
+  identifier: string;
+  // Seconds the base station waits before going to sleep when told to
+  timeout: number | null;
+}
+
+export interface LighthouseGroup {
+  id: string;
+  name: string;
+  deviceIds: string[];
+}
+
+export interface LighthouseOpenVRPowerRule {
+  groupId: string;
+  onStart: LighthouseDevicePowerState | null;
+  onStop: LighthouseDevicePowerState | null;
+  v1Timeout: number | null;
+}
+
+export interface LighthouseDevicePowerResult {
+  deviceId: string;
+  success: boolean;
+  attempts: number;
+  error: { error: string; message: string | null } | null;
+}
+
+export interface LighthouseBatchPowerCompletedEvent {
+  groupId: string | null;
+  powerState: LighthouseDevicePowerState;
+  results: LighthouseDevicePowerResult[];
+}
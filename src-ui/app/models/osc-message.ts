@@ -1,8 +1,24 @@
-export type OSCValueType = 'int' | 'float' | 'string' | 'bool' | 'unsupported';
+export type OSCValueType =
+  | 'int'
+  | 'float'
+  | 'string'
+  | 'bool'
+  | 'long'
+  | 'double'
+  | 'char'
+  | 'blob'
+  | 'time'
+  | 'color'
+  | 'midi'
+  | 'nil'
+  | 'inf'
+  | 'array'
+  | 'unsupported';
 
 export interface OSCValueRaw {
   kind: OSCValueType;
-  value: string;
+  value: string | null;
+  values?: OSCValueRaw[];
 }
 
 export interface OSCMessageRaw {
@@ -10,6 +26,79 @@ export interface OSCMessageRaw {
   values: OSCValueRaw[];
 }
 
+export interface OSCReceiverStats {
+  packetsReceived: number;
+  packetsDropped: number;
+  packetsMalformed: number;
+  queueSize: number;
+}
+
+export interface OSCRelayTarget {
+  id: string;
+  address: string;
+  enabled: boolean;
+  filters: string[];
+  rewrites: { from: string; to: string }[];
+}
+
+export interface OSCRelayTargetStatus {
+  id: string;
+  address: string;
+  health: 'IDLE' | 'OK' | 'ERROR' | 'DISABLED';
+  packetsForwarded: number;
+  sendErrors: number;
+  consecutiveSendErrors: number;
+  lastForwardedAt: number | null;
+  lastError: string | null;
+}
+
+export interface VRCAvatarState {
+  avatarId: string | null;
+  parameters: Record<string, { value: OSCValueRaw; updatedAt: number }>;
+}
+
+export interface VRCAvatarParameterChangedEvent {
+  name: string;
+  value: OSCValueRaw;
+}
+
+export interface OSCQueryService {
+  name: string;
+  // Advertised as _oscjson._tcp
+  oscquery: OSCServiceAddress | null;
+  // Advertised as _osc._udp
+  osc: OSCServiceAddress | null;
+}
+
+export interface OSCServiceAddress {
+  host: string;
+  port: number;
+}
+
+export interface OSCQueryHostInfo {
+  name: string | null;
+  oscIp: string | null;
+  oscPort: number | null;
+  oscTransport: string | null;
+  extensions: Record<string, boolean>;
+}
+
+export interface OSCQueryNode {
+  fullPath: string;
+  description: string | null;
+  typeTag: string | null;
+  // 0: No access, 1: Read-only, 2: Write-only, 3: Read and write
+  access: 0 | 1 | 2 | 3 | null;
+  range: { min: unknown; max: unknown; vals: unknown[] | null }[];
+  value: unknown[];
+  contents: OSCQueryNode[];
+}
+
+export interface OSCTargetOverride {
+  oscAddress: string | null;
+  oscqueryAddress: string | null;
+}
+
 export interface OSCValue {
   kind: OSCValueType;
 }
@@ -34,6 +123,56 @@ export interface OSCBoolValue extends OSCValue {
   value: boolean;
 }
 
+export interface OSCLongValue extends OSCValue {
+  kind: 'long';
+  value: bigint;
+}
+
+export interface OSCDoubleValue extends OSCValue {
+  kind: 'double';
+  value: number;
+}
+
+export interface OSCCharValue extends OSCValue {
+  kind: 'char';
+  value: string;
+}
+
+// Blobs, colors (RRGGBBAA) and MIDI messages (PPSSD1D2) are passed as hex strings
+export interface OSCBlobValue extends OSCValue {
+  kind: 'blob';
+  value: string;
+}
+
+export interface OSCColorValue extends OSCValue {
+  kind: 'color';
+  value: string;
+}
+
+export interface OSCMidiValue extends OSCValue {
+  kind: 'midi';
+  value: string;
+}
+
+// 64-bit NTP timestamp
+export interface OSCTimeValue extends OSCValue {
+  kind: 'time';
+  value: bigint;
+}
+
+export interface OSCNilValue extends OSCValue {
+  kind: 'nil';
+}
+
+export interface OSCInfValue extends OSCValue {
+  kind: 'inf';
+}
+
+export interface OSCArrayValue extends OSCValue {
+  kind: 'array';
+  value: OSCValue[];
+}
+
 export interface OSCUnsupportedValue extends OSCValue {
   kind: 'unsupported';
 }
@@ -54,17 +193,31 @@ export function parseOSCValue(value: OSCValueRaw): OSCValue {
   let parsedValue: unknown;
   switch (value.kind) {
     case 'int':
-      parsedValue = parseInt(value.value);
+      parsedValue = parseInt(value.value ?? '');
       break;
     case 'float':
-      parsedValue = parseFloat(value.value);
+    case 'double':
+      parsedValue = parseFloat(value.value ?? '');
+      break;
+    case 'long':
+    case 'time':
+      parsedValue = BigInt(value.value ?? '0');
       break;
     case 'string':
+    case 'char':
+    case 'blob':
+    case 'color':
+    case 'midi':
       parsedValue = value.value;
       break;
     case 'bool':
       parsedValue = value.value === 'true';
       break;
+    case 'array':
+      parsedValue = (value.values ?? []).map(parseOSCValue);
+      break;
+    case 'nil':
+    case 'inf':
     case 'unsupported':
       parsedValue = undefined;
       break;
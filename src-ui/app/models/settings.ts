@@ -38,6 +38,7 @@ export interface AppSettings {
   lighthouseConsolePath: string;
   lighthousePowerControl: boolean;
   lighthousePowerOffState: LighthouseDevicePowerState;
+  // No longer used: V1 identifiers are stored by the core. Only kept for migrating existing identifiers.
   v1LighthouseIdentifiers: {
     [deviceId: string]: string;
   };
@@ -1,9 +1,28 @@
-export type VRChatLogEventType = 'OnPlayerJoined' | 'OnPlayerLeft' | 'OnLocationChange';
+export type VRChatLogEventType =
+  | 'OnPlayerJoined'
+  | 'OnPlayerLeft'
+  | 'OnLocationChange'
+  | 'OnWorldEntered'
+  | 'OnAvatarChanged'
+  | 'OnVideoPlayerUrl'
+  | 'OnPortalDropped'
+  | 'OnVoteKickInitiated'
+  | 'OnModerationNotice'
+  | 'OnUserAuthenticated'
+  | 'OnApplicationQuit';
 
 export type VRChatLogEvent =
   | VRChatOnPlayerJoinedEvent
   | VRChatOnPlayerLeftEvent
-  | VRChatOnLocationChangeEvent;
+  | VRChatOnLocationChangeEvent
+  | VRChatOnWorldEnteredEvent
+  | VRChatOnAvatarChangedEvent
+  | VRChatOnVideoPlayerUrlEvent
+  | VRChatOnPortalDroppedEvent
+  | VRChatOnVoteKickInitiatedEvent
+  | VRChatOnModerationNoticeEvent
+  | VRChatOnUserAuthenticatedEvent
+  | VRChatOnApplicationQuitEvent;
 
 interface VRChatLogEventBase {
   type: VRChatLogEventType;
@@ -27,3 +46,43 @@ export interface VRChatOnLocationChangeEvent extends VRChatLogEventBase {
   type: 'OnLocationChange';
   instanceId: string;
 }
+
+export interface VRChatOnWorldEnteredEvent extends VRChatLogEventBase {
+  type: 'OnWorldEntered';
+  worldName: string;
+}
+
+export interface VRChatOnAvatarChangedEvent extends VRChatLogEventBase {
+  type: 'OnAvatarChanged';
+  displayName: string;
+  avatarName: string;
+}
+
+export interface VRChatOnVideoPlayerUrlEvent extends VRChatLogEventBase {
+  type: 'OnVideoPlayerUrl';
+  url: string;
+}
+
+export interface VRChatOnPortalDroppedEvent extends VRChatLogEventBase {
+  type: 'OnPortalDropped';
+}
+
+export interface VRChatOnVoteKickInitiatedEvent extends VRChatLogEventBase {
+  type: 'OnVoteKickInitiated';
+  displayName: string;
+}
+
+export interface VRChatOnModerationNoticeEvent extends VRChatLogEventBase {
+  type: 'OnModerationNotice';
+  message: string;
+}
+
+export interface VRChatOnUserAuthenticatedEvent extends VRChatLogEventBase {
+  type: 'OnUserAuthenticated';
+  displayName: string;
+  userId: string;
+}
+
+export interface VRChatOnApplicationQuitEvent extends VRChatLogEventBase {
+  type: 'OnApplicationQuit';
+}
@@ -15,14 +15,26 @@ import {
   Observable,
   of,
   shareReplay,
+  Subject,
   take,
 } from 'rxjs';
-import { LighthouseDevice, LighthouseDevicePowerState } from '../models/lighthouse-device';
+import {
+  LighthouseBatchPowerCompletedEvent,
+  LighthouseDevice,
+  LighthouseDevicePowerResult,
+  LighthouseDevicePowerState,
+  LighthouseGroup,
+  LighthouseMonitorConfig,
+  LighthouseOpenVRPowerRule,
+  LighthouseV1DeviceConfig,
+} from '../models/lighthouse-device';
 import { AppSettingsService } from './app-settings.service';
 import { pRetry } from '../utils/promise-utils';
 
 const DEFAULT_SCAN_DURATION = 8;
 export type LighthouseStatus = 'uninitialized' | 'noAdapter' | 'adapterError' | 'ready';
+// Errors for which retrying a power command won't help
+const V1_IDENTIFIER_ERRORS = ['MissingV1Identifier', 'InvalidV1Identifier', 'V1IdentifierRejected'];
 
 interface LighthouseScanningStatusChangedEvent {
   scanning: boolean;
@@ -36,12 +48,21 @@ interface LighthouseDeviceDiscoveredEvent {
   device: LighthouseDevice;
 }
 
+interface LighthouseDeviceInfoChangedEvent {
+  device: LighthouseDevice;
+}
+
 interface LighthouseDevicePowerStateChangedEvent {
   deviceId: string;
   powerState: LighthouseDevicePowerState;
   v1Timeout: number | null;
 }
 
+interface LighthouseDeviceReachabilityChangedEvent {
+  deviceId: string;
+  reachable: boolean;
+}
+
 @Injectable({
   providedIn: 'root',
 })
@@ -55,7 +76,14 @@ export class LighthouseService {
     LighthouseDevice[]
   >([]);
   public readonly devices: Observable<LighthouseDevice[]> = this._devices.asObservable();
-  private v1Identifiers: { [id: string]: string } = {};
+  private readonly _v1Configs: BehaviorSubject<{ [deviceId: string]: LighthouseV1DeviceConfig }> =
+    new BehaviorSubject<{ [deviceId: string]: LighthouseV1DeviceConfig }>({});
+  public readonly v1Configs: Observable<{ [deviceId: string]: LighthouseV1DeviceConfig }> =
+    this._v1Configs.asObservable();
+  private readonly _batchPowerCompleted: Subject<LighthouseBatchPowerCompletedEvent> =
+    new Subject<LighthouseBatchPowerCompletedEvent>();
+  public readonly batchPowerCompleted: Observable<LighthouseBatchPowerCompletedEvent> =
+    this._batchPowerCompleted.asObservable();
 
   constructor(private appSettings: AppSettingsService) {}
 
@@ -69,13 +97,24 @@ export class LighthouseService {
     listen<LighthouseDeviceDiscoveredEvent>('LIGHTHOUSE_DEVICE_DISCOVERED', (event) =>
       this.handleDeviceDiscovered(event.payload)
     );
+    listen<LighthouseDeviceInfoChangedEvent>('LIGHTHOUSE_DEVICE_INFO_CHANGED', (event) =>
+      this.handleDeviceDiscovered(event.payload)
+    );
     listen<LighthouseDevicePowerStateChangedEvent>(
       'LIGHTHOUSE_DEVICE_POWER_STATE_CHANGED',
       (event) => this.handleDevicePowerStateChange(event.payload)
     );
-    this.appSettings.settings.subscribe((settings) => {
-      this.v1Identifiers = settings.v1LighthouseIdentifiers;
-    });
+    listen<LighthouseDeviceReachabilityChangedEvent>(
+      'LIGHTHOUSE_DEVICE_REACHABILITY_CHANGED',
+      (event) => this.handleDeviceReachabilityChange(event.payload)
+    );
+    listen<LighthouseBatchPowerCompletedEvent>('LIGHTHOUSE_BATCH_POWER_COMPLETED', (event) =>
+      this._batchPowerCompleted.next(event.payload)
+    );
+    this._v1Configs.next(
+      await invoke<{ [deviceId: string]: LighthouseV1DeviceConfig }>('lighthouse_get_v1_configs')
+    );
+    await this.migrateV1Identifiers();
     // Respond to lighthouse power control being turned on or off
     this.appSettings.settings
       .pipe(
@@ -122,13 +161,8 @@ export class LighthouseService {
     force = false
   ) {
     // If the device is a V1 and we don't have the identifier, don't send the command
-    let v1Identifier = undefined;
-    if (device.deviceType === 'lighthouseV1') {
-      if (!this.v1Identifiers[device.id]) {
-        return;
-      }
-      v1Identifier = parseInt(this.v1Identifiers[device.id], 16);
-    }
+    // (The core uses the stored identifier for V1 base stations)
+    if (this.deviceNeedsIdentifier(device)) return;
     // Handle force flag
     if (!force) {
       device.transitioningToPowerState = ['on', 'sleep', 'standby'].includes(powerState)
@@ -137,16 +171,22 @@ export class LighthouseService {
       this._devices.next(this._devices.value);
     }
     // Set the power state
-    await pRetry(
-      () =>
-        invoke('lighthouse_set_device_power_state', {
-          deviceId: device.id,
-          powerState,
-          v1Identifier,
-        }),
-      3,
-      500
-    );
+    try {
+      await pRetry(
+        () =>
+          invoke('lighthouse_set_device_power_state', {
+            deviceId: device.id,
+            powerState,
+          }),
+        3,
+        500,
+        (e) => !this.isV1IdentifierError(e)
+      );
+    } catch (e) {
+      device.transitioningToPowerState = undefined;
+      this._devices.next(this._devices.value);
+      throw e;
+    }
     // Wait for state to change (timeout after 10 seconds)
     await firstValueFrom(
       merge(
@@ -163,6 +203,103 @@ export class LighthouseService {
     this._devices.next(this._devices.value);
   }
 
+  /**
+   * Stores the identifier (and optionally the sleep timeout) of a V1 base station in the core
+   */
+  public async setV1Config(deviceId: string, config: LighthouseV1DeviceConfig | null) {
+    await invoke('lighthouse_set_v1_config', { deviceId, config });
+    const configs = { ...this._v1Configs.value };
+    if (config) configs[deviceId] = { ...config, identifier: config.identifier.toUpperCase() };
+    else delete configs[deviceId];
+    this._v1Configs.next(configs);
+  }
+
+  public isV1IdentifierError(error: any): boolean {
+    return V1_IDENTIFIER_ERRORS.includes(error?.error);
+  }
+
+  // V1 identifiers used to be stored in the app settings. Moves them to the core.
+  private async migrateV1Identifiers() {
+    const settings = await firstValueFrom(this.appSettings.settings);
+    const identifiers = settings.v1LighthouseIdentifiers ?? {};
+    if (!Object.keys(identifiers).length) return;
+    // Identifiers that could not be migrated are kept, so they can be retried on the next start
+    const remaining: { [deviceId: string]: string } = {};
+    for (const [deviceId, identifier] of Object.entries(identifiers)) {
+      if (this._v1Configs.value[deviceId]) continue;
+      try {
+        await this.setV1Config(deviceId, { identifier, timeout: null });
+      } catch (e) {
+        console.warn(`Could not migrate V1 identifier for lighthouse ${deviceId}`, e);
+        remaining[deviceId] = identifier;
+      }
+    }
+    this.appSettings.updateSettings({ v1LighthouseIdentifiers: remaining });
+  }
+
+  public async identify(device: LighthouseDevice) {
+    await invoke('lighthouse_identify_device', { deviceId: device.id });
+  }
+
+  /**
+   * Returns the V2 base stations that share a channel with another base station
+   */
+  public getChannelConflicts(devices: LighthouseDevice[]): LighthouseDevice[] {
+    return devices.filter(
+      (device) =>
+        device.channel !== null &&
+        devices.some((other) => other.id !== device.id && other.channel === device.channel)
+    );
+  }
+
+  public async getGroups(): Promise<LighthouseGroup[]> {
+    return await invoke<LighthouseGroup[]>('lighthouse_get_groups');
+  }
+
+  public async setGroups(groups: LighthouseGroup[]) {
+    await invoke('lighthouse_set_groups', { groups });
+  }
+
+  public async setGroupPowerState(
+    groupId: string,
+    powerState: LighthouseDevicePowerState,
+    v1Timeout?: number
+  ): Promise<LighthouseDevicePowerResult[]> {
+    return await invoke<LighthouseDevicePowerResult[]>('lighthouse_set_group_power_state', {
+      groupId,
+      powerState,
+      v1Timeout,
+    });
+  }
+
+  public async setPowerStateBatch(
+    deviceIds: string[],
+    powerState: LighthouseDevicePowerState,
+    v1Timeout?: number
+  ): Promise<LighthouseDevicePowerResult[]> {
+    return await invoke<LighthouseDevicePowerResult[]>('lighthouse_set_power_state_batch', {
+      deviceIds,
+      powerState,
+      v1Timeout,
+    });
+  }
+
+  public async getMonitorConfig(): Promise<LighthouseMonitorConfig> {
+    return await invoke<LighthouseMonitorConfig>('lighthouse_get_monitor_config');
+  }
+
+  public async setMonitorConfig(config: LighthouseMonitorConfig) {
+    await invoke('lighthouse_set_monitor_config', { config });
+  }
+
+  public async getOpenVRPowerRules(): Promise<LighthouseOpenVRPowerRule[]> {
+    return await invoke<LighthouseOpenVRPowerRule[]>('lighthouse_get_openvr_power_rules');
+  }
+
+  public async setOpenVRPowerRules(rules: LighthouseOpenVRPowerRule[]) {
+    await invoke('lighthouse_set_openvr_power_rules', { rules });
+  }
+
   private async handleStatusChange(event: LighthouseStatusChangedEvent) {
     if (this._status.value !== event.status) {
       this._status.next(event.status);
@@ -173,7 +310,9 @@ export class LighthouseService {
     if (this._scanning.value !== event.scanning) this._scanning.next(event.scanning);
   }
 
-  private async handleDeviceDiscovered(event: LighthouseDeviceDiscoveredEvent) {
+  private async handleDeviceDiscovered(
+    event: LighthouseDeviceDiscoveredEvent | LighthouseDeviceInfoChangedEvent
+  ) {
     const device = event.device;
     const devices = this._devices.value;
     if (!devices.some((d) => d.id === device.id)) {
@@ -199,6 +338,14 @@ export class LighthouseService {
     this._devices.next(devices);
   }
 
+  private async handleDeviceReachabilityChange(event: LighthouseDeviceReachabilityChangedEvent) {
+    const devices = this._devices.value;
+    const device = devices.find((d) => d.id === event.deviceId);
+    if (!device) return;
+    device.reachable = event.reachable;
+    this._devices.next(devices);
+  }
+
   private async updateDevices() {
     const devices = this._devices.value;
     (await invoke<LighthouseDevice[]>('lighthouse_get_devices')).forEach((d) => {
@@ -229,7 +376,8 @@ export class LighthouseService {
                 powerState: timeout === 0 ? 'on' : 'sleep',
               }),
             retries,
-            retryDelay
+            retryDelay,
+            (e) => !this.isV1IdentifierError(e)
           );
         };
         const waitForTimeoutValue = async (value: number, timeout = 10000) => {
@@ -273,8 +421,9 @@ export class LighthouseService {
           }
           // Success!
           subscriber.next('SUCCESS');
-        } catch {
-          subscriber.next('ERROR');
+        } catch (e) {
+          // The base station ignored the sleep command
+          subscriber.next(this.isV1IdentifierError(e) ? 'INVALID' : 'ERROR');
         } finally {
           subscriber.complete();
         }
@@ -283,6 +432,6 @@ export class LighthouseService {
   }
 
   public deviceNeedsIdentifier(device: LighthouseDevice) {
-    return device.deviceType === 'lighthouseV1' && !this.v1Identifiers[device.id];
+    return device.deviceType === 'lighthouseV1' && !this._v1Configs.value[device.id];
   }
 }
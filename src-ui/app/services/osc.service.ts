@@ -5,7 +5,20 @@ import { flatten } from 'lodash';
 import { TaskQueue } from '../utils/task-queue';
 import { debug, error, info } from '@tauri-apps/plugin-log';
 import { listen } from '@tauri-apps/api/event';
-import { OSCMessage, OSCMessageRaw, parseOSCMessage } from '../models/osc-message';
+import {
+  OSCMessage,
+  OSCMessageRaw,
+  OSCReceiverStats,
+  OSCRelayTarget,
+  OSCRelayTargetStatus,
+  OSCQueryHostInfo,
+  OSCQueryNode,
+  OSCQueryService,
+  OSCServiceAddress,
+  OSCTargetOverride,
+  parseOSCMessage,
+  VRCAvatarState,
+} from '../models/osc-message';
 import {
   asyncScheduler,
   BehaviorSubject,
@@ -51,6 +64,11 @@ export class OscService {
   private readonly _oscMethods: BehaviorSubject<OscMethod<unknown>[]> = new BehaviorSubject<
     OscMethod<unknown>[]
   >([]);
+  private readonly _discoveredServices: BehaviorSubject<OSCQueryService[]> = new BehaviorSubject<
+    OSCQueryService[]
+  >([]);
+  public readonly discoveredServices: Observable<OSCQueryService[]> =
+    this._discoveredServices.asObservable();
   private avatarContext: AvatarContext | null = null;
 
   constructor(private appSettings: AppSettingsService) {}
@@ -91,6 +109,10 @@ export class OscService {
         // )
       )
       .subscribe();
+    await listen<OSCQueryService[]>('OSCQUERY_SERVICES_CHANGED', (event) => {
+      this._discoveredServices.next(event.payload);
+    });
+    this._discoveredServices.next(await invoke<OSCQueryService[]>('osc_get_discovered_services'));
     await listen<string | null>('VRC_OSC_ADDRESS_CHANGED', (event) => {
       this._vrchatOscAddress.next(event.payload);
     });
@@ -141,6 +163,117 @@ export class OscService {
     });
   }
 
+  /**
+   * Subscribe to messages matching the given OSC address patterns (e.g. `/avatar/parameters/*`).
+   * Each subscriber has its own set of patterns, which replaces any previously set for it.
+   */
+  public async setReceiveAddressFilter(subscriber: string, patterns: string[]) {
+    await invoke('set_osc_receive_address_filter', { subscriber, patterns });
+  }
+
+  public async removeReceiveAddressFilter(subscriber: string) {
+    await invoke('remove_osc_receive_address_filter', { subscriber });
+  }
+
+  public async getReceiverStats(): Promise<OSCReceiverStats> {
+    return await invoke<OSCReceiverStats>('get_osc_receiver_stats');
+  }
+
+  public async setRelayTargets(targets: OSCRelayTarget[]) {
+    await invoke('set_osc_relay_targets', { targets });
+  }
+
+  public async getRelayStatus(): Promise<OSCRelayTargetStatus[]> {
+    return await invoke<OSCRelayTargetStatus[]>('get_osc_relay_status');
+  }
+
+  public async startRecording(path: string) {
+    await invoke('osc_start_recording', { path });
+  }
+
+  public async stopRecording(): Promise<number> {
+    return await invoke<number>('osc_stop_recording');
+  }
+
+  public async startReplay(path: string, speed = 1.0) {
+    await invoke('osc_start_replay', { path, speed });
+  }
+
+  public async stopReplay() {
+    await invoke('osc_stop_replay');
+  }
+
+  public async getAvatarState(): Promise<VRCAvatarState> {
+    return await invoke<VRCAvatarState>('vrc_avatar_get_state');
+  }
+
+  /**
+   * Receive `VRC_AVATAR_PARAMETER_CHANGED` events for avatar parameters with names matching the given patterns.
+   */
+  public async subscribeAvatarParameters(subscriber: string, patterns: string[]) {
+    await invoke('vrc_avatar_subscribe_parameters', { subscriber, patterns });
+  }
+
+  public async unsubscribeAvatarParameters(subscriber: string) {
+    await invoke('vrc_avatar_unsubscribe_parameters', { subscriber });
+  }
+
+  /**
+   * Queue a message for the VRChat chatbox. Long messages are split up and sent at a rate VRChat accepts.
+   */
+  public async sendChatboxMessage(text: string, notifySound = true) {
+    for (const addr of this.getOscTargetAddresses()) {
+      await invoke('vrc_chatbox_send', { addr, text, notifySound });
+    }
+  }
+
+  public async setChatboxTyping(typing: boolean) {
+    for (const addr of this.getOscTargetAddresses()) {
+      await invoke('vrc_chatbox_set_typing', { addr, typing });
+    }
+  }
+
+  /**
+   * Press a VRChat input button (e.g. `Jump`), which is released automatically after the given duration.
+   */
+  public async pressInputButton(button: string, durationMs?: number) {
+    for (const addr of this.getOscTargetAddresses()) {
+      await invoke('vrc_input_press_button', { addr, button, durationMs });
+    }
+  }
+
+  public async setInputAxis(axis: string, value: number) {
+    for (const addr of this.getOscTargetAddresses()) {
+      await invoke('vrc_input_set_axis', { addr, axis, value });
+    }
+  }
+
+  /**
+   * Target the discovered service with the given name, or the detected VRChat client if null.
+   */
+  public async selectDiscoveredService(name: string | null) {
+    await invoke('osc_select_discovered_service', { name });
+  }
+
+  public async setTargetOverride(targetOverride: OSCTargetOverride) {
+    await invoke('osc_set_target_override', { targetOverride });
+  }
+
+  public async getOscQueryHostInfo(address: OSCServiceAddress): Promise<OSCQueryHostInfo> {
+    return await invoke<OSCQueryHostInfo>('oscquery_get_host_info', {
+      host: address.host,
+      port: address.port,
+    });
+  }
+
+  public async getOscQueryTree(address: OSCServiceAddress, path = '/'): Promise<OSCQueryNode> {
+    return await invoke<OSCQueryNode>('oscquery_get_tree', {
+      host: address.host,
+      port: address.port,
+      path,
+    });
+  }
+
   private async stopOscServer(): Promise<void> {
     this._oscServerAddress.next(null);
     this._oscQueryServerAddress.next(null);
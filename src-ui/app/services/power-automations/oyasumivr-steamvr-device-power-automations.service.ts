@@ -8,20 +8,30 @@ import { AutomationConfigService } from '../automation-config.service';
 import { LighthouseService } from '../lighthouse.service';
 import { EventLogService } from '../event-log.service';
 import {
-  asyncScheduler,
+  combineLatest,
   debounceTime,
+  distinctUntilChanged,
   filter,
   firstValueFrom,
   map,
-  pairwise,
   startWith,
-  throttleTime,
+  switchMap,
 } from 'rxjs';
 import { OpenVRService } from '../openvr.service';
 import { EventLogLighthouseSetPowerState } from 'src-ui/app/models/event-log-entry';
 import { AppSettingsService } from '../app-settings.service';
-import { LighthouseDevice } from '../../models/lighthouse-device';
+import {
+  LighthouseDevice,
+  LighthouseDevicePowerState,
+  LighthouseGroup,
+  LighthouseOpenVRPowerRule,
+} from '../../models/lighthouse-device';
 import { DeviceManagerService } from '../device-manager.service';
+import { isEqual } from 'lodash';
+
+// The core powers these groups on or off when SteamVR starts or stops, even while the window is closed
+const STEAMVR_STOP_GROUP_ID = 'OYASUMIVR_STEAMVR_STOP';
+const STEAMVR_START_GROUP_ID = 'OYASUMIVR_STEAMVR_START';
 
 @Injectable({
   providedIn: 'root',
@@ -47,32 +57,47 @@ export class OyasumiVRSteamVRDevicePowerAutomationsService {
       .pipe(map((configs) => configs.DEVICE_POWER_AUTOMATIONS))
       .subscribe((config) => (this.config = config));
 
-    // Detect SteamVR stops
-    this.openvr.status
+    // Keep the core's SteamVR power rules in sync with the config
+    combineLatest([
+      this.automationConfig.configs.pipe(
+        map((configs) => [
+          configs.DEVICE_POWER_AUTOMATIONS.turnOffDevicesOnSteamVRStop,
+          configs.DEVICE_POWER_AUTOMATIONS.turnOnDevicesOnSteamVRStart,
+        ]),
+        distinctUntilChanged((a, b) => isEqual(a, b))
+      ),
+      this.appSettings.settings.pipe(
+        map((settings) => settings.lighthousePowerOffState),
+        distinctUntilChanged()
+      ),
+      this.lighthouse.devices.pipe(
+        map((devices) => devices.map((d) => d.id)),
+        distinctUntilChanged((a, b) => isEqual(a, b))
+      ),
+    ])
       .pipe(
-        pairwise(),
         debounceTime(1000),
-        filter(([oldStatus, newStatus]) => oldStatus === 'INITIALIZED' && newStatus === 'INACTIVE'),
-        throttleTime(5000, asyncScheduler, { leading: true, trailing: false })
+        switchMap(([, powerOffState]) => this.updateSteamVRPowerRules(powerOffState))
       )
-      .subscribe(() => {
-        this.handleSteamVRStop();
-      });
+      .subscribe();
 
-    // Detect SteamVR starts
-    this.openvr.status
+    // Log the power changes the core made for SteamVR starting or stopping
+    this.lighthouse.batchPowerCompleted
       .pipe(
-        pairwise(),
-        debounceTime(1000),
         filter(
-          ([oldStatus, newStatus]) =>
-            (oldStatus === 'INACTIVE' || oldStatus === 'INITIALIZING') &&
-            newStatus === 'INITIALIZED'
-        ),
-        throttleTime(5000, asyncScheduler, { leading: true, trailing: false })
+          (event) =>
+            event.groupId === STEAMVR_STOP_GROUP_ID || event.groupId === STEAMVR_START_GROUP_ID
+        )
       )
-      .subscribe(() => {
-        this.handleSteamVRStart();
+      .subscribe((event) => {
+        const changedDevices = event.results.filter((result) => result.success).length;
+        if (!changedDevices) return;
+        this.eventLog.logEvent({
+          type: 'lighthouseSetPowerState',
+          reason: event.groupId === STEAMVR_STOP_GROUP_ID ? 'STEAMVR_STOP' : 'STEAMVR_START',
+          devices: changedDevices > 1 ? 'VARIOUS' : 'SINGLE',
+          state: event.powerState,
+        } as EventLogLighthouseSetPowerState);
       });
 
     // Listen for newly discovered lighthouses
@@ -89,49 +114,39 @@ export class OyasumiVRSteamVRDevicePowerAutomationsService {
       });
   }
 
-  private async handleSteamVRStop() {
-    const applicableDevices = await this.deviceManager.getDevicesForSelection(
+  private async updateSteamVRPowerRules(powerOffState: LighthouseDevicePowerState) {
+    const stopDevices = await this.deviceManager.getDevicesForSelection(
       this.config.turnOffDevicesOnSteamVRStop
     );
-    const applicableLighthouses = applicableDevices.lighthouseDevices.filter(
-      (d) => d.powerState === 'on' || d.powerState === 'booting'
-    );
-    if (applicableLighthouses.length) {
-      this.eventLog.logEvent({
-        type: 'lighthouseSetPowerState',
-        reason: 'STEAMVR_STOP',
-        devices: applicableLighthouses.length > 1 ? 'VARIOUS' : 'SINGLE',
-        state: this.appSettings.settingsSync.lighthousePowerOffState,
-      } as EventLogLighthouseSetPowerState);
-      await Promise.all(
-        applicableLighthouses.map((lighthouse) =>
-          this.lighthouse.setPowerState(
-            lighthouse,
-            this.appSettings.settingsSync.lighthousePowerOffState
-          )
-        )
-      );
-    }
-  }
-
-  private async handleSteamVRStart() {
-    const applicableDevices = await this.deviceManager.getDevicesForSelection(
+    const startDevices = await this.deviceManager.getDevicesForSelection(
       this.config.turnOnDevicesOnSteamVRStart
     );
-    const applicableLighthouses = applicableDevices.lighthouseDevices.filter(
-      (d) => d.powerState === 'sleep' || d.powerState === 'standby'
+    const groups: LighthouseGroup[] = [
+      {
+        id: STEAMVR_STOP_GROUP_ID,
+        name: 'Turn off when SteamVR stops',
+        deviceIds: stopDevices.lighthouseDevices.map((d) => d.id),
+      },
+      {
+        id: STEAMVR_START_GROUP_ID,
+        name: 'Turn on when SteamVR starts',
+        deviceIds: startDevices.lighthouseDevices.map((d) => d.id),
+      },
+    ];
+    const rules: LighthouseOpenVRPowerRule[] = [
+      { groupId: STEAMVR_STOP_GROUP_ID, onStart: null, onStop: powerOffState, v1Timeout: null },
+      { groupId: STEAMVR_START_GROUP_ID, onStart: 'on', onStop: null, v1Timeout: null },
+    ];
+    // Leave any other groups and rules alone
+    const ownGroupIds = groups.map((g) => g.id);
+    const otherGroups = (await this.lighthouse.getGroups()).filter(
+      (g) => !ownGroupIds.includes(g.id)
     );
-    if (applicableLighthouses.length) {
-      this.eventLog.logEvent({
-        type: 'lighthouseSetPowerState',
-        reason: 'STEAMVR_START',
-        devices: applicableLighthouses.length > 1 ? 'VARIOUS' : 'SINGLE',
-        state: 'on',
-      } as EventLogLighthouseSetPowerState);
-      await Promise.all(
-        applicableLighthouses.map((lighthouse) => this.lighthouse.setPowerState(lighthouse, 'on'))
-      );
-    }
+    const otherRules = (await this.lighthouse.getOpenVRPowerRules()).filter(
+      (r) => !ownGroupIds.includes(r.groupId)
+    );
+    await this.lighthouse.setGroups([...otherGroups, ...groups]);
+    await this.lighthouse.setOpenVRPowerRules([...otherRules, ...rules]);
   }
 
   // This handler turns newly discovered lighthouses on or off based on the SteamVR status, if they are configured to be affected.
@@ -1,6 +1,7 @@
 import { Injectable } from '@angular/core';
 import { AutomationConfigService } from '../automation-config.service';
 import { listen } from '@tauri-apps/api/event';
+import { invoke } from '@tauri-apps/api/core';
 import {
   AUTOMATION_CONFIGS_DEFAULT,
   SleepModeEnableForSleepDetectorAutomationConfig,
@@ -16,7 +17,12 @@ import {
   skip,
 } from 'rxjs';
 import { SleepService } from '../sleep.service';
-import { SleepDetectorStateReport } from '../../models/events';
+import {
+  GestureDetected,
+  SleepBaseline,
+  SleepClassification,
+  SleepDetectorStateReport,
+} from '../../models/events';
 import { NotificationService } from '../notification.service';
 import { TranslateService } from '@ngx-translate/core';
 import { EventLogService } from '../event-log.service';
@@ -31,7 +37,7 @@ export type SleepDetectorStateReportHandlingResult =
   | 'SLEEP_MODE_ALREADY_ENABLED'
   | 'NOT_IN_ACTIVATION_WINDOW'
   | 'NOT_RUNNING_LONG_ENOUGH'
-  | 'TOO_MUCH_MOVEMENT'
+  | 'NOT_ASLEEP'
   | 'RATE_LIMITED'
   | 'SLEEP_MODE_DISABLED_TOO_RECENTLY'
   | 'POSE_UPRIGHT_TOO_RECENTLY'
@@ -101,10 +107,26 @@ export class SleepModeForSleepDetectorAutomationService {
         const result = await this.handleStateReportForEnable(event.payload);
         this._lastStateReportHandlingResult.next(result);
       });
-      // Dismiss sleep check for head shake
-      await listen<{ gesture: string }>('GESTURE_DETECTED', (event) => {
-        if (event.payload.gesture !== 'head_shake') return;
-        this.dismissSleepCheck();
+      // Act on the user falling asleep straight away, rather than waiting for the next state report
+      await listen<SleepClassification>('SLEEP_DETECTOR_STATE_CHANGED', async (event) => {
+        const report = this._lastStateReport.value;
+        if (!report || event.payload.state !== 'ASLEEP') return;
+        const result = await this.handleStateReportForEnable({
+          ...report,
+          classification: event.payload,
+        });
+        this._lastStateReportHandlingResult.next(result);
+      });
+      // Dismiss sleep check for head shake, confirm it for head nod
+      await listen<GestureDetected>('GESTURE_DETECTED', (event) => {
+        switch (event.payload.gesture) {
+          case 'head_shake':
+            this.dismissSleepCheck();
+            break;
+          case 'head_nod':
+            this.confirmSleepCheck();
+            break;
+        }
       });
       // Detect controller button presence indication
       this.openvrInputService.state
@@ -147,6 +169,27 @@ export class SleepModeForSleepDetectorAutomationService {
     }
   }
 
+  async confirmSleepCheck() {
+    if (this.sleepEnableTimeoutId) {
+      clearTimeout(this.sleepEnableTimeoutId);
+      this.sleepEnableTimeoutId = null;
+      if (this.sleepCheckNotificationId) {
+        await this.notifications.clearNotification(this.sleepCheckNotificationId);
+        this.sleepCheckNotificationId = null;
+      }
+      await this.enableSleepModeAfterSleepCheck();
+    }
+  }
+
+  private async enableSleepModeAfterSleepCheck() {
+    this._lastStateReportHandlingResult.next('SLEEP_CHECK_USER_ASLEEP');
+    await this.sleep.enableSleepMode({
+      type: 'AUTOMATION',
+      automation: 'SLEEP_MODE_ENABLE_FOR_SLEEP_DETECTOR',
+    });
+    this._lastStateReportHandlingResult.next('SLEEP_MODE_ENABLED');
+  }
+
   async handleStateReportForEnable(
     report: SleepDetectorStateReport
   ): Promise<SleepDetectorStateReportHandlingResult> {
@@ -166,10 +209,8 @@ export class SleepModeForSleepDetectorAutomationService {
     // Stop here if the sleep detection has been running for less than the detection window
     if (Date.now() - report.startTime < 1000 * 60 * this.enableConfig.detectionWindowMinutes)
       return 'NOT_RUNNING_LONG_ENOUGH';
-    // Stop here if the positional movement was too high in the past 15 minutes
-    const threshold =
-      this.enableConfig.calibrationValue * this.calibrationFactors[this.enableConfig.sensitivity];
-    if (report.distanceInLast15Minutes > threshold) return 'TOO_MUCH_MOVEMENT';
+    // Stop here if the core does not classify the user as asleep
+    if (report.classification.state !== 'ASLEEP') return 'NOT_ASLEEP';
     // Stop here if the last time we tried enabling was less than the detection window
     if (Date.now() - this.lastEnableAttempt < 1000 * 60 * this.enableConfig.detectionWindowMinutes)
       return 'RATE_LIMITED';
@@ -204,12 +245,7 @@ export class SleepModeForSleepDetectorAutomationService {
       if (this.sleepEnableTimeoutId) return 'SLEEP_CHECK_ALREADY_IN_PROGRESS';
       this.sleepEnableTimeoutId = setTimeout(async () => {
         this.sleepEnableTimeoutId = null;
-        this._lastStateReportHandlingResult.next('SLEEP_CHECK_USER_ASLEEP');
-        await this.sleep.enableSleepMode({
-          type: 'AUTOMATION',
-          automation: 'SLEEP_MODE_ENABLE_FOR_SLEEP_DETECTOR',
-        });
-        this._lastStateReportHandlingResult.next('SLEEP_MODE_ENABLED');
+        await this.enableSleepModeAfterSleepCheck();
       }, 20000) as unknown as number;
       return 'SLEEP_CHECK';
     }
@@ -237,12 +273,23 @@ export class SleepModeForSleepDetectorAutomationService {
 
   public async calibrate(): Promise<number> {
     let distanceInLast10Seconds = -1;
+    let rotationInLast10Seconds = -1;
     if (this._lastStateReport.value) {
       if (Date.now() - this._lastStateReport.value.startTime > 1000 * 10) {
         distanceInLast10Seconds = this._lastStateReport.value.distanceInLast10Seconds;
+        rotationInLast10Seconds = this._lastStateReport.value.rotationInLast10Seconds;
       }
     }
     if (distanceInLast10Seconds > 0) {
+      // The user was lying still, so use their movement as the resting baseline of the sleep classifier
+      const baseline = await invoke<SleepBaseline>('sleep_detector_get_baseline');
+      await invoke('sleep_detector_set_baseline', {
+        baseline: {
+          ...baseline,
+          restingDistancePerSecond: distanceInLast10Seconds / 10,
+          restingRotationPerSecond: rotationInLast10Seconds / 10,
+        },
+      });
       await this.automationConfig.updateAutomationConfig<SleepModeEnableForSleepDetectorAutomationConfig>(
         'SLEEP_MODE_ENABLE_FOR_SLEEP_DETECTOR',
         {
@@ -5,12 +5,23 @@ import * as moment from 'moment';
 import { listen } from '@tauri-apps/api/event';
 import { invoke } from '@tauri-apps/api/core';
 
-interface RawLogEvent {
+type RawLogEvent = {
   time: number;
-  event: string;
-  data: string;
   initialLoad: boolean;
-}
+} & (
+  | { event: 'InitialLoadComplete' }
+  | { event: 'OnPlayerJoined'; data: { displayName: string; userId: string | null } }
+  | { event: 'OnPlayerLeft'; data: { displayName: string; userId: string | null } }
+  | { event: 'OnLocationChange'; data: { instanceId: string } }
+  | { event: 'OnWorldEntered'; data: { worldName: string } }
+  | { event: 'OnAvatarChanged'; data: { displayName: string; avatarName: string } }
+  | { event: 'OnVideoPlayerUrl'; data: { url: string } }
+  | { event: 'OnPortalDropped' }
+  | { event: 'OnVoteKickInitiated'; data: { displayName: string } }
+  | { event: 'OnModerationNotice'; data: { message: string } }
+  | { event: 'OnUserAuthenticated'; data: { displayName: string; userId: string | null } }
+  | { event: 'OnApplicationQuit' }
+);
 
 @Injectable({
   providedIn: 'root',
@@ -33,62 +44,80 @@ export class VRChatLogService {
     await listen<string | null>('VRC_LOG_CURRENT_FILE', (event) =>
       this._logPath.next(event.payload)
     );
-    await invoke('init_vrc_log_watcher');
+    await invoke('init_vrc_log_watcher', { resumeFromLastPosition: true });
   }
 
   private handleLogEvent(event: RawLogEvent) {
+    const timestamp = moment.unix(event.time).toDate();
+    const initialLoad = event.initialLoad;
     switch (event.event) {
       case 'InitialLoadComplete':
         this._initialLoadComplete.next(true);
         break;
-      case 'OnPlayerJoined': {
-        const { displayName, userId } = this.parseNameAndId(event.data);
+      case 'OnPlayerJoined':
+      case 'OnPlayerLeft':
+      case 'OnUserAuthenticated':
         this._logEvents.next({
-          type: 'OnPlayerJoined',
-          timestamp: moment.unix(event.time).toDate(),
-          initialLoad: event.initialLoad,
-          displayName,
-          userId,
+          type: event.event,
+          timestamp,
+          initialLoad,
+          displayName: event.data.displayName,
+          userId: event.data.userId ?? '',
         });
         break;
-      }
-      case 'OnPlayerLeft': {
-        const { displayName, userId } = this.parseNameAndId(event.data);
+      case 'OnLocationChange':
         this._logEvents.next({
-          type: 'OnPlayerLeft',
-          timestamp: moment.unix(event.time).toDate(),
-          initialLoad: event.initialLoad,
-          displayName,
-          userId,
+          type: 'OnLocationChange',
+          timestamp,
+          initialLoad,
+          instanceId: event.data.instanceId,
         });
         break;
-      }
-      case 'OnLocationChange':
+      case 'OnWorldEntered':
         this._logEvents.next({
-          type: 'OnLocationChange',
-          timestamp: moment.unix(event.time).toDate(),
-          instanceId: event.data,
-          initialLoad: event.initialLoad,
+          type: 'OnWorldEntered',
+          timestamp,
+          initialLoad,
+          worldName: event.data.worldName,
         });
-    }
-  }
-
-  private parseNameAndId(data: string): { displayName: string; userId: string } {
-    const parts = data.split(' ');
-    if (
-      parts.length > 1 &&
-      parts[parts.length - 1].startsWith('(') &&
-      parts[parts.length - 1].endsWith(')')
-    ) {
-      return {
-        displayName: parts.slice(0, parts.length - 1).join(' '),
-        userId: parts[parts.length - 1].slice(1, -1),
-      };
-    } else {
-      return {
-        displayName: data,
-        userId: '',
-      };
+        break;
+      case 'OnAvatarChanged':
+        this._logEvents.next({
+          type: 'OnAvatarChanged',
+          timestamp,
+          initialLoad,
+          displayName: event.data.displayName,
+          avatarName: event.data.avatarName,
+        });
+        break;
+      case 'OnVideoPlayerUrl':
+        this._logEvents.next({
+          type: 'OnVideoPlayerUrl',
+          timestamp,
+          initialLoad,
+          url: event.data.url,
+        });
+        break;
+      case 'OnVoteKickInitiated':
+        this._logEvents.next({
+          type: 'OnVoteKickInitiated',
+          timestamp,
+          initialLoad,
+          displayName: event.data.displayName,
+        });
+        break;
+      case 'OnModerationNotice':
+        this._logEvents.next({
+          type: 'OnModerationNotice',
+          timestamp,
+          initialLoad,
+          message: event.data.message,
+        });
+        break;
+      case 'OnPortalDropped':
+      case 'OnApplicationQuit':
+        this._logEvents.next({ type: event.event, timestamp, initialLoad });
+        break;
     }
   }
 }
@@ -18,7 +18,8 @@ export function sleep(ms: number) {
 export async function pRetry<T>(
   promise: () => Promise<T>,
   retries: number,
-  retryDelay: number
+  retryDelay: number,
+  shouldRetry: (error: any) => boolean = () => true
 ): Promise<T> {
   let attempts = 0;
   let error: any;
@@ -29,6 +30,7 @@ export async function pRetry<T>(
     } catch (err) {
       error = err;
       attempts++;
+      if (!shouldRetry(err)) break;
 
       if (attempts <= retries) {
         await new Promise((resolve) => setTimeout(resolve, retryDelay));
@@ -9,10 +9,14 @@ import {
 } from 'src-ui/app/models/settings';
 import { takeUntilDestroyed } from '@angular/core/rxjs-interop';
 import { SelectBoxItem } from 'src-ui/app/components/select-box/select-box.component';
-import { LighthouseDevicePowerState } from 'src-ui/app/models/lighthouse-device';
+import {
+  LighthouseDevicePowerState,
+  LighthouseMonitorConfig,
+} from 'src-ui/app/models/lighthouse-device';
 import { StartWithSteamVRHowToModalComponent } from './start-with-steamvr-how-to-modal/start-with-steamvr-how-to-modal.component';
 import { TelemetryService } from 'src-ui/app/services/telemetry.service';
 import { LighthouseConsoleService } from 'src-ui/app/services/lighthouse-console.service';
+import { LighthouseService } from 'src-ui/app/services/lighthouse.service';
 import { AppSettingsService } from 'src-ui/app/services/app-settings.service';
 import { ModalService } from 'src-ui/app/services/modal.service';
 
@@ -55,6 +59,26 @@ export class SettingsGeneralViewComponent implements OnInit {
     },
   ];
   lighthousePowerOffModeOption: SelectBoxItem | undefined;
+  lighthouseMonitorConfig?: LighthouseMonitorConfig;
+  lighthouseUnreachableTimeoutOptions: SelectBoxItem[] = [
+    {
+      id: '30000',
+      label: 'settings.general.lighthousePowerControl.unreachableTimeout.options.30000',
+    },
+    {
+      id: '60000',
+      label: 'settings.general.lighthousePowerControl.unreachableTimeout.options.60000',
+    },
+    {
+      id: '120000',
+      label: 'settings.general.lighthousePowerControl.unreachableTimeout.options.120000',
+    },
+    {
+      id: '300000',
+      label: 'settings.general.lighthousePowerControl.unreachableTimeout.options.300000',
+    },
+  ];
+  lighthouseUnreachableTimeoutOption: SelectBoxItem | undefined;
   sleepModeStartupBehaviourOptions: SelectBoxItem[] = [
     {
       id: 'PERSIST',
@@ -91,13 +115,14 @@ export class SettingsGeneralViewComponent implements OnInit {
 
   constructor(
     private lighthouse: LighthouseConsoleService,
+    private lighthouseService: LighthouseService,
     private telemetry: TelemetryService,
     private modalService: ModalService,
     private destroyRef: DestroyRef,
     private settingsService: AppSettingsService
   ) {}
 
-  ngOnInit(): void {
+  async ngOnInit() {
     this.settingsService.settings
       .pipe(takeUntilDestroyed(this.destroyRef))
       .subscribe((settings) => (this.appSettings = settings));
@@ -127,6 +152,11 @@ export class SettingsGeneralViewComponent implements OnInit {
           (o) => o.id === settings.quitWithSteamVR
         );
       });
+    const monitorConfig = await this.lighthouseService.getMonitorConfig();
+    this.lighthouseMonitorConfig = monitorConfig;
+    this.lighthouseUnreachableTimeoutOption = this.lighthouseUnreachableTimeoutOptions.find(
+      (o) => o.id === monitorConfig.unreachableTimeoutMs.toString()
+    );
   }
 
   setUserLanguage(languageCode: string) {
@@ -198,6 +228,16 @@ export class SettingsGeneralViewComponent implements OnInit {
     });
   }
 
+  async onChangeLighthouseUnreachableTimeout(option: SelectBoxItem | undefined) {
+    if (!option || !this.lighthouseMonitorConfig) return;
+    this.lighthouseMonitorConfig = {
+      ...this.lighthouseMonitorConfig,
+      unreachableTimeoutMs: parseInt(option.id),
+    };
+    this.lighthouseUnreachableTimeoutOption = option;
+    await this.lighthouseService.setMonitorConfig(this.lighthouseMonitorConfig);
+  }
+
   onChangeSleepModeStartupBehaviour(option: SelectBoxItem | undefined) {
     if (!option) return;
     this.settingsService.updateSettings({